@@ -0,0 +1,89 @@
+use std::{fs, path::Path};
+
+use crate::error::{Error, Result};
+use crate::stats::Counts;
+
+/// Load the counts from the baseline file. Returns `None` if the file does not exist
+/// yet, e.g. on the first run.
+pub fn load<P: AsRef<Path>>(baseline_path: P) -> Result<Option<Counts>> {
+    let baseline_path = baseline_path.as_ref();
+    if !baseline_path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(baseline_path).map_err(|source| Error::ReadBaseline {
+        path: baseline_path.to_path_buf(),
+        source,
+    })?;
+    toml::from_str(&content)
+        .map(Some)
+        .map_err(|source| Error::ParseBaseline {
+            path: baseline_path.to_path_buf(),
+            source,
+        })
+}
+
+/// Overwrite the baseline file with the given counts. The counts are written to a
+/// temporary file first and then renamed, so a failed write never corrupts the
+/// existing baseline.
+pub fn save<P: AsRef<Path>>(baseline_path: P, counts: &Counts) -> Result<()> {
+    let baseline_path = baseline_path.as_ref();
+    let mut tmp_path = baseline_path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let write_error = |source| Error::WriteBaseline {
+        path: baseline_path.to_path_buf(),
+        source,
+    };
+    fs::write(&tmp_path, toml::to_string(counts)?).map_err(write_error)?;
+    fs::rename(&tmp_path, baseline_path).map_err(write_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn counts(alice_prs: u64) -> Counts {
+        Counts {
+            prs: HashMap::from([("alice".to_string(), alice_prs)]),
+            pr_reviews: HashMap::from([("bob".to_string(), 3)]),
+            pr_sizes: HashMap::from([("alice".to_string(), [0, 1, 0, 0, 0])]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir =
+            std::env::temp_dir().join(format!("gh-overseer-test-{}-baseline", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let baseline_path = dir.join("baseline.toml");
+        let _ = fs::remove_file(&baseline_path);
+        assert!(load(&baseline_path).unwrap().is_none());
+
+        save(&baseline_path, &counts(1)).unwrap();
+        let baseline = load(&baseline_path).unwrap().unwrap();
+        assert_eq!(baseline.prs, counts(1).prs);
+        assert_eq!(baseline.pr_reviews, counts(1).pr_reviews);
+        assert_eq!(baseline.pr_sizes, counts(1).pr_sizes);
+        assert!(baseline.issues.is_empty());
+        assert!(!dir.join("baseline.toml.tmp").exists());
+
+        // A failed save keeps the existing baseline intact, e.g. the temporary file can't
+        // be written as a directory is in the way.
+        fs::create_dir(dir.join("baseline.toml.tmp")).unwrap();
+        assert!(matches!(
+            save(&baseline_path, &counts(2)),
+            Err(Error::WriteBaseline { .. })
+        ));
+        assert_eq!(load(&baseline_path).unwrap().unwrap().prs, counts(1).prs);
+
+        fs::write(&baseline_path, "prs = 1").unwrap();
+        assert!(matches!(
+            load(&baseline_path),
+            Err(Error::ParseBaseline { .. })
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
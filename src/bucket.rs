@@ -0,0 +1,112 @@
+use chrono::{DateTime, Datelike, Duration, TimeZone, Utc};
+use clap::ValueEnum;
+
+/// The time bucket to break the time range down into.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bucket {
+    /// The 7-day windows from the start time.
+    Week,
+    /// The calendar months, where the first and the last ones might be partial.
+    Month,
+}
+
+/// Split the time range into the consecutive buckets, returning the start and end time of
+/// each bucket. The end time of each bucket is right before the start time of the next
+/// one, so an item on the boundary only falls into one bucket.
+pub fn split(
+    bucket: Bucket,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let mut buckets = Vec::new();
+    let mut bucket_start = start_time;
+    while bucket_start <= end_time {
+        let next_start = match bucket {
+            Bucket::Week => bucket_start + Duration::weeks(1),
+            Bucket::Month => next_month(bucket_start),
+        };
+        if next_start > end_time {
+            buckets.push((bucket_start, end_time));
+            break;
+        }
+        buckets.push((bucket_start, next_start - Duration::nanoseconds(1)));
+        bucket_start = next_start;
+    }
+    buckets
+}
+
+/// Get the start of the next calendar month.
+fn next_month(time: DateTime<Utc>) -> DateTime<Utc> {
+    let (year, month) = if time.month() == 12 {
+        (time.year() + 1, 1)
+    } else {
+        (time.year(), time.month() + 1)
+    };
+    Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0)
+        .single()
+        .expect("the first day of a month should always exist")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    #[test]
+    fn test_split_weeks() {
+        let end_of = |time: &str| self::time(time) - Duration::nanoseconds(1);
+        assert_eq!(
+            split(
+                Bucket::Week,
+                time("2024-01-01T00:00:00Z"),
+                time("2024-01-20T12:00:00Z")
+            ),
+            [
+                (time("2024-01-01T00:00:00Z"), end_of("2024-01-08T00:00:00Z")),
+                (time("2024-01-08T00:00:00Z"), end_of("2024-01-15T00:00:00Z")),
+                // The last bucket is cut off by the end time.
+                (time("2024-01-15T00:00:00Z"), time("2024-01-20T12:00:00Z")),
+            ]
+        );
+        // A time range shorter than a bucket is a single bucket.
+        assert_eq!(
+            split(
+                Bucket::Week,
+                time("2024-01-01T00:00:00Z"),
+                time("2024-01-03T00:00:00Z")
+            ),
+            [(time("2024-01-01T00:00:00Z"), time("2024-01-03T00:00:00Z"))]
+        );
+        assert!(split(
+            Bucket::Week,
+            time("2024-01-03T00:00:00Z"),
+            time("2024-01-01T00:00:00Z")
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_split_months() {
+        let end_of = |time: &str| self::time(time) - Duration::nanoseconds(1);
+        // The first and the last months are partial, across the end of the year.
+        assert_eq!(
+            split(
+                Bucket::Month,
+                time("2023-12-15T08:00:00Z"),
+                time("2024-02-10T00:00:00Z")
+            ),
+            [
+                (time("2023-12-15T08:00:00Z"), end_of("2024-01-01T00:00:00Z")),
+                (time("2024-01-01T00:00:00Z"), end_of("2024-02-01T00:00:00Z")),
+                (time("2024-02-01T00:00:00Z"), time("2024-02-10T00:00:00Z")),
+            ]
+        );
+        assert_eq!(
+            next_month(time("2024-02-29T23:59:59Z")),
+            time("2024-03-01T00:00:00Z")
+        );
+    }
+}
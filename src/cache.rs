@@ -0,0 +1,179 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use octocrab::models;
+use serde::{Deserialize, Serialize};
+
+/// The previously fetched issues, comments and reviews of a repo, so the next run only
+/// needs to fetch the ones updated since the watermark.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepoCache {
+    /// The start time of the first run, the items updated before it are never fetched.
+    start_time: DateTime<Utc>,
+    /// All the items updated before the watermark have been fetched.
+    watermark: DateTime<Utc>,
+    /// The allowed users when the cache is built, it's rebuilt once they change.
+    users: Vec<String>,
+    issues: Vec<models::issues::Issue>,
+    issue_comments: Vec<models::issues::Comment>,
+    pull_request_comments: Vec<models::pulls::Comment>,
+    pull_request_reviews: Vec<models::pulls::Review>,
+}
+
+/// Get the path of the cache file of the repo, e.g. "<cache_dir>/owner__name.json".
+fn cache_path<P: AsRef<Path>>(cache_dir: P, repo: &str) -> PathBuf {
+    cache_dir
+        .as_ref()
+        .join(format!("{}.json", repo.replace('/', "__")))
+}
+
+/// Merge the updated items into the cached ones by their IDs, where the updated ones
+/// replace the cached ones with the same ID.
+fn merge_by_id<T, K: Eq + std::hash::Hash>(cached: &mut Vec<T>, updated: Vec<T>, id: fn(&T) -> K) {
+    let mut positions: HashMap<K, usize> = cached
+        .iter()
+        .enumerate()
+        .map(|(position, item)| (id(item), position))
+        .collect();
+    for item in updated {
+        match positions.get(&id(&item)) {
+            Some(&position) => cached[position] = item,
+            None => {
+                positions.insert(id(&item), cached.len());
+                cached.push(item);
+            }
+        }
+    }
+}
+
+impl RepoCache {
+    /// Create an empty cache, which makes the run fetch everything since the start time.
+    pub fn new(start_time: DateTime<Utc>, users: Vec<String>) -> Self {
+        Self {
+            start_time,
+            watermark: start_time,
+            users: sorted(users),
+            issues: Vec::new(),
+            issue_comments: Vec::new(),
+            pull_request_comments: Vec::new(),
+            pull_request_reviews: Vec::new(),
+        }
+    }
+
+    /// Load the cache of the repo if it's still valid for the given start time and users.
+    /// Returns `None` if there is no cache yet, it's unreadable, or it's invalidated, in
+    /// which case everything should be fetched again.
+    pub fn load<P: AsRef<Path>>(
+        cache_dir: P,
+        repo: &str,
+        start_time: DateTime<Utc>,
+        users: Vec<String>,
+    ) -> Option<Self> {
+        let cache_path = cache_path(cache_dir, repo);
+        if !cache_path.exists() {
+            return None;
+        }
+        let cache: Self = match fs::read_to_string(&cache_path)
+            .map_err(|err| err.to_string())
+            .and_then(|content| serde_json::from_str(&content).map_err(|err| err.to_string()))
+        {
+            Ok(cache) => cache,
+            Err(err) => {
+                warn!(
+                    "failed to load cache '{}', ignoring it: {}",
+                    cache_path.display(),
+                    err
+                );
+                return None;
+            }
+        };
+        if cache.users != sorted(users) {
+            info!("users changed since the cache of '{}', ignoring it", repo);
+            return None;
+        }
+        // The items updated between the start time and the cache start time are missing.
+        if cache.start_time > start_time {
+            info!(
+                "cache of '{}' starts after {}, ignoring it",
+                repo, start_time
+            );
+            return None;
+        }
+        info!("cache of '{}' loaded, watermark: {}", repo, cache.watermark);
+        Some(cache)
+    }
+
+    /// Overwrite the cache file of the repo. The cache is written to a temporary file
+    /// first and then renamed, so a failed write never corrupts the existing cache.
+    pub fn save<P: AsRef<Path>>(&self, cache_dir: P, repo: &str) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(cache_dir.as_ref())?;
+        let cache_path = cache_path(cache_dir, repo);
+        let mut tmp_path = cache_path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, serde_json::to_string(self)?)?;
+        fs::rename(&tmp_path, cache_path)?;
+        Ok(())
+    }
+
+    /// Get the time to fetch the updated items since.
+    pub fn watermark(&self) -> DateTime<Utc> {
+        self.watermark
+    }
+
+    /// Advance the watermark to the time all the items updated before are fetched. It
+    /// never goes back, e.g. after a run with an earlier end time.
+    pub fn advance_watermark(&mut self, watermark: DateTime<Utc>) {
+        self.watermark = self.watermark.max(watermark);
+    }
+
+    /// Merge the updated issues (including PRs) and return all of them.
+    pub fn merge_issues(
+        &mut self,
+        issues: Vec<models::issues::Issue>,
+    ) -> Vec<models::issues::Issue> {
+        merge_by_id(&mut self.issues, issues, |issue| issue.id);
+        self.issues.clone()
+    }
+
+    /// Merge the updated issue comments and return all of them.
+    pub fn merge_issue_comments(
+        &mut self,
+        comments: Vec<models::issues::Comment>,
+    ) -> Vec<models::issues::Comment> {
+        merge_by_id(&mut self.issue_comments, comments, |comment| comment.id);
+        self.issue_comments.clone()
+    }
+
+    /// Merge the updated pull request comments and return all of them.
+    pub fn merge_pull_request_comments(
+        &mut self,
+        comments: Vec<models::pulls::Comment>,
+    ) -> Vec<models::pulls::Comment> {
+        merge_by_id(&mut self.pull_request_comments, comments, |comment| {
+            comment.id
+        });
+        self.pull_request_comments.clone()
+    }
+
+    /// Merge the reviews of the updated pull requests and return all of them. A dismissed
+    /// review keeps its ID, so it replaces the cached approval.
+    pub fn merge_pull_request_reviews(
+        &mut self,
+        reviews: Vec<models::pulls::Review>,
+    ) -> Vec<models::pulls::Review> {
+        merge_by_id(&mut self.pull_request_reviews, reviews, |review| review.id);
+        self.pull_request_reviews.clone()
+    }
+}
+
+fn sorted(mut users: Vec<String>) -> Vec<String> {
+    users.sort();
+    users.dedup();
+    users
+}
@@ -0,0 +1,157 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::stats::Stats;
+
+/// What tells a run apart from the others, only the same run can resume a checkpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunKey {
+    /// The start and end times as given, e.g. "7d", so a run with the relative times can
+    /// still be resumed later, within the time range of the checkpoint.
+    pub time_range: (String, Option<String>),
+    /// The allowed users, sorted.
+    pub users: Vec<String>,
+    /// The repos to fetch, in the configured order.
+    pub repos: Vec<String>,
+    /// The time bucket to break the time range down into, if any.
+    pub bucket: Option<String>,
+}
+
+/// The stats of the repos fetched so far by a run, so a run dying halfway, e.g. by OOM,
+/// can be resumed without fetching them again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    key: RunKey,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    /// The stats and the bucket stats of each fetched repo.
+    repos: BTreeMap<String, (Stats, Vec<Stats>)>,
+}
+
+/// The view of a checkpoint to save, without the repos failed to be fetched.
+#[derive(Serialize)]
+struct SavedCheckpoint<'a> {
+    key: &'a RunKey,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    repos: BTreeMap<&'a String, &'a (Stats, Vec<Stats>)>,
+}
+
+/// Get the default path of the checkpoint file in the temporary directory.
+pub fn default_path() -> PathBuf {
+    std::env::temp_dir().join("gh-overseer.checkpoint.json")
+}
+
+impl Checkpoint {
+    /// Create an empty checkpoint of the run with the resolved time range.
+    pub fn new(key: RunKey, start_time: DateTime<Utc>, end_time: DateTime<Utc>) -> Self {
+        Self {
+            key,
+            start_time,
+            end_time,
+            repos: BTreeMap::new(),
+        }
+    }
+
+    /// Load the checkpoint from the file. Returns `None` if the file does not exist, e.g.
+    /// the last run has completed.
+    pub fn load<P: AsRef<Path>>(checkpoint_path: P) -> Result<Option<Self>, Box<dyn Error>> {
+        let checkpoint_path = checkpoint_path.as_ref();
+        if !checkpoint_path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&fs::read_to_string(
+            checkpoint_path,
+        )?)?))
+    }
+
+    /// Overwrite the checkpoint file with the repos fetched so far, except the ones with
+    /// any listing failed, which should be fetched again. The checkpoint is written to a
+    /// temporary file first and then renamed, so a failed write never corrupts the
+    /// existing checkpoint.
+    pub fn save<P: AsRef<Path>>(&self, checkpoint_path: P) -> Result<(), Box<dyn Error>> {
+        let checkpoint_path = checkpoint_path.as_ref();
+        let saved = SavedCheckpoint {
+            key: &self.key,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            repos: self
+                .repos
+                .iter()
+                .filter(|(repo, (stats, _))| !stats.hard_failed_repos().contains(*repo))
+                .collect(),
+        };
+        let mut tmp_path = checkpoint_path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, serde_json::to_string(&saved)?)?;
+        fs::rename(&tmp_path, checkpoint_path)?;
+        Ok(())
+    }
+
+    /// Get the key of the run of the checkpoint.
+    pub fn key(&self) -> &RunKey {
+        &self.key
+    }
+
+    /// Get the time range resolved by the run of the checkpoint.
+    pub fn time_range(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        (self.start_time, self.end_time)
+    }
+
+    /// Rebuild the loaded stats with the config, which is not saved in the checkpoint.
+    pub fn with_config(self, config: &Config) -> Self {
+        Self {
+            repos: self
+                .repos
+                .into_iter()
+                .map(|(repo, (stats, bucket_stats))| {
+                    (
+                        repo,
+                        (
+                            stats.with_config(config),
+                            bucket_stats
+                                .into_iter()
+                                .map(|stats| stats.with_config(config))
+                                .collect(),
+                        ),
+                    )
+                })
+                .collect(),
+            ..self
+        }
+    }
+
+    /// Whether the repo has been fetched.
+    pub fn contains(&self, repo: &str) -> bool {
+        self.repos.contains_key(repo)
+    }
+
+    /// Get the number of the repos fetched.
+    pub fn len(&self) -> usize {
+        self.repos.len()
+    }
+
+    /// Whether no repo has been fetched yet.
+    pub fn is_empty(&self) -> bool {
+        self.repos.is_empty()
+    }
+
+    /// Record the stats and the bucket stats of a fetched repo.
+    pub fn record(&mut self, stats: Stats, bucket_stats: Vec<Stats>) {
+        self.repos
+            .insert(stats.repo().to_string(), (stats, bucket_stats));
+    }
+
+    /// Take the stats and the bucket stats of all the fetched repos.
+    pub fn into_repos(self) -> BTreeMap<String, (Stats, Vec<Stats>)> {
+        self.repos
+    }
+}
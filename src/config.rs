@@ -1,66 +1,916 @@
-use std::{error::Error, fs, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    io::{self, Read},
+    path::Path,
+    time::Duration,
+};
 
+use chrono::{DateTime, Datelike, NaiveDate, Utc, Weekday};
+use clap::ValueEnum;
+use regex::Regex;
 use serde::Deserialize;
 
+use crate::error::Error;
+use crate::fetcher::MAX_PAGE_SIZE;
+use crate::notifier::Platform;
+use crate::stats::Metric;
+
 const FEISHU_BOT_WEBHOOK_URL_ENV: &str = "GH_OVERSEER_FEISHU_BOT_WEBHOOK_URL";
 const GITHUB_PERSONAL_TOKEN_ENV: &str = "GH_OVERSEER_GITHUB_PERSONAL_TOKEN";
+const GITHUB_PERSONAL_TOKEN_FILE_ENV: &str = "GH_OVERSEER_GITHUB_PERSONAL_TOKEN_FILE";
+const GITHUB_BASE_URL_ENV: &str = "GH_OVERSEER_GITHUB_BASE_URL";
+
+/// The format of the config file.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConfigFormat {
+    #[default]
+    Toml,
+    Json,
+}
+
+/// The problems found in the config by `Config::validate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The repo is not in the `owner/name` form.
+    InvalidRepo(String),
+    /// No users to count the stats of.
+    NoUsers,
+    /// No comments to be considered as a LGTM approval.
+    NoLgtmComments,
+    /// The LGTM pattern is not a valid regex, with the reason.
+    InvalidLgtmPattern(String, String),
+    /// The GitHub base URL is not a valid HTTP(S) URL.
+    InvalidBaseUrl(String),
+    /// Only some of the GitHub App fields are set.
+    IncompleteGithubApp,
+    /// The business hours are not a valid range of hours within a day.
+    InvalidBusinessHours(u32, u32),
+    /// More than one problem found.
+    Multiple(Vec<ConfigError>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::InvalidRepo(repo) => {
+                write!(f, "invalid repo '{}', should be 'owner/name'", repo)
+            }
+            ConfigError::NoUsers => write!(f, "no users configured in `review.users`"),
+            ConfigError::NoLgtmComments => {
+                write!(
+                    f,
+                    "no LGTM comments configured in `review.lgtm_comments` or `review.lgtm_patterns`"
+                )
+            }
+            ConfigError::InvalidLgtmPattern(pattern, reason) => {
+                write!(f, "invalid LGTM pattern '{}': {}", pattern, reason)
+            }
+            ConfigError::InvalidBaseUrl(url) => {
+                write!(
+                    f,
+                    "invalid github base url '{}', should be a HTTP(S) URL",
+                    url
+                )
+            }
+            ConfigError::IncompleteGithubApp => {
+                write!(
+                    f,
+                    "incomplete github app, set all of `access.github_app_id`, \
+                     `access.github_app_installation_id` and `access.github_app_private_key_file`"
+                )
+            }
+            ConfigError::InvalidBusinessHours(start, end) => {
+                write!(
+                    f,
+                    "invalid business hours [{}, {}], should be [start, end] with 0 <= start < end <= 24",
+                    start, end
+                )
+            }
+            ConfigError::Multiple(errors) => {
+                let errors: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+                write!(f, "{}", errors.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// The labels filtering the issues and PRs, along with their comments, reviews and
+/// events, e.g. to only count the activities on the issues labeled "needs-review".
+#[derive(Debug, Clone, Default)]
+pub struct LabelFilter {
+    include_labels: HashSet<String>,
+    exclude_labels: HashSet<String>,
+}
+
+impl LabelFilter {
+    /// Whether nothing is filtered by the labels.
+    pub fn is_empty(&self) -> bool {
+        self.include_labels.is_empty() && self.exclude_labels.is_empty()
+    }
+
+    /// Whether the issue with the labels is counted, i.e. it has any of the included
+    /// labels if any, and none of the excluded labels.
+    pub fn matches<'a>(&self, labels: impl IntoIterator<Item = &'a str>) -> bool {
+        let labels: Vec<&str> = labels.into_iter().collect();
+        (self.include_labels.is_empty()
+            || labels
+                .iter()
+                .any(|label| self.include_labels.contains(*label)))
+            && !labels
+                .iter()
+                .any(|label| self.exclude_labels.contains(*label))
+    }
+}
+
+/// The GitHub App installation to authenticate as, with the path of the PEM private key
+/// of the app.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GithubApp {
+    pub app_id: u64,
+    pub installation_id: u64,
+    pub private_key_file: String,
+}
+
+/// The business hours to measure the time to first response in, i.e. the hours from the
+/// start hour to the end hour of each weekday other than the holidays, so a PR created
+/// on Friday evening and responded to on Monday morning only waits for the business hours
+/// in between. The day boundaries, the hours and the holidays are all in UTC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BusinessHours {
+    start_hour: u32,
+    end_hour: u32,
+    holidays: HashSet<NaiveDate>,
+}
+
+impl BusinessHours {
+    /// Get the business time elapsed from the start time to the end time, zero if the end
+    /// time is not after the start time.
+    pub fn duration(&self, start_time: DateTime<Utc>, end_time: DateTime<Utc>) -> chrono::Duration {
+        let mut duration = chrono::Duration::zero();
+        let mut day = start_time.date_naive();
+        while day <= end_time.date_naive() {
+            if !matches!(day.weekday(), Weekday::Sat | Weekday::Sun)
+                && !self.holidays.contains(&day)
+            {
+                // The end hour may be 24, i.e. the midnight of the next day.
+                let midnight = day.and_hms_opt(0, 0, 0).unwrap().and_utc();
+                let hour = |hour: u32| midnight + chrono::Duration::hours(i64::from(hour));
+                let start = hour(self.start_hour).max(start_time);
+                let end = hour(self.end_hour).min(end_time);
+                if end > start {
+                    duration += end - start;
+                }
+            }
+            day += chrono::Duration::days(1);
+        }
+        duration
+    }
+}
+
+/// The users excluded from the stats, even if they are in the allowed users.
+#[derive(Debug, Clone, Default)]
+pub struct UserExclusion {
+    exclude_bots: bool,
+    exclude_users: HashSet<String>,
+}
+
+impl UserExclusion {
+    /// Whether the user is excluded, i.e. it's explicitly excluded or it's a bot account
+    /// while the bots are excluded. The logins of the GitHub App bots all end with "[bot]",
+    /// e.g. "dependabot[bot]", while the bots running on a regular account should be
+    /// excluded explicitly.
+    pub fn excludes(&self, user: &str) -> bool {
+        self.exclude_users.contains(user) || (self.exclude_bots && user.ends_with("[bot]"))
+    }
+}
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
+    #[serde(default)]
     access: Access,
     review: Review,
+    #[serde(default)]
+    report: Report,
+    #[serde(default)]
+    fetch: Fetch,
+    #[serde(default)]
+    scoring: HashMap<Metric, f64>,
+    #[serde(default)]
+    notifiers: Vec<NotifierConfig>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// The access fields, each of which falls back to its env var if absent, so the config
+/// can be free of any secret.
+#[derive(Debug, Clone, Default, Deserialize)]
 struct Access {
-    // TODO: support send the stats result to the Feishu/Lark bot.
-    feishu_bot_webhook_url: String,
-    github_personal_token: String,
+    feishu_bot_webhook_url: Option<String>,
+    github_personal_token: Option<String>,
+    github_personal_token_file: Option<String>,
+    github_base_url: Option<String>,
+    github_app_id: Option<u64>,
+    github_app_installation_id: Option<u64>,
+    github_app_private_key_file: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 struct Review {
+    #[serde(default)]
     users: Vec<String>,
+    #[serde(default)]
     repos: Vec<String>,
+    #[serde(default)]
+    orgs: Vec<String>,
+    #[serde(default)]
     lgtm_comments: Vec<String>,
+    #[serde(default)]
+    lgtm_patterns: Vec<String>,
+    #[serde(default)]
+    approval_labels: Vec<String>,
+    #[serde(default)]
+    include_labels: Vec<String>,
+    #[serde(default)]
+    exclude_labels: Vec<String>,
+    #[serde(default)]
+    count_reopens: bool,
+    #[serde(default)]
+    count_closes: bool,
+    #[serde(default)]
+    count_labels: bool,
+    #[serde(default)]
+    count_reactions: bool,
+    #[serde(default)]
+    exclude_draft_prs: bool,
+    #[serde(default)]
+    credit_coauthors: bool,
+    #[serde(default)]
+    collapse_review_threads: bool,
+    #[serde(default)]
+    exclude_bots: bool,
+    #[serde(default)]
+    exclude_users: Vec<String>,
+    #[serde(default)]
+    track_all_users: bool,
+    #[serde(default)]
+    min_comment_length: usize,
+    #[serde(default = "default_pr_size_thresholds")]
+    pr_size_thresholds: [u64; 4],
+    #[serde(default)]
+    weight_lgtm_by_size: bool,
+    #[serde(default)]
+    business_hours: Option<[u32; 2]>,
+    #[serde(default)]
+    holidays: Vec<NaiveDate>,
+    #[serde(default)]
+    teams: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    user_aliases: HashMap<String, String>,
+    #[serde(default)]
+    user_display_names: HashMap<String, String>,
+    #[serde(default)]
+    repo_overrides: Vec<RepoOverride>,
+}
+
+/// The users of a repo replacing the global `review.users`, e.g. for a repo owned by
+/// another team.
+#[derive(Debug, Clone, Deserialize)]
+struct RepoOverride {
+    repo: String,
+    users: Vec<String>,
+}
+
+/// A notifier sending the stats to the webhook of a chat platform.
+#[derive(Debug, Clone, Deserialize)]
+struct NotifierConfig {
+    platform: Platform,
+    webhook_url: String,
+    // The Feishu open ID of each login to @-mention in the card, only for Feishu.
+    #[serde(default)]
+    feishu_open_ids: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct Report {
+    baseline: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Fetch {
+    #[serde(default = "default_max_retries")]
+    max_retries: u32,
+    #[serde(default = "default_retry_base_delay_ms")]
+    retry_base_delay_ms: u64,
+    #[serde(default = "default_max_concurrency")]
+    max_concurrency: usize,
+    #[serde(default = "default_issue_comments_concurrency")]
+    issue_comments_concurrency: usize,
+    #[serde(default = "default_request_timeout_secs")]
+    request_timeout_secs: u64,
+    #[serde(default = "default_page_size")]
+    page_size: u32,
+}
+
+impl Default for Fetch {
+    fn default() -> Self {
+        Self {
+            max_retries: default_max_retries(),
+            retry_base_delay_ms: default_retry_base_delay_ms(),
+            max_concurrency: default_max_concurrency(),
+            issue_comments_concurrency: default_issue_comments_concurrency(),
+            request_timeout_secs: default_request_timeout_secs(),
+            page_size: default_page_size(),
+        }
+    }
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_retry_base_delay_ms() -> u64 {
+    1000
+}
+
+fn default_max_concurrency() -> usize {
+    4
+}
+
+fn default_issue_comments_concurrency() -> usize {
+    8
+}
+
+fn default_request_timeout_secs() -> u64 {
+    30
+}
+
+fn default_page_size() -> u32 {
+    u32::from(MAX_PAGE_SIZE)
+}
+
+/// Get the value of the env var, `None` if it's unset or empty.
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// Whether the repo is in the `owner/name` form.
+fn is_valid_repo(repo: &str) -> bool {
+    match repo.split_once('/') {
+        Some((owner, name)) => {
+            !owner.is_empty()
+                && !name.is_empty()
+                && !name.contains('/')
+                && !repo.contains(char::is_whitespace)
+        }
+        None => false,
+    }
+}
+
+/// Whether the URL is a valid HTTP(S) URL, e.g. "https://github.example.com/api/v3/".
+fn is_valid_base_url(url: &str) -> bool {
+    reqwest::Url::parse(url)
+        .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.host().is_some())
+}
+
+/// The lines changed thresholds between the XS/S/M/L/XL PR size buckets, e.g. a PR with
+/// less than 10 lines changed is XS and one with at least 500 lines changed is XL.
+fn default_pr_size_thresholds() -> [u64; 4] {
+    [10, 30, 100, 500]
 }
 
 impl Config {
-    pub fn load<P: AsRef<Path>>(config_path: P) -> Result<Self, Box<dyn Error>> {
-        toml::from_str(
-            fs::read_to_string(config_path)
-                .expect("failed to load config file")
-                .as_str(),
-        )
-        .map_err(|e| e.into())
+    pub fn load<P: AsRef<Path>>(config_path: P) -> Result<Self, Error> {
+        Self::load_as(config_path, ConfigFormat::Toml)
     }
 
-    pub fn feishu_bot_webhook_url(&self) -> String {
-        if let Ok(url) = std::env::var(FEISHU_BOT_WEBHOOK_URL_ENV) {
-            url
-        } else {
-            self.access.feishu_bot_webhook_url.clone()
+    /// Load the config file in the given format, where the path "-" means the stdin, e.g.
+    /// to pipe in a config generated in CI.
+    pub fn load_as<P: AsRef<Path>>(config_path: P, format: ConfigFormat) -> Result<Self, Error> {
+        let path = config_path.as_ref();
+        if path == Path::new("-") {
+            return Self::from_reader(io::stdin().lock(), format);
+        }
+        let file = fs::File::open(path).map_err(|source| Error::ReadConfig {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::read(file, path, format)
+    }
+
+    /// Read the config in the given format from the reader, whose errors are reported
+    /// with the path "-".
+    pub fn from_reader<R: Read>(reader: R, format: ConfigFormat) -> Result<Self, Error> {
+        Self::read(reader, Path::new("-"), format)
+    }
+
+    fn read<R: Read>(mut reader: R, path: &Path, format: ConfigFormat) -> Result<Self, Error> {
+        let path = path.to_path_buf();
+        let mut content = String::new();
+        if let Err(source) = reader.read_to_string(&mut content) {
+            return Err(Error::ReadConfig { path, source });
+        }
+        match format {
+            ConfigFormat::Toml => {
+                toml::from_str(&content).map_err(|source| Error::ParseConfig { path, source })
+            }
+            ConfigFormat::Json => serde_json::from_str(&content)
+                .map_err(|source| Error::ParseJsonConfig { path, source }),
         }
     }
 
-    pub fn github_personal_token(&self) -> String {
-        if let Ok(token) = std::env::var(GITHUB_PERSONAL_TOKEN_ENV) {
-            token
+    /// Check that the repos are all in the `owner/name` form, the users and the LGTM
+    /// comments are not empty, and the GitHub base URL and the business hours are valid
+    /// if set. All the problems are reported at once.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut errors: Vec<ConfigError> = self
+            .review
+            .repos
+            .iter()
+            .chain(
+                self.review
+                    .repo_overrides
+                    .iter()
+                    .map(|repo_override| &repo_override.repo),
+            )
+            .filter(|repo| !is_valid_repo(repo))
+            .map(|repo| ConfigError::InvalidRepo(repo.clone()))
+            .collect();
+        if self.review.users.is_empty() && !self.review.track_all_users {
+            errors.push(ConfigError::NoUsers);
+        }
+        if self.review.lgtm_comments.is_empty() && self.review.lgtm_patterns.is_empty() {
+            errors.push(ConfigError::NoLgtmComments);
+        }
+        for pattern in &self.review.lgtm_patterns {
+            if let Err(err) = Regex::new(pattern) {
+                errors.push(ConfigError::InvalidLgtmPattern(
+                    pattern.clone(),
+                    err.to_string(),
+                ));
+            }
+        }
+        if let Some(url) = self.github_base_url() {
+            if !is_valid_base_url(&url) {
+                errors.push(ConfigError::InvalidBaseUrl(url));
+            }
+        }
+        let github_app_fields = [
+            self.access.github_app_id.is_some(),
+            self.access.github_app_installation_id.is_some(),
+            self.access.github_app_private_key_file.is_some(),
+        ];
+        if github_app_fields.contains(&true) && github_app_fields.contains(&false) {
+            errors.push(ConfigError::IncompleteGithubApp);
+        }
+        if let Some([start, end]) = self.review.business_hours {
+            if start >= end || end > 24 {
+                errors.push(ConfigError::InvalidBusinessHours(start, end));
+            }
+        }
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.remove(0)),
+            _ => Err(ConfigError::Multiple(errors)),
+        }
+    }
+
+    /// Get the Feishu/Lark bot webhook URL from the env var or the config in order of
+    /// precedence, empty if set by neither.
+    pub fn feishu_bot_webhook_url(&self) -> String {
+        env_var(FEISHU_BOT_WEBHOOK_URL_ENV)
+            .or_else(|| self.access.feishu_bot_webhook_url.clone())
+            .unwrap_or_default()
+    }
+
+    /// Get the platform, the webhook URL and the Feishu open IDs of the users to mention
+    /// of each notifier to send the stats to. The Feishu/Lark bot configured by
+    /// `feishu_bot_webhook_url` comes first if set, without mentioning anyone.
+    pub fn notifiers(&self) -> Vec<(Platform, String, HashMap<String, String>)> {
+        let feishu_bot_webhook_url = self.feishu_bot_webhook_url();
+        (!feishu_bot_webhook_url.is_empty())
+            .then(|| (Platform::Feishu, feishu_bot_webhook_url, HashMap::new()))
+            .into_iter()
+            .chain(self.notifiers.iter().map(|notifier| {
+                (
+                    notifier.platform,
+                    notifier.webhook_url.clone(),
+                    notifier.feishu_open_ids.clone(),
+                )
+            }))
+            .collect()
+    }
+
+    /// Get the GitHub personal token from the env var, the token file or the config in
+    /// order of precedence, where the token file is also told by the env var first. The
+    /// token file is read with the surrounding whitespaces trimmed, e.g. a secret mounted
+    /// by Docker or Kubernetes.
+    pub fn github_personal_token(&self) -> Result<String, Error> {
+        if let Some(token) = env_var(GITHUB_PERSONAL_TOKEN_ENV) {
+            Ok(token)
+        } else if let Some(token_file) = env_var(GITHUB_PERSONAL_TOKEN_FILE_ENV)
+            .or_else(|| self.access.github_personal_token_file.clone())
+        {
+            let token = fs::read_to_string(&token_file).map_err(|source| Error::ReadTokenFile {
+                path: token_file.clone(),
+                source,
+            })?;
+            Ok(token.trim().to_string())
         } else {
-            self.access.github_personal_token.clone()
+            self.access
+                .github_personal_token
+                .clone()
+                .filter(|token| !token.is_empty())
+                .ok_or(Error::MissingToken)
         }
     }
 
+    /// Get the GitHub App to authenticate as an installation of, which takes precedence
+    /// over the personal token for its far higher rate limits. `None` unless all of its
+    /// fields are set.
+    pub fn github_app(&self) -> Option<GithubApp> {
+        Some(GithubApp {
+            app_id: self.access.github_app_id?,
+            installation_id: self.access.github_app_installation_id?,
+            private_key_file: self.access.github_app_private_key_file.clone()?,
+        })
+    }
+
+    /// Get the base URL of the GitHub API from the env var or the config in order of
+    /// precedence, e.g. of a GitHub Enterprise Server instance. `None` means the public
+    /// GitHub.
+    pub fn github_base_url(&self) -> Option<String> {
+        env_var(GITHUB_BASE_URL_ENV).or_else(|| self.access.github_base_url.clone())
+    }
+
     pub fn review_users(&self) -> Vec<String> {
         self.review.users.clone()
     }
 
+    /// Get the allowed users of the repo, which are the ones of its override in
+    /// `review.repo_overrides` if any, or the global `review.users` otherwise.
+    pub fn review_repo_users(&self, repo: &str) -> Vec<String> {
+        self.review
+            .repo_overrides
+            .iter()
+            .find(|repo_override| repo_override.repo == repo)
+            .map_or_else(
+                || self.review_users(),
+                |repo_override| repo_override.users.clone(),
+            )
+    }
+
+    /// Get the global users along with the users of all the repo overrides, deduplicated.
+    pub fn review_all_users(&self) -> Vec<String> {
+        let mut users = self.review_users();
+        for repo_override in &self.review.repo_overrides {
+            for user in &repo_override.users {
+                if !users.contains(user) {
+                    users.push(user.clone());
+                }
+            }
+        }
+        users
+    }
+
     pub fn review_repos(&self) -> Vec<String> {
         self.review.repos.clone()
     }
 
+    /// Get the orgs whose repos are all fetched besides the explicitly listed repos.
+    pub fn review_orgs(&self) -> Vec<String> {
+        self.review.orgs.clone()
+    }
+
+    /// Get the users excluded from the stats, even if they are in the allowed users.
+    pub fn review_user_exclusion(&self) -> UserExclusion {
+        UserExclusion {
+            exclude_bots: self.review.exclude_bots,
+            exclude_users: self.review.exclude_users.iter().cloned().collect(),
+        }
+    }
+
+    /// Whether to count all the users except the excluded ones, instead of only the
+    /// allowed users.
+    pub fn review_track_all_users(&self) -> bool {
+        self.review.track_all_users
+    }
+
     /// Get the comments that are considered as a LGTM approval.
     pub fn review_lgtm_comments(&self) -> Vec<String> {
         self.review.lgtm_comments.clone()
     }
+
+    /// Get the regexes matching the comments that are considered as a LGTM approval, e.g.
+    /// `(?i)^approved\b` or `:\+1:`. The invalid ones are reported by `validate` and
+    /// skipped here.
+    pub fn review_lgtm_patterns(&self) -> Vec<Regex> {
+        self.review
+            .lgtm_patterns
+            .iter()
+            .filter_map(|pattern| Regex::new(pattern).ok())
+            .collect()
+    }
+
+    /// Get the labels filtering the issues and PRs to count the activities on.
+    pub fn review_label_filter(&self) -> LabelFilter {
+        LabelFilter {
+            include_labels: self.review.include_labels.iter().cloned().collect(),
+            exclude_labels: self.review.exclude_labels.iter().cloned().collect(),
+        }
+    }
+
+    /// Get the labels that are considered as a LGTM approval when applied to a PR.
+    pub fn review_approval_labels(&self) -> Vec<String> {
+        self.review.approval_labels.clone()
+    }
+
+    /// Whether to count the issues and PRs reopened by each user.
+    pub fn review_count_reopens(&self) -> bool {
+        self.review.count_reopens
+    }
+
+    /// Whether to count the issues and PRs closed by each user, i.e. the actors of the
+    /// `closed` timeline events, as the `closed_by` of an issue is missing in the listing.
+    pub fn review_count_closes(&self) -> bool {
+        self.review.count_closes
+    }
+
+    /// Whether to count the labels added to the issues and PRs by each user.
+    pub fn review_count_labels(&self) -> bool {
+        self.review.count_labels
+    }
+
+    /// Whether to count the thumbs-up reactions given by each user to the issues, PRs
+    /// and their comments, which takes a request for each of them.
+    pub fn review_count_reactions(&self) -> bool {
+        self.review.count_reactions
+    }
+
+    /// Whether to exclude the draft PRs from the PRs created by each user. A PR is told
+    /// by whether it's a draft now, rather than when it was created.
+    pub fn review_exclude_draft_prs(&self) -> bool {
+        self.review.exclude_draft_prs
+    }
+
+    /// Whether to credit the allowed co-authors of a PR, i.e. the ones in the
+    /// `Co-authored-by:` trailers of its body, with the PR as well as its author.
+    pub fn review_credit_coauthors(&self) -> bool {
+        self.review.credit_coauthors
+    }
+
+    /// Whether to count the review comments of a user in a thread once, rather than once
+    /// per reply, so the back-and-forth discussions are not over-weighted.
+    pub fn review_collapse_review_threads(&self) -> bool {
+        self.review.collapse_review_threads
+    }
+
+    /// Get the min length in characters of the comments to be counted, with the
+    /// surrounding whitespaces trimmed. 0 means all the comments are counted.
+    pub fn review_min_comment_length(&self) -> usize {
+        self.review.min_comment_length
+    }
+
+    /// Get the lines changed thresholds between the XS/S/M/L/XL PR size buckets.
+    pub fn review_pr_size_thresholds(&self) -> [u64; 4] {
+        self.review.pr_size_thresholds
+    }
+
+    /// Whether to also weight the LGTMs of each user by the sizes of the approved PRs,
+    /// which needs to fetch the details of each approved PR.
+    pub fn review_weight_lgtm_by_size(&self) -> bool {
+        self.review.weight_lgtm_by_size
+    }
+
+    /// Get the business hours to measure the time to first response in, configured by
+    /// `review.business_hours` as the start and end hours in UTC like `[9, 18]`, with the
+    /// holidays excluded as well by `review.holidays` like `["2024-12-25"]`. `None` means
+    /// the wall-clock time, where the holidays are ignored.
+    pub fn review_business_hours(&self) -> Option<BusinessHours> {
+        let [start_hour, end_hour] = self.review.business_hours?;
+        Some(BusinessHours {
+            start_hour,
+            end_hour,
+            holidays: self.review.holidays.iter().copied().collect(),
+        })
+    }
+
+    /// Get the team of each user, which is configured as the members of each team.
+    pub fn review_user_teams(&self) -> HashMap<String, String> {
+        self.review
+            .teams
+            .iter()
+            .flat_map(|(team, members)| {
+                members
+                    .iter()
+                    .map(move |member| (member.clone(), team.clone()))
+            })
+            .collect()
+    }
+
+    /// Get the aliases of the users, from an old login to the canonical one, e.g. for a
+    /// renamed account, so the activities under both logins are counted as one user.
+    pub fn review_user_aliases(&self) -> HashMap<String, String> {
+        self.review.user_aliases.clone()
+    }
+
+    /// Get the display names of the users by their logins, e.g. the real names, which are
+    /// shown in place of the logins by the outputs for humans if asked.
+    pub fn review_user_display_names(&self) -> HashMap<String, String> {
+        self.review.user_display_names.clone()
+    }
+
+    /// Get the path of the baseline file. If set, each run reports the deltas against
+    /// the baseline and then overwrites it with the current counts.
+    pub fn report_baseline(&self) -> Option<String> {
+        self.report.baseline.clone()
+    }
+
+    /// Get the weight of each metric in the contribution score, the metrics not configured
+    /// weigh 1.0.
+    pub fn scoring_weights(&self) -> HashMap<Metric, f64> {
+        self.scoring.clone()
+    }
+
+    /// Get the max number of retries of a request on transient GitHub errors.
+    pub fn fetch_max_retries(&self) -> u32 {
+        self.fetch.max_retries
+    }
+
+    /// Get the delay before the first retry, which doubles after each retry.
+    pub fn fetch_retry_base_delay(&self) -> Duration {
+        Duration::from_millis(self.fetch.retry_base_delay_ms)
+    }
+
+    /// Get the max number of repos to fetch simultaneously, to avoid tripping the abuse
+    /// detection of GitHub with a burst of requests. At least one repo is fetched.
+    pub fn fetch_max_concurrency(&self) -> usize {
+        self.fetch.max_concurrency.max(1)
+    }
+
+    /// Get the number of the issues of a repo to fetch the comments of concurrently.
+    pub fn fetch_issue_comments_concurrency(&self) -> usize {
+        self.fetch.issue_comments_concurrency
+    }
+
+    /// Get the time a single request may take before it's aborted and retried.
+    pub fn fetch_request_timeout(&self) -> Duration {
+        Duration::from_secs(self.fetch.request_timeout_secs)
+    }
+
+    /// Get the number of items to request in a single page of a listing, clamped to
+    /// between one and the max of 100 GitHub allows.
+    pub fn fetch_page_size(&self) -> u8 {
+        self.fetch.page_size.clamp(1, u32::from(MAX_PAGE_SIZE)) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(config: &str) -> Config {
+        Config::from_reader(config.as_bytes(), ConfigFormat::Toml).unwrap()
+    }
+
+    #[test]
+    fn test_validate() {
+        let valid = config(
+            r#"
+            [review]
+            users = ["alice"]
+            repos = ["tikv/tikv"]
+            lgtm_comments = ["lgtm"]
+            "#,
+        );
+        assert_eq!(valid.validate(), Ok(()));
+
+        let invalid = config(
+            r#"
+            [access]
+            github_base_url = "ftp://example.com"
+            github_app_id = 1
+
+            [review]
+            users = []
+            repos = ["tikv", "tikv/tikv"]
+            lgtm_patterns = ["(lgtm"]
+            "#,
+        );
+        let Err(ConfigError::Multiple(errors)) = invalid.validate() else {
+            panic!("expect multiple errors");
+        };
+        assert_eq!(errors.len(), 5);
+        assert_eq!(errors[0], ConfigError::InvalidRepo("tikv".to_string()));
+        assert_eq!(errors[1], ConfigError::NoUsers);
+        assert!(
+            matches!(&errors[2], ConfigError::InvalidLgtmPattern(pattern, _) if pattern == "(lgtm")
+        );
+        assert_eq!(
+            errors[3],
+            ConfigError::InvalidBaseUrl("ftp://example.com".to_string())
+        );
+        assert_eq!(errors[4], ConfigError::IncompleteGithubApp);
+    }
+
+    #[test]
+    fn test_fetch_page_size() {
+        let page_size = |fetch: &str| {
+            config(&format!(
+                "[review]\nusers = [\"alice\"]\n[fetch]\n{}",
+                fetch
+            ))
+            .fetch_page_size()
+        };
+        assert_eq!(page_size(""), MAX_PAGE_SIZE);
+        assert_eq!(page_size("page_size = 0"), 1);
+        assert_eq!(page_size("page_size = 50"), 50);
+        // Larger than a byte, which is still clamped rather than failing to parse.
+        assert_eq!(page_size("page_size = 1000"), MAX_PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_business_hours() {
+        let config = config(
+            r#"
+            [review]
+            users = ["alice"]
+            lgtm_comments = ["lgtm"]
+            business_hours = [9, 18]
+            holidays = ["2024-01-08"]
+            "#,
+        );
+        let business_hours = config.review_business_hours().unwrap();
+        let duration = |start: &str, end: &str| {
+            business_hours.duration(start.parse().unwrap(), end.parse().unwrap())
+        };
+        // Within the same business day.
+        assert_eq!(
+            duration("2024-01-05T10:00:00Z", "2024-01-05T12:30:00Z"),
+            chrono::Duration::minutes(150)
+        );
+        // From Friday evening to Tuesday morning over the weekend and the holiday.
+        assert_eq!(
+            duration("2024-01-05T17:00:00Z", "2024-01-09T10:00:00Z"),
+            chrono::Duration::hours(2)
+        );
+        // Outside the business hours.
+        assert_eq!(
+            duration("2024-01-05T19:00:00Z", "2024-01-06T12:00:00Z"),
+            chrono::Duration::zero()
+        );
+        assert_eq!(
+            duration("2024-01-05T12:00:00Z", "2024-01-05T10:00:00Z"),
+            chrono::Duration::zero()
+        );
+
+        let with_business_hours = |hours: [u32; 2]| {
+            let mut config = config.clone();
+            config.review.business_hours = Some(hours);
+            config
+        };
+        // The whole days of Friday and Tuesday.
+        let whole_day = with_business_hours([0, 24])
+            .review_business_hours()
+            .unwrap();
+        assert_eq!(
+            whole_day.duration(
+                "2024-01-05T00:00:00Z".parse().unwrap(),
+                "2024-01-10T00:00:00Z".parse().unwrap()
+            ),
+            chrono::Duration::hours(48)
+        );
+        assert_eq!(
+            with_business_hours([18, 9]).validate(),
+            Err(ConfigError::InvalidBusinessHours(18, 9))
+        );
+        assert_eq!(
+            with_business_hours([9, 25]).validate(),
+            Err(ConfigError::InvalidBusinessHours(9, 25))
+        );
+    }
+
+    #[test]
+    fn test_validate_single_error() {
+        let no_lgtm_comments = config(
+            r#"
+            [review]
+            users = ["alice"]
+            "#,
+        );
+        assert_eq!(
+            no_lgtm_comments.validate(),
+            Err(ConfigError::NoLgtmComments)
+        );
+
+        // The users can be empty when tracking all users.
+        let track_all_users = config(
+            r#"
+            [review]
+            track_all_users = true
+            lgtm_comments = ["lgtm"]
+            "#,
+        );
+        assert_eq!(track_all_users.validate(), Ok(()));
+    }
 }
@@ -0,0 +1,276 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fs,
+    path::Path,
+};
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::notifier::{Notifier, Platform};
+use crate::stats::Stats;
+
+/// The status of the delivery of a report to a notifier target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Delivered,
+    Failed,
+}
+
+/// The delivery status of a report to each notifier target, so a re-run sending the same
+/// report only retries the targets it failed to be delivered to.
+///
+/// A report is identified by its time range resolved in RFC3339, e.g.
+/// "2024-01-01T00:00:00+00:00~2024-01-08T00:00:00+00:00", so the re-run should resolve
+/// the same time range, e.g. by the absolute start and end times, or `--resume` which
+/// keeps the time range of the checkpoint. A report of any other time range starts over
+/// with all the targets. A target is identified by its platform and a hash of its
+/// webhook URL, which keeps the secret in the URL out of the state file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeliveryState {
+    report: String,
+    targets: BTreeMap<String, DeliveryStatus>,
+}
+
+impl DeliveryState {
+    /// Create the state of the report of the stats with nothing delivered yet.
+    pub fn new(stats: &Stats) -> Self {
+        Self {
+            report: report_id(stats),
+            targets: BTreeMap::new(),
+        }
+    }
+
+    /// Load the state of the report of the stats from the file. Returns a new state if
+    /// the file does not exist yet, or it's of another report.
+    pub fn load<P: AsRef<Path>>(state_path: P, stats: &Stats) -> Result<Self, Box<dyn Error>> {
+        let state_path = state_path.as_ref();
+        if !state_path.exists() {
+            return Ok(Self::new(stats));
+        }
+        let state: Self = serde_json::from_str(&fs::read_to_string(state_path)?)?;
+        if state.report != report_id(stats) {
+            return Ok(Self::new(stats));
+        }
+        Ok(state)
+    }
+
+    /// Overwrite the state file. The state is written to a temporary file first and then
+    /// renamed, so a failed write never corrupts the existing state.
+    pub fn save<P: AsRef<Path>>(&self, state_path: P) -> Result<(), Box<dyn Error>> {
+        let state_path = state_path.as_ref();
+        let mut tmp_path = state_path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp_path, state_path)?;
+        Ok(())
+    }
+
+    /// Whether the report has been delivered to the target.
+    pub fn is_delivered(&self, platform: Platform, webhook_url: &str) -> bool {
+        self.targets.get(&target_id(platform, webhook_url)) == Some(&DeliveryStatus::Delivered)
+    }
+
+    /// Record the status of the delivery to the target.
+    pub fn record(&mut self, platform: Platform, webhook_url: &str, status: DeliveryStatus) {
+        self.targets
+            .insert(target_id(platform, webhook_url), status);
+    }
+
+    /// Send the stats to each target of `Config::notifiers` by the notifier created by
+    /// `new_notifier`, skipping the ones already delivered to, and record the status of
+    /// each delivery. The state is saved to the file if any after each target, so the
+    /// delivered ones are kept even if the run dies halfway.
+    pub async fn deliver<F>(
+        &mut self,
+        stats: &Stats,
+        targets: Vec<(Platform, String, HashMap<String, String>)>,
+        new_notifier: F,
+        state_path: Option<&Path>,
+    ) where
+        F: Fn(Platform, String, HashMap<String, String>) -> Box<dyn Notifier>,
+    {
+        for (platform, webhook_url, feishu_open_ids) in targets {
+            if self.is_delivered(platform, &webhook_url) {
+                info!("stats already sent to the {} webhook, skip it", platform);
+                continue;
+            }
+            let notifier = new_notifier(platform, webhook_url.clone(), feishu_open_ids);
+            let status = if let Err(err) = notifier.send(stats).await {
+                error!("failed to send stats to the {} webhook: {}", platform, err);
+                DeliveryStatus::Failed
+            } else {
+                info!("stats sent to the {} webhook", platform);
+                DeliveryStatus::Delivered
+            };
+            self.record(platform, &webhook_url, status);
+            if let Some(state_path) = state_path {
+                if let Err(err) = self.save(state_path) {
+                    warn!(
+                        "failed to save notify state '{}': {}",
+                        state_path.display(),
+                        err
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Get the identity of the report of the stats, i.e. its resolved time range.
+fn report_id(stats: &Stats) -> String {
+    let (start_time, end_time) = stats.time_range();
+    format!("{}~{}", start_time.to_rfc3339(), end_time.to_rfc3339())
+}
+
+/// Get the identity of the target, e.g. "slack:5f0c6a4b3e2d1c0f", with the 64-bit
+/// FNV-1a hash of the webhook URL, which is stable across the builds unlike the hasher
+/// of the standard library.
+fn target_id(platform: Platform, webhook_url: &str) -> String {
+    let hash = webhook_url
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        });
+    format!("{}:{:016x}", platform, hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+    use chrono::{DateTime, Utc};
+
+    use super::*;
+    use crate::config::{Config, ConfigFormat};
+
+    /// The notifier recording the webhook URL of each send, which fails to send to the
+    /// webhooks down.
+    struct FakeNotifier {
+        webhook_url: String,
+        down: Arc<Mutex<Vec<String>>>,
+        sent: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Notifier for FakeNotifier {
+        async fn send(&self, _: &Stats) -> Result<(), Box<dyn Error>> {
+            self.sent.lock().unwrap().push(self.webhook_url.clone());
+            if self.down.lock().unwrap().contains(&self.webhook_url) {
+                return Err("webhook down".into());
+            }
+            Ok(())
+        }
+    }
+
+    fn stats(config: &Config, end_time: &str) -> Stats {
+        let time = |time: &str| time.parse::<DateTime<Utc>>().unwrap();
+        Stats::new(
+            config,
+            "tikv/tikv",
+            time("2024-01-01T00:00:00Z"),
+            time(end_time),
+        )
+    }
+
+    #[test]
+    fn test_delivery_state() {
+        let config = Config::from_reader(
+            "[review]\nusers = [\"alice\"]\nlgtm_comments = [\"lgtm\"]\n".as_bytes(),
+            ConfigFormat::Toml,
+        )
+        .unwrap();
+        let report = stats(&config, "2024-01-08T00:00:00Z");
+        let state_path = std::env::temp_dir().join(format!(
+            "gh-overseer-test-{}.notify-state.json",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&state_path);
+
+        let mut state = DeliveryState::load(&state_path, &report).unwrap();
+        assert_eq!(state, DeliveryState::new(&report));
+        state.record(
+            Platform::Slack,
+            "https://hooks.slack.com/a",
+            DeliveryStatus::Delivered,
+        );
+        state.record(
+            Platform::Slack,
+            "https://hooks.slack.com/b",
+            DeliveryStatus::Failed,
+        );
+        state.save(&state_path).unwrap();
+
+        // The same report only retries the failed targets.
+        let state = DeliveryState::load(&state_path, &report).unwrap();
+        assert!(state.is_delivered(Platform::Slack, "https://hooks.slack.com/a"));
+        assert!(!state.is_delivered(Platform::Slack, "https://hooks.slack.com/b"));
+        assert!(!state.is_delivered(Platform::Discord, "https://hooks.slack.com/a"));
+        let saved = fs::read_to_string(&state_path).unwrap();
+        assert!(!saved.contains("hooks.slack.com"));
+
+        // Another report starts over.
+        let other = stats(&config, "2024-01-09T00:00:00Z");
+        let state = DeliveryState::load(&state_path, &other).unwrap();
+        assert!(!state.is_delivered(Platform::Slack, "https://hooks.slack.com/a"));
+        fs::remove_file(&state_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_deliver() {
+        let config = Config::from_reader(
+            "[review]\nusers = [\"alice\"]\nlgtm_comments = [\"lgtm\"]\n".as_bytes(),
+            ConfigFormat::Toml,
+        )
+        .unwrap();
+        let report = stats(&config, "2024-01-08T00:00:00Z");
+        let state_path = std::env::temp_dir().join(format!(
+            "gh-overseer-test-{}.deliver-state.json",
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&state_path);
+        let targets = || {
+            ["https://hooks.slack.com/a", "https://hooks.slack.com/b"]
+                .into_iter()
+                .map(|webhook_url| (Platform::Slack, webhook_url.to_string(), HashMap::new()))
+                .collect::<Vec<_>>()
+        };
+        let down = Arc::new(Mutex::new(vec!["https://hooks.slack.com/b".to_string()]));
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let new_notifier = |_, webhook_url, _| -> Box<dyn Notifier> {
+            Box::new(FakeNotifier {
+                webhook_url,
+                down: down.clone(),
+                sent: sent.clone(),
+            })
+        };
+
+        // The first run fails to deliver to the webhook down.
+        let mut state = DeliveryState::load(&state_path, &report).unwrap();
+        state
+            .deliver(&report, targets(), new_notifier, Some(&state_path))
+            .await;
+        assert_eq!(sent.lock().unwrap().len(), 2);
+
+        // The re-run only retries the failed one, which is back up.
+        down.lock().unwrap().clear();
+        sent.lock().unwrap().clear();
+        let mut state = DeliveryState::load(&state_path, &report).unwrap();
+        state
+            .deliver(&report, targets(), new_notifier, Some(&state_path))
+            .await;
+        assert_eq!(*sent.lock().unwrap(), ["https://hooks.slack.com/b"]);
+
+        // Nothing is sent once all are delivered.
+        sent.lock().unwrap().clear();
+        let mut state = DeliveryState::load(&state_path, &report).unwrap();
+        state
+            .deliver(&report, targets(), new_notifier, Some(&state_path))
+            .await;
+        assert!(sent.lock().unwrap().is_empty());
+        fs::remove_file(&state_path).unwrap();
+    }
+}
@@ -0,0 +1,78 @@
+use std::{io, path::PathBuf, time::Duration};
+
+use thiserror::Error;
+
+use crate::config::ConfigError;
+
+/// The errors of loading the config and fetching the stats.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The config file can't be read.
+    #[error("failed to read config file '{}': {source}", .path.display())]
+    ReadConfig { path: PathBuf, source: io::Error },
+    /// The TOML config is invalid or misses the required fields.
+    #[error("failed to parse config file '{}': {source}", .path.display())]
+    ParseConfig {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    /// The JSON config is invalid or misses the required fields.
+    #[error("failed to parse json config file '{}': {source}", .path.display())]
+    ParseJsonConfig {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// The problems found in the config by `Config::validate`.
+    #[error("invalid config: {0}")]
+    InvalidConfig(#[from] ConfigError),
+    /// The GitHub personal token file can't be read.
+    #[error("failed to read token file '{path}': {source}")]
+    ReadTokenFile { path: String, source: io::Error },
+    /// The GitHub personal token is set by neither the config nor the env vars.
+    #[error(
+        "no github personal token configured, set `access.github_personal_token`, \
+         `access.github_personal_token_file` or the GH_OVERSEER_GITHUB_PERSONAL_TOKEN env var"
+    )]
+    MissingToken,
+    /// The private key file of the GitHub App can't be read.
+    #[error("failed to read github app private key file '{path}': {source}")]
+    ReadPrivateKey { path: String, source: io::Error },
+    /// The private key of the GitHub App is not a valid RSA key in PEM.
+    #[error("invalid github app private key '{path}': {source}")]
+    InvalidPrivateKey {
+        path: String,
+        source: jsonwebtoken::errors::Error,
+    },
+    /// The repo is not in the `owner/name` form.
+    #[error("invalid repo '{0}', should be 'owner/name'")]
+    InvalidRepo(String),
+    /// The repos of the org can't be fetched.
+    #[error("failed to fetch repos of org '{org}': {source}")]
+    FetchOrgRepos { org: String, source: Box<Error> },
+    /// The GitHub client fails, e.g. to be built with an invalid base URL.
+    #[error("github client error: {0}")]
+    GitHub(#[from] octocrab::Error),
+    /// A request to GitHub takes longer than the timeout, which is retried as transient.
+    #[error("github request timed out after {0:?}")]
+    Timeout(Duration),
+    /// The baseline file can't be read.
+    #[error("failed to read baseline file '{}': {source}", .path.display())]
+    ReadBaseline { path: PathBuf, source: io::Error },
+    /// The baseline file is not the TOML of the counts.
+    #[error("failed to parse baseline file '{}': {source}", .path.display())]
+    ParseBaseline {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    /// The counts can't be serialized into the TOML baseline.
+    #[error("failed to serialize baseline: {0}")]
+    SerializeBaseline(#[from] toml::ser::Error),
+    /// The baseline file can't be written.
+    #[error("failed to write baseline file '{}': {source}", .path.display())]
+    WriteBaseline { path: PathBuf, source: io::Error },
+    /// No repos to fetch the stats of.
+    #[error("no repos configured, nothing to fetch")]
+    NoRepos,
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -1,15 +1,288 @@
-use std::{any::type_name, future::Future, sync::Arc};
+mod graphql;
 
-use chrono::{DateTime, Utc};
-use log::{error, info};
-use octocrab::{models, params, Octocrab};
-use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    future::Future,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use clap::ValueEnum;
+use futures::{stream, StreamExt};
+use http::{StatusCode, Uri};
+use log::{debug, error, info, warn};
+use octocrab::{models, FromResponse, Octocrab, Page};
+use serde::de::DeserializeOwned;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use self::graphql::Prefetched;
+use crate::error::Error;
+
+/// The max number of items to request in a single page GitHub allows, which is also the
+/// default page size.
+pub const MAX_PAGE_SIZE: u8 = 100;
+
+/// The maximum number of commits GitHub lists for a single PR, even if all the pages are
+/// followed, see `warn_if_truncated` for the caps of the other endpoints.
+const PULL_REQUEST_COMMITS_CAP: usize = 250;
+
+/// The default number of the issues to fetch the comments of concurrently.
+const DEFAULT_ISSUE_COMMENTS_CONCURRENCY: usize = 8;
+
+/// The endpoints failed to be fetched, shared between the fetch tasks of a fetcher.
+#[derive(Debug, Clone, Default)]
+pub struct Failures(Arc<Mutex<FailedEndpoints>>);
+
+#[derive(Debug, Default)]
+struct FailedEndpoints {
+    endpoints: Vec<String>,
+    // Whether any listing of the whole repo failed, e.g. the issues.
+    listing_failed: bool,
+}
+
+impl Failures {
+    /// Record a failed endpoint of a single issue or PR, the data of the others is intact.
+    fn record(&self, endpoint: impl Into<String>) {
+        self.0.lock().unwrap().endpoints.push(endpoint.into());
+    }
+
+    /// Record a failed listing of the whole repo, which means the fetch of the repo is
+    /// hard-failed as the data is missing rather than partial.
+    fn record_listing(&self, endpoint: impl Into<String>) {
+        let mut failed = self.0.lock().unwrap();
+        failed.endpoints.push(endpoint.into());
+        failed.listing_failed = true;
+    }
+}
+
+/// The GitHub API to fetch the issues, comments and reviews with.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Api {
+    /// The REST API, which needs a request per issue for the comments and per PR for the
+    /// reviews.
+    #[default]
+    Rest,
+    /// The GraphQL API, which fetches the issues and PRs along with their comments and
+    /// reviews in far fewer requests. The ones too many to fit in a query fall back to
+    /// the REST API.
+    Graphql,
+}
+
+/// The state of the issues and PRs to fetch.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IssueState {
+    /// Only the issues and PRs open now, e.g. for a snapshot of the ongoing work.
+    Open,
+    /// Only the closed issues and PRs, including the merged PRs.
+    Closed,
+    /// All the issues and PRs.
+    #[default]
+    All,
+}
+
+impl IssueState {
+    /// Get the `state` parameter of the REST listing.
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Closed => "closed",
+            Self::All => "all",
+        }
+    }
+}
+
+/// An item of a repo which can be reacted to.
+#[derive(Debug, Clone, Copy)]
+pub enum Reactable {
+    /// An issue or PR with its number.
+    Issue(u64),
+    /// An issue comment with its ID.
+    IssueComment(u64),
+    /// A PR review comment with its ID.
+    PullRequestComment(u64),
+}
+
+impl Reactable {
+    fn path(&self, owner: &str, repo_name: &str) -> String {
+        match self {
+            Self::Issue(number) => {
+                format!("/repos/{}/{}/issues/{}/reactions", owner, repo_name, number)
+            }
+            Self::IssueComment(id) => format!(
+                "/repos/{}/{}/issues/comments/{}/reactions",
+                owner, repo_name, id
+            ),
+            Self::PullRequestComment(id) => format!(
+                "/repos/{}/{}/pulls/comments/{}/reactions",
+                owner, repo_name, id
+            ),
+        }
+    }
+}
+
+/// The retry policy of the requests on transient GitHub errors, i.e. 429, 502, 503, 504,
+/// secondary rate limits, network errors and timeouts. The delay before the n-th retry is
+/// `base_delay * 2 ^ (n - 1)` with a random jitter of up to half of it.
+#[derive(Debug, Clone, Copy)]
+pub struct Retry {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    /// The time a single request may take before it's aborted and retried, excluding
+    /// the wait for the rate limit to reset.
+    pub timeout: Duration,
+}
+
+/// The GitHub client which retries the requests on transient errors and waits for the
+/// exhausted rate limit to reset.
+#[derive(Debug, Clone)]
+struct Client {
+    octocrab: Arc<Octocrab>,
+    retry: Retry,
+    progress: Option<ProgressReporter>,
+    // The path of the base URL of the GitHub API without the trailing slash, e.g.
+    // "/api/v3" of a GitHub Enterprise Server instance, to resolve the relative links.
+    base_path: String,
+    // The number of items to request in a single page of a listing.
+    page_size: u8,
+}
+
+/// The progress of a listing of a repo, i.e. the number of the items in a newly fetched
+/// page.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    pub repo: String,
+    pub listing: &'static str,
+    pub fetched: usize,
+}
+
+/// The reporter sending the progress of a listing as the pages come in.
+#[derive(Debug, Clone)]
+struct ProgressReporter {
+    tx: UnboundedSender<Progress>,
+    repo: String,
+    listing: &'static str,
+}
+
+impl ProgressReporter {
+    fn report(&self, fetched: usize) {
+        // The progress is best-effort, ignore it if nobody is receiving.
+        let _ = self.tx.send(Progress {
+            repo: self.repo.clone(),
+            listing: self.listing,
+            fetched,
+        });
+    }
+}
+
+impl Client {
+    /// Send the request and retry it with exponential backoff on transient errors, the
+    /// other errors fail fast.
+    async fn retry<T, F, R>(&self, request: F) -> crate::error::Result<T>
+    where
+        F: Fn() -> R,
+        R: Future<Output = crate::error::Result<T>>,
+    {
+        let mut retries = 0;
+        loop {
+            match request().await {
+                Err(err) if retries < self.retry.max_retries && is_transient(&err) => {
+                    let delay = backoff(self.retry.base_delay, retries);
+                    retries += 1;
+                    warn!(
+                        "transient github error, retry {}/{} in {:?}: {}",
+                        retries, self.retry.max_retries, delay, err
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Send a `GET` request to the route, which is either relative to the GitHub API or an
+    /// absolute `next` link of a page. Once the rate limit is exhausted as told by the
+    /// `X-RateLimit-*` headers, wait until it resets, and resend the request if it was
+    /// rejected by the rate limit.
+    async fn get<R: FromResponse>(&self, route: &str) -> crate::error::Result<R> {
+        loop {
+            let response = self.timeout(self.octocrab._get(route)).await?;
+            let header = |name: &str| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+            };
+            if let Some(reset) =
+                rate_limit_reset(header("x-ratelimit-remaining"), header("x-ratelimit-reset"))
+            {
+                let wait =
+                    (reset - Utc::now()).to_std().unwrap_or_default() + Duration::from_secs(1);
+                info!(
+                    "github rate limit exhausted, sleep {:?} until it resets at {}",
+                    wait, reset
+                );
+                tokio::time::sleep(wait).await;
+                if matches!(response.status().as_u16(), 403 | 429) {
+                    continue;
+                }
+            }
+            let status = response.status();
+            return self
+                .timeout(async move {
+                    let response = octocrab::map_github_error(response)
+                        .await
+                        .map_err(|err| with_status(err, status))?;
+                    R::from_response(response).await
+                })
+                .await;
+        }
+    }
+
+    /// Build the route of a listing with the query parameters, requesting the page size.
+    fn list_route(&self, path: &str, params: &[(&str, String)]) -> String {
+        let query = params
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, encode_query_value(value)))
+            .chain(std::iter::once(format!("per_page={}", self.page_size)))
+            .collect::<Vec<_>>()
+            .join("&");
+        format!("{}?{}", path, query)
+    }
+
+    /// Abort the request once it takes longer than the timeout, which fails it with
+    /// `Error::Timeout` to be retried.
+    async fn timeout<T>(
+        &self,
+        request: impl Future<Output = octocrab::Result<T>>,
+    ) -> crate::error::Result<T> {
+        match tokio::time::timeout(self.retry.timeout, request).await {
+            Ok(result) => Ok(result?),
+            Err(_) => {
+                warn!("github request timed out after {:?}", self.retry.timeout);
+                Err(Error::Timeout(self.retry.timeout))
+            }
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Fetcher {
     repo: (String, String), // (owner, repo_name)
-    octocrab: Arc<Octocrab>,
+    client: Client,
     start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    failures: Failures,
+    // The number of the issues to fetch the comments of concurrently.
+    issue_comments_concurrency: usize,
+    // The data fetched along with the issues by GraphQL, `None` means the REST API.
+    prefetched: Option<Arc<Mutex<Prefetched>>>,
+    // The directory to read the listings from instead of GitHub, `None` means GitHub.
+    fixtures_dir: Option<PathBuf>,
+    // The state of the issues and PRs to fetch.
+    issue_state: IssueState,
 }
 
 impl Fetcher {
@@ -18,39 +291,204 @@ impl Fetcher {
         octocrab: Octocrab,
         repo: &str,
         start_time: impl Into<chrono::DateTime<chrono::Utc>>,
-    ) -> Result<Self, &'static str> {
+        end_time: impl Into<chrono::DateTime<chrono::Utc>>,
+        retry: Retry,
+    ) -> crate::error::Result<Self> {
         info!("fetcher init with repo '{}'", repo);
         Ok(Self {
             repo: repo
                 .split_once('/')
                 .map(|(owner, repo_name)| (owner.to_string(), repo_name.to_string()))
-                .ok_or("invalid repo name, should be 'owner/repo_name'")?,
-            octocrab: Arc::new(octocrab),
+                .ok_or_else(|| Error::InvalidRepo(repo.to_string()))?,
+            client: Client {
+                octocrab: Arc::new(octocrab),
+                retry,
+                progress: None,
+                base_path: String::new(),
+                page_size: MAX_PAGE_SIZE,
+            },
             start_time: start_time.into(),
+            end_time: end_time.into(),
+            failures: Failures::default(),
+            issue_comments_concurrency: DEFAULT_ISSUE_COMMENTS_CONCURRENCY,
+            prefetched: None,
+            fixtures_dir: None,
+            issue_state: IssueState::All,
         })
     }
 
+    /// Set the GitHub API to fetch the issues, comments and reviews with.
+    pub fn set_api(&mut self, api: Api) {
+        self.prefetched = match api {
+            Api::Rest => None,
+            Api::Graphql => Some(Arc::default()),
+        };
+    }
+
+    /// Read each listing from the JSON fixture file in the directory instead of fetching
+    /// it from GitHub, e.g. "issues" of "tikv/tikv" from `<dir>/tikv/tikv/issues.json`
+    /// and "pull request reviews" from `<dir>/tikv/tikv/pull_request_reviews.json`. Each
+    /// listing has its own fixture, e.g. `base_branch_pull_requests.json` for the PRs of
+    /// the base branch and `pull_request_details.json` for the details of the PRs. The
+    /// items of a fixture are returned as is, without any filtering by the time range or
+    /// the numbers, and a missing fixture means no items.
+    pub fn set_fixtures_dir(&mut self, fixtures_dir: impl Into<PathBuf>) {
+        self.fixtures_dir = Some(fixtures_dir.into());
+    }
+
+    /// Only fetch the issues and PRs in the state, e.g. the open ones for a snapshot. The
+    /// comments and reviews of the issues and PRs not fetched are not fetched either.
+    pub fn set_issue_state(&mut self, issue_state: IssueState) {
+        self.issue_state = issue_state;
+    }
+
+    /// Report the progress of each listing to the sender as the pages come in.
+    pub fn set_progress(&mut self, tx: UnboundedSender<Progress>) {
+        self.client.progress = Some(ProgressReporter {
+            tx,
+            repo: format!("{}/{}", self.repo.0, self.repo.1),
+            listing: "",
+        });
+    }
+
+    /// Set the number of items to request in a single page of a listing, between one and
+    /// the max GitHub allows.
+    pub fn set_page_size(&mut self, page_size: u8) {
+        self.client.page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+    }
+
+    /// Set the number of the issues to fetch the comments of concurrently, at least one.
+    pub fn set_issue_comments_concurrency(&mut self, concurrency: usize) {
+        self.issue_comments_concurrency = concurrency.max(1);
+    }
+
+    /// Set the base URL of the GitHub API the client is built with, to resolve the
+    /// relative `next` links of the pages against it.
+    pub fn set_base_url(&mut self, base_url: &str) {
+        self.client.base_path = base_path(base_url);
+    }
+
+    /// Get the endpoints failed to be fetched so far, whose data is missing or partial.
+    pub fn failed_endpoints(&self) -> Vec<String> {
+        self.failures.0.lock().unwrap().endpoints.clone()
+    }
+
+    /// Whether any listing of the whole repo failed to be fetched so far, e.g. the issues,
+    /// so the stats of the repo are not trustworthy at all.
+    pub fn hard_failed(&self) -> bool {
+        self.failures.0.lock().unwrap().listing_failed
+    }
+
     /// Fetch all the issues (including PRs) from the repository.
     pub fn fetch_issues(&self) -> UnboundedReceiver<Vec<models::issues::Issue>> {
-        self.fetch(|octocrab, owner, repo_name, start_time| async move {
-            match octocrab
-                .issues(owner.clone(), repo_name.clone())
-                .list()
-                .state(params::State::All)
-                .since(start_time)
-                .send()
-                .await
-            {
-                Ok(res) => res.items,
-                Err(err) => {
-                    error!(
-                        "failed to fetch issues from {}/{}: {}",
-                        owner, repo_name, err
-                    );
-                    vec![]
+        let issue_state = self.issue_state;
+        if let Some(prefetched) = self.prefetched.clone() {
+            return self.fetch(
+                "issues",
+                move |octocrab, owner, repo_name, start_time, end_time, failures| async move {
+                    match graphql::fetch_issues(
+                        &octocrab,
+                        &owner,
+                        &repo_name,
+                        start_time,
+                        end_time,
+                        issue_state,
+                        &prefetched,
+                    )
+                    .await
+                    {
+                        Ok(issues) => issues,
+                        Err(err) => {
+                            error!(
+                                "failed to fetch issues by graphql from {}/{}: {}",
+                                owner, repo_name, err
+                            );
+                            failures.record_listing("issues");
+                            vec![]
+                        }
+                    }
+                },
+            );
+        }
+        self.fetch(
+            "issues",
+            move |octocrab, owner, repo_name, start_time, end_time, failures| async move {
+                // Issues created after the end time have no activity within the time range,
+                // so list them in the creation order to stop once passing the end time.
+                let route = octocrab.list_route(
+                    &format!("/repos/{}/{}/issues", owner, repo_name),
+                    &[
+                        ("state", issue_state.as_str().to_string()),
+                        ("since", timestamp(start_time)),
+                        ("sort", "created".to_string()),
+                        ("direction", "asc".to_string()),
+                    ],
+                );
+                let first_page = octocrab.retry(|| octocrab.get(&route)).await;
+                let after_end_time = |issue: &models::issues::Issue| issue.created_at > end_time;
+                match all_pages_until(&octocrab, first_page, after_end_time).await {
+                    Ok(issues) => {
+                        if issues.is_empty() {
+                            info!(
+                                "no issues or pull requests updated since {} in {}/{}",
+                                start_time, owner, repo_name
+                            );
+                        }
+                        issues
+                    }
+                    Err(err) => {
+                        match access_denied_hint(&err) {
+                            Some(hint) => error!(
+                                "failed to fetch issues from {}/{}, {}: {}",
+                                owner, repo_name, hint, err
+                            ),
+                            None => error!(
+                                "failed to fetch issues from {}/{}: {}",
+                                owner, repo_name, err
+                            ),
+                        }
+                        failures.record_listing("issues");
+                        vec![]
+                    }
                 }
-            }
-        })
+            },
+        )
+    }
+
+    /// Fetch all the issues (including PRs) that may be open as of the end time, i.e. the
+    /// ones open now and the ones closed since the end time, which must have been updated
+    /// since then by the close. The ones created after or closed before the end time
+    /// should be filtered out by the caller.
+    pub fn fetch_open_issues(&self) -> UnboundedReceiver<Vec<models::issues::Issue>> {
+        self.fetch(
+            "open issues",
+            |octocrab, owner, repo_name, _, end_time, failures| async move {
+                let mut issues = Vec::new();
+                for params in [
+                    vec![("state", "open".to_string())],
+                    vec![
+                        ("state", "closed".to_string()),
+                        ("since", timestamp(end_time)),
+                    ],
+                ] {
+                    let route = octocrab
+                        .list_route(&format!("/repos/{}/{}/issues", owner, repo_name), &params);
+                    let first_page = octocrab.retry(|| octocrab.get(&route)).await;
+                    match all_pages(&octocrab, first_page).await {
+                        Ok(page_issues) => issues.extend(page_issues),
+                        Err(err) => {
+                            error!(
+                                "failed to fetch open issues from {}/{}: {}",
+                                owner, repo_name, err
+                            );
+                            failures.record_listing("open issues");
+                            return vec![];
+                        }
+                    }
+                }
+                issues
+            },
+        )
     }
 
     /// Fetch all the comments of the issues from the repository.
@@ -58,97 +496,1147 @@ impl Fetcher {
         &self,
         issue_ids: Vec<u64>,
     ) -> UnboundedReceiver<Vec<models::issues::Comment>> {
-        self.fetch(|octocrab, owner, repo_name, start_time| async move {
-            let mut comments = Vec::new();
-            for issue_id in issue_ids {
-                match octocrab
-                    .issues(owner.clone(), repo_name.clone())
-                    .list_comments(issue_id)
-                    .since(start_time)
-                    .send()
-                    .await
-                {
-                    Ok(res) => comments.extend(res.items),
+        self.fetch_issue_comments_since(issue_ids, Some(self.start_time))
+    }
+
+    /// Fetch all the comments of the issues updated since the given time from the repository,
+    /// or all of them regardless of the time if `since` is `None`.
+    pub fn fetch_issue_comments_since(
+        &self,
+        issue_ids: Vec<u64>,
+        since: Option<DateTime<Utc>>,
+    ) -> UnboundedReceiver<Vec<models::issues::Comment>> {
+        let concurrency = self.issue_comments_concurrency;
+        let (prefetched, issue_ids) =
+            self.prefetched(issue_ids, |prefetched| &prefetched.issue_comments);
+        self.fetch(
+            "issue comments",
+            move |octocrab, owner, repo_name, _, end_time, failures| async move {
+                // Comments are listed in the creation order.
+                let after_end_time =
+                    |comment: &models::issues::Comment| comment.created_at > end_time;
+                // The prefetched comments are all the comments of the issues.
+                let mut comments: Vec<_> = prefetched
+                    .into_iter()
+                    .filter(|comment| {
+                        let updated_at = comment.updated_at.unwrap_or(comment.created_at);
+                        since.is_none_or(|since| updated_at >= since) && !after_end_time(comment)
+                    })
+                    .collect();
+                let (octocrab, owner, repo_name, failures, after_end_time) =
+                    (&octocrab, &owner, &repo_name, &failures, &after_end_time);
+                let fetched: Vec<_> = stream::iter(issue_ids)
+                    .map(|issue_id| async move {
+                        let route = octocrab.list_route(
+                            &format!(
+                                "/repos/{}/{}/issues/{}/comments",
+                                owner, repo_name, issue_id
+                            ),
+                            &since
+                                .map(|since| ("since", timestamp(since)))
+                                .into_iter()
+                                .collect::<Vec<_>>(),
+                        );
+                        let first_page = octocrab.retry(|| octocrab.get(&route)).await;
+                        match all_pages_until(octocrab, first_page, after_end_time).await {
+                            Ok(res) => res,
+                            Err(err) => {
+                                error!(
+                                    "failed to fetch issue comments from {}/{}#{}: {}",
+                                    owner, repo_name, issue_id, err
+                                );
+                                failures.record(format!("issue comments #{}", issue_id));
+                                vec![]
+                            }
+                        }
+                    })
+                    .buffer_unordered(concurrency)
+                    .concat()
+                    .await;
+                comments.extend(fetched);
+                comments
+            },
+        )
+    }
+
+    /// Fetch all the comments of the pull requests from the repository.
+    pub fn fetch_pull_request_comments(&self) -> UnboundedReceiver<Vec<models::pulls::Comment>> {
+        let prefetched = self
+            .prefetched
+            .as_ref()
+            .and_then(|prefetched| prefetched.lock().unwrap().pull_request_comments.clone());
+        self.fetch(
+            "pull request comments",
+            move |octocrab, owner, repo_name, start_time, end_time, failures| async move {
+                if let Some(comments) = prefetched {
+                    return comments
+                        .into_iter()
+                        .filter(|comment| {
+                            comment.updated_at >= start_time && comment.created_at <= end_time
+                        })
+                        .collect();
+                }
+                let route = octocrab.list_route(
+                    &format!("/repos/{}/{}/pulls/comments", owner, repo_name),
+                    &[
+                        ("since", timestamp(start_time)),
+                        ("sort", "created".to_string()),
+                        ("direction", "asc".to_string()),
+                    ],
+                );
+                let first_page = octocrab.retry(|| octocrab.get(&route)).await;
+                let after_end_time =
+                    |comment: &models::pulls::Comment| comment.created_at > end_time;
+                match all_pages_until(&octocrab, first_page, after_end_time).await {
+                    Ok(comments) => comments,
                     Err(err) => {
                         error!(
-                            "failed to fetch issue comments from {}/{}#{}: {}",
-                            owner, repo_name, issue_id, err
+                            "failed to fetch pull request comments from {}/{}: {}",
+                            owner, repo_name, err
                         );
+                        failures.record_listing("pull request comments");
+                        vec![]
                     }
                 }
-            }
-            comments
-        })
+            },
+        )
     }
 
-    /// Fetch all the comments of the pull requests from the repository.
-    pub fn fetch_pull_request_comments(&self) -> UnboundedReceiver<Vec<models::pulls::Comment>> {
-        self.fetch(move |octocrab, owner, repo_name, start_time| async move {
-            match octocrab
-                .pulls(owner.clone(), repo_name.clone())
-                .list_comments(None)
-                .since(start_time)
-                .send()
-                .await
-            {
-                Ok(res) => res.items,
-                Err(err) => {
-                    error!(
-                        "failed to fetch pull request comments from {}/{}: {}",
-                        owner, repo_name, err
-                    );
-                    vec![]
+    /// Fetch all the pull requests targeting the given base branch from the repository.
+    pub fn fetch_pull_requests_by_base(
+        &self,
+        base_branch: String,
+    ) -> UnboundedReceiver<Vec<models::pulls::PullRequest>> {
+        self.fetch(
+            "base branch pull requests",
+            move |octocrab, owner, repo_name, _, _, failures| async move {
+                let route = octocrab.list_route(
+                    &format!("/repos/{}/{}/pulls", owner, repo_name),
+                    &[("state", "all".to_string()), ("base", base_branch.clone())],
+                );
+                let first_page = octocrab.retry(|| octocrab.get(&route)).await;
+                match all_pages(&octocrab, first_page).await {
+                    Ok(pull_requests) => pull_requests,
+                    Err(err) => {
+                        error!(
+                            "failed to fetch pull requests based on '{}' from {}/{}: {}",
+                            base_branch, owner, repo_name, err
+                        );
+                        failures
+                            .record_listing(format!("pull requests based on '{}'", base_branch));
+                        vec![]
+                    }
                 }
-            }
-        })
+            },
+        )
     }
 
-    /// Fetch all the reviews of the pull requests from the repository.
-    pub fn fetch_pull_request_reviews(
+    /// Fetch all the draft pull requests created since the given time from the repository,
+    /// which tells whether a pull request is a draft by its current state.
+    pub fn fetch_draft_pull_requests(
         &self,
-        pull_request_ids: Vec<u64>,
-    ) -> UnboundedReceiver<Vec<models::pulls::Review>> {
-        self.fetch(move |octocrab, owner, repo_name, _| async move {
-            let mut reviews = Vec::new();
-            for pull_request_id in pull_request_ids {
-                match octocrab
-                    .pulls(owner.clone(), repo_name.clone())
-                    .list_reviews(pull_request_id)
-                    .send()
-                    .await
-                {
-                    Ok(res) => reviews.extend(res.items),
+        created_since: DateTime<Utc>,
+    ) -> UnboundedReceiver<Vec<models::pulls::PullRequest>> {
+        self.fetch(
+            "draft pull requests",
+            move |octocrab, owner, repo_name, _, _, failures| async move {
+                // Listed in the reverse creation order to stop once passing the time.
+                let route = octocrab.list_route(
+                    &format!("/repos/{}/{}/pulls", owner, repo_name),
+                    &[
+                        ("state", "all".to_string()),
+                        ("sort", "created".to_string()),
+                        ("direction", "desc".to_string()),
+                    ],
+                );
+                let before_created_since = |pull_request: &models::pulls::PullRequest| {
+                    pull_request
+                        .created_at
+                        .is_some_and(|created_at| created_at < created_since)
+                };
+                let first_page = octocrab.retry(|| octocrab.get(&route)).await;
+                match all_pages_until(&octocrab, first_page, before_created_since).await {
+                    Ok(pull_requests) => pull_requests
+                        .into_iter()
+                        .filter(|pull_request| pull_request.draft == Some(true))
+                        .collect(),
                     Err(err) => {
                         error!(
-                            "failed to fetch pull request reviews from {}/{}#{}: {}",
-                            owner, repo_name, pull_request_id, err
+                            "failed to fetch draft pull requests from {}/{}: {}",
+                            owner, repo_name, err
                         );
+                        failures.record_listing("draft pull requests");
+                        vec![]
                     }
                 }
-            }
-            reviews
-        })
+            },
+        )
     }
 
-    fn fetch<T, F, R>(&self, fetch_fn: F) -> UnboundedReceiver<Vec<T>>
+    /// Fetch the details of the pull requests from the repository, e.g. the lines changed.
+    pub fn fetch_pull_requests(
+        &self,
+        pull_request_ids: Vec<u64>,
+    ) -> UnboundedReceiver<Vec<models::pulls::PullRequest>> {
+        self.fetch(
+            "pull request details",
+            move |octocrab, owner, repo_name, _, _, failures| async move {
+                let mut pull_requests = Vec::new();
+                for pull_request_id in pull_request_ids {
+                    let route = format!("/repos/{}/{}/pulls/{}", owner, repo_name, pull_request_id);
+                    match octocrab
+                        .retry(|| octocrab.get::<models::pulls::PullRequest>(&route))
+                        .await
+                    {
+                        Ok(pull_request) => pull_requests.push(pull_request),
+                        Err(err) => {
+                            error!(
+                                "failed to fetch pull request from {}/{}#{}: {}",
+                                owner, repo_name, pull_request_id, err
+                            );
+                            failures.record(format!("pull request #{}", pull_request_id));
+                        }
+                    }
+                }
+                pull_requests
+            },
+        )
+    }
+
+    /// Fetch all the reviews of the pull requests from the repository, given with their
+    /// updated times. The reviews API has no `since`, so the PRs not updated since the
+    /// start time are skipped instead, which can't have any review submitted since then.
+    pub fn fetch_pull_request_reviews(
+        &self,
+        pull_requests: Vec<(u64, DateTime<Utc>)>,
+    ) -> UnboundedReceiver<Vec<models::pulls::Review>> {
+        let (pull_requests, stale): (Vec<_>, Vec<_>) = pull_requests
+            .into_iter()
+            .partition(|(_, updated_at)| *updated_at >= self.start_time);
+        if !stale.is_empty() {
+            debug!(
+                "skip fetching reviews of {} pull requests not updated since {}",
+                stale.len(),
+                self.start_time
+            );
+        }
+        let pull_request_ids = pull_requests
+            .into_iter()
+            .map(|(pull_request_id, _)| pull_request_id)
+            .collect();
+        let (prefetched, pull_request_ids) = self.prefetched(pull_request_ids, |prefetched| {
+            &prefetched.pull_request_reviews
+        });
+        self.fetch(
+            "pull request reviews",
+            move |octocrab, owner, repo_name, _, end_time, failures| async move {
+                // Reviews are listed in the submission order.
+                let after_end_time = |review: &models::pulls::Review| {
+                    review
+                        .submitted_at
+                        .is_some_and(|submitted_at| submitted_at > end_time)
+                };
+                let mut reviews: Vec<_> = prefetched
+                    .into_iter()
+                    .filter(|review| !after_end_time(review))
+                    .collect();
+                for pull_request_id in pull_request_ids {
+                    let route = octocrab.list_route(
+                        &format!(
+                            "/repos/{}/{}/pulls/{}/reviews",
+                            owner, repo_name, pull_request_id
+                        ),
+                        &[],
+                    );
+                    let first_page = octocrab.retry(|| octocrab.get(&route)).await;
+                    match all_pages_until(&octocrab, first_page, after_end_time).await {
+                        Ok(res) => reviews.extend(res),
+                        Err(err) => {
+                            error!(
+                                "failed to fetch pull request reviews from {}/{}#{}: {}",
+                                owner, repo_name, pull_request_id, err
+                            );
+                            failures.record(format!("pull request reviews #{}", pull_request_id));
+                        }
+                    }
+                }
+                reviews
+            },
+        )
+    }
+
+    /// Fetch all the timeline events of the issues (including PRs) from the repository,
+    /// paired with the number of the issue they belong to.
+    pub fn fetch_timeline_events(
+        &self,
+        issue_ids: Vec<u64>,
+    ) -> UnboundedReceiver<Vec<(u64, models::timelines::TimelineEvent)>> {
+        self.fetch(
+            "timeline events",
+            move |octocrab, owner, repo_name, _, end_time, failures| async move {
+                // Timeline events are listed in the chronological order.
+                let after_end_time = |event: &models::timelines::TimelineEvent| {
+                    event
+                        .created_at
+                        .is_some_and(|created_at| created_at > end_time)
+                };
+                let mut events = Vec::new();
+                for issue_id in issue_ids {
+                    let route = octocrab.list_route(
+                        &format!(
+                            "/repos/{}/{}/issues/{}/timeline",
+                            owner, repo_name, issue_id
+                        ),
+                        &[],
+                    );
+                    let first_page = octocrab.retry(|| octocrab.get(&route)).await;
+                    match all_pages_until(&octocrab, first_page, after_end_time).await {
+                        Ok(res) => events.extend(res.into_iter().map(|event| (issue_id, event))),
+                        Err(err) => {
+                            error!(
+                                "failed to fetch timeline events from {}/{}#{}: {}",
+                                owner, repo_name, issue_id, err
+                            );
+                            failures.record(format!("timeline events #{}", issue_id));
+                        }
+                    }
+                }
+                events
+            },
+        )
+    }
+
+    /// Fetch all the commits of the pull requests from the repository, paired with the
+    /// number of the pull request they belong to.
+    pub fn fetch_pull_request_commits(
+        &self,
+        pull_request_ids: Vec<u64>,
+    ) -> UnboundedReceiver<Vec<(u64, models::repos::RepoCommit)>> {
+        self.fetch(
+            "pull request commits",
+            move |octocrab, owner, repo_name, _, _, failures| async move {
+                let mut commits = Vec::new();
+                for pull_request_id in pull_request_ids {
+                    let route = octocrab.list_route(
+                        &format!(
+                            "/repos/{}/{}/pulls/{}/commits",
+                            owner, repo_name, pull_request_id
+                        ),
+                        &[],
+                    );
+                    let first_page = octocrab
+                        .retry(|| octocrab.get::<Page<models::repos::RepoCommit>>(&route))
+                        .await;
+                    match all_pages(&octocrab, first_page).await {
+                        Ok(res) => {
+                            warn_if_truncated(
+                                &format!("commits of pull request #{}", pull_request_id),
+                                &owner,
+                                &repo_name,
+                                res.len(),
+                                PULL_REQUEST_COMMITS_CAP,
+                            );
+                            commits.extend(res.into_iter().map(|commit| (pull_request_id, commit)))
+                        }
+                        Err(err) => {
+                            error!(
+                                "failed to fetch pull request commits from {}/{}#{}: {}",
+                                owner, repo_name, pull_request_id, err
+                            );
+                            failures.record(format!("pull request commits #{}", pull_request_id));
+                        }
+                    }
+                }
+                commits
+            },
+        )
+    }
+
+    /// Fetch all the reactions to the issues, PRs and comments from the repository.
+    pub fn fetch_reactions(
+        &self,
+        items: Vec<Reactable>,
+    ) -> UnboundedReceiver<Vec<models::reactions::Reaction>> {
+        self.fetch(
+            "reactions",
+            move |octocrab, owner, repo_name, _, _, failures| async move {
+                let mut reactions = Vec::new();
+                for item in items {
+                    let route = octocrab.list_route(&item.path(&owner, &repo_name), &[]);
+                    let first_page = octocrab
+                        .retry(|| octocrab.get::<Page<models::reactions::Reaction>>(&route))
+                        .await;
+                    match all_pages(&octocrab, first_page).await {
+                        Ok(res) => reactions.extend(res),
+                        Err(err) => {
+                            error!(
+                                "failed to fetch reactions to {:?} from {}/{}: {}",
+                                item, owner, repo_name, err
+                            );
+                            failures.record(format!("reactions to {:?}", item));
+                        }
+                    }
+                }
+                reactions
+            },
+        )
+    }
+
+    /// Get the prefetched items of the issues or PRs, and the ones not prefetched which
+    /// should be fetched by REST.
+    fn prefetched<T: Clone>(
+        &self,
+        numbers: Vec<u64>,
+        items: impl Fn(&Prefetched) -> &HashMap<u64, Vec<T>>,
+    ) -> (Vec<T>, Vec<u64>) {
+        let Some(prefetched) = &self.prefetched else {
+            return (vec![], numbers);
+        };
+        let prefetched = prefetched.lock().unwrap();
+        let items = items(&prefetched);
+        let (numbers, missing): (Vec<u64>, Vec<u64>) = numbers
+            .into_iter()
+            .partition(|number| items.contains_key(number));
+        (
+            numbers
+                .iter()
+                .flat_map(|number| items[number].iter().cloned())
+                .collect(),
+            missing,
+        )
+    }
+
+    fn fetch<T, F, R>(&self, listing: &'static str, fetch_fn: F) -> UnboundedReceiver<Vec<T>>
     where
-        T: 'static + Send,
-        F: 'static + Send + FnOnce(Arc<Octocrab>, String, String, DateTime<Utc>) -> R,
+        T: 'static + Send + DeserializeOwned,
+        F: 'static
+            + Send
+            + FnOnce(Client, String, String, DateTime<Utc>, DateTime<Utc>, Failures) -> R,
         R: Send + Future<Output = Vec<T>>,
     {
         let (owner, repo_name) = (self.repo.0.clone(), self.repo.1.clone());
         info!(
-            "fetching {} data from '{}/{}'",
-            type_name::<T>(),
-            self.repo.0,
-            self.repo.1,
+            "fetching {} from '{}/{}'",
+            listing, self.repo.0, self.repo.1,
         );
         let (tx, rx) = unbounded_channel();
-        let octocrab = self.octocrab.clone();
-        let start_time = self.start_time.clone();
-        tokio::spawn(
-            async move { tx.send(fetch_fn(octocrab, owner, repo_name, start_time).await) },
-        );
+        if let Some(fixtures_dir) = &self.fixtures_dir {
+            let fixture = fixtures_dir
+                .join(&owner)
+                .join(&repo_name)
+                .join(format!("{}.json", listing.replace(' ', "_")));
+            let _ = tx.send(read_fixture(&fixture, &self.failures));
+            return rx;
+        }
+        let mut octocrab = self.client.clone();
+        if let Some(progress) = &mut octocrab.progress {
+            progress.listing = listing;
+        }
+        let (start_time, end_time) = (self.start_time, self.end_time);
+        let failures = self.failures.clone();
+        tokio::spawn(async move {
+            tx.send(fetch_fn(octocrab, owner, repo_name, start_time, end_time, failures).await)
+        });
         rx
     }
 }
+
+/// Read the items of a listing from the JSON fixture file, which is an array of the items
+/// as returned by GitHub. A missing fixture means no items, while an invalid one is
+/// recorded as a failed listing.
+fn read_fixture<T: DeserializeOwned>(fixture: &Path, failures: &Failures) -> Vec<T> {
+    if !fixture.exists() {
+        info!("no fixture at '{}', treated as empty", fixture.display());
+        return Vec::new();
+    }
+    let items = fs::read_to_string(fixture)
+        .map_err(|err| err.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|err| err.to_string()));
+    match items {
+        Ok(items) => items,
+        Err(err) => {
+            error!("failed to read fixture '{}': {}", fixture.display(), err);
+            failures.record_listing(fixture.display().to_string());
+            Vec::new()
+        }
+    }
+}
+
+/// Fetch the full names of all the repos of the org, e.g. "tikv/tikv", excluding the
+/// archived ones unless `include_archived` is set. The base URL is the one the client is
+/// built with, if any, and the page size is clamped like `Fetcher::set_page_size`.
+pub async fn fetch_org_repos(
+    octocrab: Octocrab,
+    org: &str,
+    retry: Retry,
+    base_url: Option<&str>,
+    page_size: u8,
+    include_archived: bool,
+) -> crate::error::Result<Vec<String>> {
+    let client = Client {
+        octocrab: Arc::new(octocrab),
+        retry,
+        progress: None,
+        base_path: base_url.map(base_path).unwrap_or_default(),
+        page_size: page_size.clamp(1, MAX_PAGE_SIZE),
+    };
+    let route = client.list_route(
+        &format!("/orgs/{}/repos", org),
+        &[("type", "all".to_string())],
+    );
+    let first_page = client.retry(|| client.get(&route)).await;
+    let repos: Vec<models::Repository> = all_pages(&client, first_page).await?;
+    Ok(repos
+        .into_iter()
+        .filter(|repo| include_archived || !repo.archived.unwrap_or(false))
+        .filter_map(|repo| repo.full_name)
+        .collect())
+}
+
+/// Get the OAuth scopes of the token told by the `X-OAuth-Scopes` header, e.g. "repo".
+/// `None` if the token has no scopes, e.g. a fine-grained token or a GitHub App token,
+/// whose access is granted per repo instead.
+pub async fn fetch_token_scopes(
+    octocrab: Octocrab,
+    retry: Retry,
+) -> crate::error::Result<Option<Vec<String>>> {
+    let client = Client {
+        octocrab: Arc::new(octocrab),
+        retry,
+        progress: None,
+        base_path: String::new(),
+        page_size: MAX_PAGE_SIZE,
+    };
+    let response = client
+        .retry(|| async {
+            let response = client.timeout(client.octocrab._get("/user")).await?;
+            let status = response.status();
+            Ok(octocrab::map_github_error(response)
+                .await
+                .map_err(|err| with_status(err, status))?)
+        })
+        .await?;
+    Ok(response
+        .headers()
+        .get("x-oauth-scopes")
+        .and_then(|scopes| scopes.to_str().ok())
+        .map(|scopes| {
+            scopes
+                .split(',')
+                .map(|scope| scope.trim().to_string())
+                .filter(|scope| !scope.is_empty())
+                .collect()
+        }))
+}
+
+/// Tell why the repo can't be accessed if GitHub denies it. GitHub answers 404 rather
+/// than 403 for a private repo the token has no access to, so a missing repo looks the
+/// same.
+fn access_denied_hint(err: &Error) -> Option<&'static str> {
+    let StatusError { status, source } = status_error(err)?;
+    match status.as_u16() {
+        404 => Some(
+            "the repo is not found or the token has no access to it, a classic token needs \
+             the `repo` scope for the private repos, and a fine-grained token needs the repo \
+             granted with the read-only `Metadata`, `Issues` and `Pull requests` permissions",
+        ),
+        403 if !source.message.to_lowercase().contains("rate limit") => Some(
+            "the token is forbidden to access the repo, e.g. it's not authorized for the \
+             SAML SSO of the org, or the org restricts the access of the tokens",
+        ),
+        _ => None,
+    }
+}
+
+/// Collect the items of the first page and all the following pages by the `next` links.
+async fn all_pages<T: DeserializeOwned>(
+    octocrab: &Client,
+    first_page: crate::error::Result<Page<T>>,
+) -> crate::error::Result<Vec<T>> {
+    all_pages_until(octocrab, first_page, |_| false).await
+}
+
+/// Collect the items like `all_pages`, but for a listing in the chronological order, stop
+/// following the `next` links once a page ends after the time range, and drop the items
+/// after the time range.
+async fn all_pages_until<T, F>(
+    octocrab: &Client,
+    first_page: crate::error::Result<Page<T>>,
+    after_time_range: F,
+) -> crate::error::Result<Vec<T>>
+where
+    T: DeserializeOwned,
+    F: Fn(&T) -> bool,
+{
+    let mut page = first_page?;
+    let mut items = Vec::new();
+    loop {
+        let page_items = page.take_items();
+        if let Some(progress) = &octocrab.progress {
+            progress.report(page_items.len());
+        }
+        let passed = page_items.last().is_some_and(&after_time_range);
+        items.extend(
+            page_items
+                .into_iter()
+                .filter(|item| !after_time_range(item)),
+        );
+        if passed {
+            break;
+        }
+        match page
+            .next
+            .as_ref()
+            .map(|next| next_route(next, &octocrab.base_path))
+        {
+            Some(next) => page = octocrab.retry(|| octocrab.get(&next)).await?,
+            None => break,
+        }
+    }
+    Ok(items)
+}
+
+/// Get the path of the base URL without the trailing slash, e.g. "/api/v3" of
+/// "https://github.example.com/api/v3/", which is empty for the public GitHub.
+fn base_path(base_url: &str) -> String {
+    base_url
+        .parse::<Uri>()
+        .map(|base_url| base_url.path().trim_end_matches('/').to_string())
+        .unwrap_or_default()
+}
+
+/// Resolve the `next` link of a page into the route to request. Some GitHub Enterprise
+/// Server instances return the links relative to the host like "/api/v3/repos/...",
+/// which would stop the pagination or request a wrong URL as is, since the base URL is
+/// prepended to the routes. So the path of the base URL is stripped from them to be
+/// relative to the GitHub API, while the absolute links are kept as is.
+fn next_route(next: &Uri, base_path: &str) -> String {
+    if next.scheme().is_some() {
+        return next.to_string();
+    }
+    let path_and_query = next.path_and_query().map_or("/", |path| path.as_str());
+    let route = path_and_query
+        .strip_prefix(base_path)
+        .filter(|route| route.starts_with('/'))
+        .unwrap_or(path_and_query);
+    debug!("resolve relative next link '{}' to '{}'", next, route);
+    route.to_string()
+}
+
+/// Warn if the fetched listing has reached the cap of GitHub, which means the result is
+/// likely truncated and the time range should be split into smaller chunks. The page
+/// size is not a cap since all the pages are followed, but GitHub stops listing past a
+/// hard cap for some endpoints:
+///
+/// - `GET /repos/{owner}/{repo}/pulls/{number}/commits`: 250 commits, see
+///   `PULL_REQUEST_COMMITS_CAP`.
+/// - `GET /repos/{owner}/{repo}/pulls/{number}/files`: 3000 files, not fetched.
+/// - `GET /search/issues`: 1000 results per query, not fetched.
+///
+/// The issues, comments, reviews, timeline events and reactions listings have no cap.
+fn warn_if_truncated(listing: &str, owner: &str, repo_name: &str, fetched: usize, cap: usize) {
+    if fetched >= cap {
+        warn!(
+            "fetched {} {} from {}/{}, which reaches the cap of {} and is likely truncated, consider splitting the time range into smaller chunks",
+            fetched, listing, owner, repo_name, cap
+        );
+    }
+}
+
+/// Percent-encode the query value, e.g. a base branch with special characters.
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Format the time as the ISO 8601 timestamp GitHub accepts, e.g. for `since`.
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Get the time the rate limit resets if it's exhausted, i.e. no requests remaining, by
+/// the `X-RateLimit-Remaining` and `X-RateLimit-Reset` headers.
+fn rate_limit_reset(remaining: Option<&str>, reset: Option<&str>) -> Option<DateTime<Utc>> {
+    if remaining?.trim().parse::<u64>().ok()? > 0 {
+        return None;
+    }
+    Utc.timestamp_opt(reset?.trim().parse().ok()?, 0).single()
+}
+
+/// The error GitHub responded with along with the HTTP status of the response, which the
+/// `GitHubError` of octocrab doesn't carry.
+#[derive(Debug)]
+struct StatusError {
+    status: StatusCode,
+    source: octocrab::GitHubError,
+}
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.source, self.status)
+    }
+}
+
+impl std::error::Error for StatusError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Attach the HTTP status of the response to the error GitHub responded with, the other
+/// errors are kept as is.
+fn with_status(err: octocrab::Error, status: StatusCode) -> octocrab::Error {
+    match err {
+        octocrab::Error::GitHub { source, backtrace } => octocrab::Error::Other {
+            source: Box::new(StatusError { status, source }),
+            backtrace,
+        },
+        err => err,
+    }
+}
+
+/// Get the error GitHub responded with along with the HTTP status, if any.
+fn status_error(err: &Error) -> Option<&StatusError> {
+    match err {
+        Error::GitHub(octocrab::Error::Other { source, .. }) => source.downcast_ref(),
+        _ => None,
+    }
+}
+
+/// Whether GitHub rejected the request by the secondary rate limit.
+fn is_secondary_rate_limit(source: &octocrab::GitHubError) -> bool {
+    source
+        .message
+        .to_lowercase()
+        .contains("secondary rate limit")
+}
+
+/// Whether the error is transient and worth retrying.
+fn is_transient(err: &Error) -> bool {
+    if let Some(err) = status_error(err) {
+        return matches!(err.status.as_u16(), 429 | 502 | 503 | 504)
+            || (err.status == StatusCode::FORBIDDEN && is_secondary_rate_limit(&err.source));
+    }
+    match err {
+        Error::Timeout(_) => true,
+        // The GraphQL requests fail without the HTTP status.
+        Error::GitHub(octocrab::Error::GitHub { source, .. }) => is_secondary_rate_limit(source),
+        Error::GitHub(octocrab::Error::Hyper { .. } | octocrab::Error::Service { .. }) => true,
+        _ => false,
+    }
+}
+
+/// Get the delay before the next retry, which doubles after each retry with a jitter to
+/// avoid retrying in lockstep.
+fn backoff(base_delay: Duration, retries: u32) -> Duration {
+    let delay = base_delay.saturating_mul(1 << retries.min(16));
+    let jitter = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.subsec_nanos() % 1000);
+    delay.saturating_add((delay / 2).mul_f64(jitter as f64 / 1000.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    /// The error of GitHub responded without the HTTP status, e.g. to a GraphQL query.
+    fn github_error(message: &str) -> Error {
+        Error::GitHub(octocrab_error(message))
+    }
+
+    fn responded(status: u16, message: &str) -> Error {
+        let status = StatusCode::from_u16(status).unwrap();
+        Error::GitHub(with_status(octocrab_error(message), status))
+    }
+
+    /// The error is non-exhaustive, so it can only be built from a response like GitHub
+    /// does.
+    fn octocrab_error(message: &str) -> octocrab::Error {
+        let response = http::Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body(serde_json::json!({ "message": message }).to_string().into())
+            .unwrap();
+        futures::executor::block_on(octocrab::map_github_error(response)).unwrap_err()
+    }
+
+    fn client(max_retries: u32) -> Client {
+        Client {
+            octocrab: Arc::new(Octocrab::default()),
+            retry: Retry {
+                max_retries,
+                base_delay: Duration::from_millis(1),
+                timeout: Duration::from_secs(1),
+            },
+            progress: None,
+            base_path: String::new(),
+            page_size: MAX_PAGE_SIZE,
+        }
+    }
+
+    /// Serve the pages of a listing at "/pages/1", "/pages/2" and so on, each linking to
+    /// the next one like GitHub does, and 404 for the others. Returns the client of the
+    /// server and the paths requested.
+    async fn serve_pages(pages: Vec<Vec<u64>>) -> (Client, Arc<Mutex<Vec<String>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let (served, link_base_url) = (requested.clone(), base_url.clone());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                served.lock().unwrap().push(path.clone());
+                let page = path
+                    .strip_prefix("/pages/")
+                    .and_then(|page| page.parse::<usize>().ok())
+                    .filter(|page| (1..=pages.len()).contains(page));
+                let (status, link, body) = match page {
+                    Some(page) => (
+                        "200 OK",
+                        if page < pages.len() {
+                            format!(
+                                "Link: <{}/pages/{}>; rel=\"next\"\r\n",
+                                link_base_url,
+                                page + 1
+                            )
+                        } else {
+                            String::new()
+                        },
+                        serde_json::json!(pages[page - 1]).to_string(),
+                    ),
+                    None => (
+                        "404 Not Found",
+                        String::new(),
+                        serde_json::json!({ "message": "Not Found" }).to_string(),
+                    ),
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    link,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            }
+        });
+        let octocrab = Octocrab::builder()
+            .base_uri(base_url)
+            .unwrap()
+            .build()
+            .unwrap();
+        let client = Client {
+            octocrab: Arc::new(octocrab),
+            ..client(0)
+        };
+        (client, requested)
+    }
+
+    /// Create the fetcher of "tikv/tikv" from 2024-01-01 to 2024-01-08 with the client.
+    fn fetcher(client: Client) -> Fetcher {
+        let time = |time: &str| time.parse::<DateTime<Utc>>().unwrap();
+        Fetcher {
+            client,
+            ..Fetcher::new(
+                Octocrab::default(),
+                "tikv/tikv",
+                time("2024-01-01T00:00:00Z"),
+                time("2024-01-08T00:00:00Z"),
+                Retry {
+                    max_retries: 0,
+                    base_delay: Duration::from_millis(1),
+                    timeout: Duration::from_secs(1),
+                },
+            )
+            .unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_all_pages() {
+        let (client, requested) = serve_pages(vec![vec![1, 2], vec![3, 4], vec![5]]).await;
+        let first_page = client.retry(|| client.get("/pages/1")).await;
+        let items: Vec<u64> = all_pages(&client, first_page).await.unwrap();
+        assert_eq!(items, [1, 2, 3, 4, 5]);
+        assert_eq!(
+            *requested.lock().unwrap(),
+            ["/pages/1", "/pages/2", "/pages/3"]
+        );
+
+        // A failed page fails the whole listing rather than returning it partially.
+        let mut first_page = Page::default();
+        first_page.items = vec![1, 2];
+        first_page.next = Some("/pages/4".parse().unwrap());
+        let result: crate::error::Result<Vec<u64>> = all_pages(&client, Ok(first_page)).await;
+        assert!(status_error(&result.unwrap_err()).is_some_and(|err| err.status == 404));
+        let result: crate::error::Result<Vec<u64>> =
+            all_pages(&client, Err(Error::Timeout(Duration::from_secs(1)))).await;
+        assert!(matches!(result, Err(Error::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn test_all_pages_until() {
+        let pages = vec![vec![1, 2], vec![3, 5], vec![6, 7]];
+        let (client, requested) = serve_pages(pages).await;
+        let after_time_range = |item: &u64| *item > 4;
+
+        // The page ending after the time range is the last one requested, and the items
+        // after the time range in it are dropped.
+        let first_page = client.retry(|| client.get("/pages/1")).await;
+        let items = all_pages_until(&client, first_page, after_time_range)
+            .await
+            .unwrap();
+        assert_eq!(items, [1, 2, 3]);
+        assert_eq!(*requested.lock().unwrap(), ["/pages/1", "/pages/2"]);
+
+        // The first page ending after the time range stops right away.
+        requested.lock().unwrap().clear();
+        let first_page = client.retry(|| client.get("/pages/3")).await;
+        let items = all_pages_until(&client, first_page, after_time_range)
+            .await
+            .unwrap();
+        assert!(items.is_empty());
+        assert_eq!(*requested.lock().unwrap(), ["/pages/3"]);
+
+        // A page ending within the time range follows the next link as usual.
+        requested.lock().unwrap().clear();
+        let first_page = client.retry(|| client.get("/pages/1")).await;
+        let items: Vec<u64> = all_pages_until(&client, first_page, |item: &u64| *item > 6)
+            .await
+            .unwrap();
+        assert_eq!(items, [1, 2, 3, 5, 6]);
+        assert_eq!(requested.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_issues_by_state() {
+        for (issue_state, state) in [
+            (IssueState::Open, "open"),
+            (IssueState::Closed, "closed"),
+            (IssueState::All, "all"),
+        ] {
+            let (client, requested) = serve_pages(vec![]).await;
+            let mut fetcher = fetcher(client);
+            fetcher.set_issue_state(issue_state);
+            fetcher.fetch_issues().recv().await.unwrap();
+            let requested = requested.lock().unwrap();
+            assert_eq!(requested.len(), 1);
+            assert!(requested[0].starts_with("/repos/tikv/tikv/issues?"));
+            assert!(requested[0].contains(&format!("state={}&", state)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_skip_reviews_of_stale_pull_requests() {
+        let (client, requested) = serve_pages(vec![]).await;
+        let fetcher = fetcher(client);
+        let time = |time: &str| time.parse::<DateTime<Utc>>().unwrap();
+        let pull_requests = vec![
+            (1, time("2023-12-31T00:00:00Z")),
+            (2, time("2024-01-01T00:00:00Z")),
+            (3, time("2024-01-02T00:00:00Z")),
+        ];
+        fetcher
+            .fetch_pull_request_reviews(pull_requests)
+            .recv()
+            .await
+            .unwrap();
+        // Only the PRs updated since the start time are listed.
+        let requested: Vec<_> = requested
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|path| path.split('?').next().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            requested,
+            [
+                "/repos/tikv/tikv/pulls/2/reviews",
+                "/repos/tikv/tikv/pulls/3/reviews"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_reset() {
+        let reset = Utc.timestamp_opt(1704067200, 0).single();
+        assert_eq!(rate_limit_reset(Some("0"), Some("1704067200")), reset);
+        assert_eq!(rate_limit_reset(Some(" 0 "), Some(" 1704067200 ")), reset);
+        // Not exhausted yet.
+        assert_eq!(rate_limit_reset(Some("1"), Some("1704067200")), None);
+        assert_eq!(rate_limit_reset(Some("4999"), Some("1704067200")), None);
+        // Missing or malformed headers, e.g. of a GitHub Enterprise Server instance
+        // without the rate limit enabled.
+        assert_eq!(rate_limit_reset(None, None), None);
+        assert_eq!(rate_limit_reset(None, Some("1704067200")), None);
+        assert_eq!(rate_limit_reset(Some("0"), None), None);
+        assert_eq!(rate_limit_reset(Some("-1"), Some("1704067200")), None);
+        assert_eq!(rate_limit_reset(Some("0"), Some("soon")), None);
+    }
+
+    #[test]
+    fn test_is_transient() {
+        for status in [429, 502, 503, 504] {
+            assert!(is_transient(&responded(status, "oops")), "{}", status);
+        }
+        assert!(is_transient(&responded(
+            403,
+            "You have exceeded a secondary rate limit."
+        )));
+        assert!(!is_transient(&responded(403, "Resource not accessible")));
+        assert!(!is_transient(&responded(404, "Not Found")));
+        assert!(!is_transient(&responded(500, "oops")));
+        assert!(is_transient(&github_error(
+            "You have exceeded a secondary rate limit."
+        )));
+        assert!(!is_transient(&github_error("Bad credentials")));
+    }
+
+    #[test]
+    fn test_access_denied_hint() {
+        assert!(access_denied_hint(&responded(404, "Not Found")).is_some());
+        assert!(access_denied_hint(&responded(403, "Resource not accessible")).is_some());
+        assert!(access_denied_hint(&responded(403, "API rate limit exceeded")).is_none());
+        assert!(access_denied_hint(&responded(502, "Bad Gateway")).is_none());
+        assert!(access_denied_hint(&github_error("Not Found")).is_none());
+    }
+
+    #[test]
+    fn test_backoff() {
+        let base_delay = Duration::from_millis(100);
+        for retries in 0..5 {
+            let delay = backoff(base_delay, retries);
+            let expected = base_delay * 2u32.pow(retries);
+            assert!(
+                expected <= delay && delay <= expected * 3 / 2,
+                "{:?}",
+                delay
+            );
+        }
+        // The delay saturates rather than overflowing.
+        assert_eq!(backoff(Duration::MAX, 100), Duration::MAX);
+    }
+
+    #[test]
+    fn test_next_route() {
+        let next_route =
+            |next: &str, base_url: &str| next_route(&next.parse().unwrap(), &base_path(base_url));
+        // The absolute links are kept as is.
+        let absolute = "https://api.github.com/repositories/1/issues?per_page=100&page=2";
+        assert_eq!(next_route(absolute, "https://api.github.com"), absolute);
+        let absolute = "https://github.example.com/api/v3/repos/tikv/tikv/issues?page=2";
+        assert_eq!(
+            next_route(absolute, "https://github.example.com/api/v3"),
+            absolute
+        );
+        // The links relative to the GitHub API are kept as is.
+        assert_eq!(
+            next_route("/repos/tikv/tikv/issues", "https://api.github.com"),
+            "/repos/tikv/tikv/issues"
+        );
+        assert_eq!(
+            next_route(
+                "/repos/tikv/tikv/issues?state=all&page=2",
+                "https://api.github.com/"
+            ),
+            "/repos/tikv/tikv/issues?state=all&page=2"
+        );
+        // The links relative to the host of a GitHub Enterprise Server instance have the
+        // path of the base URL stripped, with the query kept.
+        for base_url in [
+            "https://github.example.com/api/v3",
+            "https://github.example.com/api/v3/",
+        ] {
+            assert_eq!(
+                next_route("/api/v3/repos/tikv/tikv/issues?page=2", base_url),
+                "/repos/tikv/tikv/issues?page=2"
+            );
+            // Only a whole segment of the path is stripped.
+            assert_eq!(
+                next_route("/api/v30/repos/tikv/tikv/issues", base_url),
+                "/api/v30/repos/tikv/tikv/issues"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient_errors() {
+        let client = client(3);
+        let attempts = Cell::new(0);
+        let result = client
+            .retry(|| async {
+                attempts.set(attempts.get() + 1);
+                if attempts.get() < 3 {
+                    Err(responded(502, "Bad Gateway"))
+                } else {
+                    Ok(attempts.get())
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up() {
+        let client = client(2);
+        let attempts = Cell::new(0);
+        let result: crate::error::Result<()> = client
+            .retry(|| async {
+                attempts.set(attempts.get() + 1);
+                Err(responded(503, "Service Unavailable"))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.get(), 3);
+
+        let attempts = Cell::new(0);
+        let result: crate::error::Result<()> = client
+            .retry(|| async {
+                attempts.set(attempts.get() + 1);
+                Err(responded(404, "Not Found"))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_timeout() {
+        let mut client = client(1);
+        client.retry.timeout = Duration::from_millis(10);
+        let attempts = Cell::new(0);
+        let result: crate::error::Result<()> = client
+            .retry(|| {
+                attempts.set(attempts.get() + 1);
+                client.timeout(async {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    Ok(())
+                })
+            })
+            .await;
+        assert!(matches!(result, Err(Error::Timeout(timeout)) if timeout == client.retry.timeout));
+        assert_eq!(attempts.get(), 2);
+    }
+}
@@ -0,0 +1,543 @@
+use std::{collections::HashMap, error::Error, sync::Mutex};
+
+use chrono::{DateTime, Utc};
+use octocrab::models;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+use super::{timestamp, Client, IssueState};
+
+type GraphqlError = Box<dyn Error + Send + Sync>;
+
+/// The login GitHub shows in place of the deleted users, which are `null` in GraphQL.
+const GHOST_LOGIN: &str = "ghost";
+
+/// The fields of the author of an issue, PR, comment or review.
+const AUTHOR_FIELDS: &str =
+    "author { login url avatarUrl ... on User { databaseId } ... on Bot { databaseId } }";
+
+/// The fields shared by the issues and PRs. An issue or PR rarely has more labels than a
+/// page holds, so the labels beyond it are dropped.
+const ISSUE_FIELDS: &str = "databaseId id number title body state url locked createdAt updatedAt \
+     closedAt labels(first: 100) { nodes { id name description color isDefault url } }";
+
+/// The comments, reviews and review comments fetched along with the issues and PRs by
+/// GraphQL, which are handed out to the listings coming after without any request. The
+/// ones of an issue or PR are missing if they are too many to fit in a single query,
+/// and should be fetched by REST instead.
+#[derive(Debug, Default)]
+pub(super) struct Prefetched {
+    pub(super) issue_comments: HashMap<u64, Vec<models::issues::Comment>>,
+    pub(super) pull_request_reviews: HashMap<u64, Vec<models::pulls::Review>>,
+    // `None` if the review comments of any PR are missing.
+    pub(super) pull_request_comments: Option<Vec<models::pulls::Comment>>,
+}
+
+impl Client {
+    /// Send the GraphQL query with the variables and retry it on transient errors. The
+    /// errors reported in the response body fail the query as well.
+    async fn graphql(&self, query: &str, variables: Value) -> Result<Value, GraphqlError> {
+        let payload = json!({ "query": query, "variables": variables });
+        let mut response: Value = self
+            .retry(|| self.timeout(self.octocrab.graphql(&payload)))
+            .await?;
+        if let Some(errors) = response.get("errors") {
+            return Err(format!("graphql errors: {}", errors).into());
+        }
+        Ok(response["data"].take())
+    }
+
+    /// Collect the nodes of all the pages of the connection at the path of the query data,
+    /// following the `endCursor` as the `after` variable. Stop once `until` tells a node
+    /// has passed the time range, which is not collected.
+    async fn all_nodes_until<F>(
+        &self,
+        query: &str,
+        mut variables: Value,
+        path: &[&str],
+        until: F,
+    ) -> Result<Vec<Value>, GraphqlError>
+    where
+        F: Fn(&Value) -> Result<bool, GraphqlError>,
+    {
+        let mut nodes = Vec::new();
+        loop {
+            let mut data = self.graphql(query, variables.clone()).await?;
+            let connection = path.iter().fold(&mut data, |data, key| &mut data[*key]);
+            let page_nodes = match connection["nodes"].take() {
+                Value::Array(page_nodes) => page_nodes,
+                _ => return Err(format!("no nodes at '{}'", path.join(".")).into()),
+            };
+            if let Some(progress) = &self.progress {
+                progress.report(page_nodes.len());
+            }
+            for node in page_nodes {
+                if until(&node)? {
+                    return Ok(nodes);
+                }
+                nodes.push(node);
+            }
+            let page_info = &connection["pageInfo"];
+            if page_info["hasNextPage"].as_bool() != Some(true) {
+                return Ok(nodes);
+            }
+            variables["after"] = page_info["endCursor"].clone();
+        }
+    }
+}
+
+/// Fetch the issues and PRs updated since the start time by GraphQL, along with their
+/// comments and reviews, which are kept in the prefetched for the later listings. The
+/// issues created after the end time are dropped like the REST listing does.
+pub(super) async fn fetch_issues(
+    client: &Client,
+    owner: &str,
+    repo_name: &str,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    issue_state: IssueState,
+    prefetched: &Mutex<Prefetched>,
+) -> Result<Vec<models::issues::Issue>, GraphqlError> {
+    let variables = json!({
+        "owner": owner,
+        "name": repo_name,
+        "after": null,
+    });
+    // The states are not filtered if null.
+    let (issue_states, pull_request_states) = match issue_state {
+        IssueState::Open => (json!(["OPEN"]), json!(["OPEN"])),
+        IssueState::Closed => (json!(["CLOSED"]), json!(["CLOSED", "MERGED"])),
+        IssueState::All => (Value::Null, Value::Null),
+    };
+
+    // The issues are listed in the creation order to stop once passing the end time.
+    let issues_query = format!(
+        "query($owner: String!, $name: String!, $since: DateTime!, $states: [IssueState!],
+               $after: String) {{
+            repository(owner: $owner, name: $name) {{
+                issues(first: 50, after: $after, filterBy: {{ since: $since, states: $states }},
+                       orderBy: {{ field: CREATED_AT, direction: ASC }}) {{
+                    pageInfo {{ hasNextPage endCursor }}
+                    nodes {{
+                        {ISSUE_FIELDS} {AUTHOR_FIELDS}
+                        comments(first: 100) {{
+                            totalCount
+                            pageInfo {{ hasNextPage }}
+                            nodes {{ databaseId id url body createdAt updatedAt {AUTHOR_FIELDS} }}
+                        }}
+                    }}
+                }}
+            }}
+        }}"
+    );
+    let mut issues_variables = variables.clone();
+    issues_variables["since"] = json!(timestamp(start_time));
+    issues_variables["states"] = issue_states;
+    let issue_nodes = client
+        .all_nodes_until(
+            &issues_query,
+            issues_variables,
+            &["repository", "issues"],
+            |node| Ok(parse::<DateTime<Utc>>(&node["createdAt"])? > end_time),
+        )
+        .await?;
+
+    // The PRs can't be filtered by the updated time, so they are listed in the reverse
+    // updated order to stop once passing the start time.
+    let pull_requests_query = format!(
+        "query($owner: String!, $name: String!, $states: [PullRequestState!],
+               $after: String) {{
+            repository(owner: $owner, name: $name) {{
+                pullRequests(first: 25, after: $after, states: $states,
+                             orderBy: {{ field: UPDATED_AT, direction: DESC }}) {{
+                    pageInfo {{ hasNextPage endCursor }}
+                    nodes {{
+                        {ISSUE_FIELDS} {AUTHOR_FIELDS}
+                        comments {{ totalCount }}
+                        reviews(first: 50) {{
+                            pageInfo {{ hasNextPage }}
+                            nodes {{
+                                databaseId id url body state submittedAt {AUTHOR_FIELDS}
+                                comments(first: 50) {{
+                                    pageInfo {{ hasNextPage }}
+                                    nodes {{
+                                        databaseId id url body createdAt updatedAt path
+                                        diffHunk position originalPosition authorAssociation
+                                        commit {{ oid }} originalCommit {{ oid }}
+                                        replyTo {{ databaseId }} {AUTHOR_FIELDS}
+                                    }}
+                                }}
+                            }}
+                        }}
+                    }}
+                }}
+            }}
+        }}"
+    );
+    let mut pull_requests_variables = variables;
+    pull_requests_variables["states"] = pull_request_states;
+    let pull_request_nodes = client
+        .all_nodes_until(
+            &pull_requests_query,
+            pull_requests_variables,
+            &["repository", "pullRequests"],
+            |node| Ok(parse::<DateTime<Utc>>(&node["updatedAt"])? < start_time),
+        )
+        .await?;
+
+    let mut issues = Vec::with_capacity(issue_nodes.len() + pull_request_nodes.len());
+    let mut fetched = Prefetched {
+        pull_request_comments: Some(Vec::new()),
+        ..Default::default()
+    };
+    for node in &issue_nodes {
+        let issue: models::issues::Issue = parse(&rest_issue(node, false))?;
+        if !has_next_page(&node["comments"]) {
+            let comments = nodes(&node["comments"])
+                .map(|comment| parse(&rest_issue_comment(comment)))
+                .collect::<Result<_, _>>()?;
+            fetched.issue_comments.insert(issue.number, comments);
+        }
+        issues.push(issue);
+    }
+    for node in &pull_request_nodes {
+        let pull_request: models::issues::Issue = parse(&rest_issue(node, true))?;
+        if pull_request.created_at > end_time {
+            continue;
+        }
+        let mut reviews = Vec::new();
+        for review in nodes(&node["reviews"]) {
+            reviews.push(parse(&rest_review(review))?);
+            if has_next_page(&review["comments"]) {
+                fetched.pull_request_comments = None;
+            }
+            if let Some(comments) = &mut fetched.pull_request_comments {
+                for comment in nodes(&review["comments"]) {
+                    comments.push(parse(&rest_review_comment(comment, review))?);
+                }
+            }
+        }
+        if has_next_page(&node["reviews"]) {
+            fetched.pull_request_comments = None;
+        } else {
+            fetched
+                .pull_request_reviews
+                .insert(pull_request.number, reviews);
+        }
+        issues.push(pull_request);
+    }
+    *prefetched.lock().unwrap() = fetched;
+    Ok(issues)
+}
+
+fn parse<T: DeserializeOwned>(value: &Value) -> Result<T, GraphqlError> {
+    Ok(T::deserialize(value)?)
+}
+
+fn nodes(connection: &Value) -> impl Iterator<Item = &Value> {
+    connection["nodes"].as_array().into_iter().flatten()
+}
+
+fn has_next_page(connection: &Value) -> bool {
+    connection["pageInfo"]["hasNextPage"].as_bool() == Some(true)
+}
+
+/// Convert the GraphQL author into the REST one. The URLs not in the GraphQL response
+/// are filled with the profile URL, and the deleted user becomes the "ghost" user as
+/// the REST API shows.
+fn rest_author(author: &Value) -> Value {
+    let login = author["login"].as_str().unwrap_or(GHOST_LOGIN);
+    let url = author["url"]
+        .as_str()
+        .map_or_else(|| format!("https://github.com/{}", login), String::from);
+    json!({
+        "login": login,
+        "id": author["databaseId"].as_u64().unwrap_or_default(),
+        "node_id": "",
+        "avatar_url": author["avatarUrl"].as_str().unwrap_or(&url),
+        "gravatar_id": "",
+        "url": url,
+        "html_url": url,
+        "followers_url": url,
+        "following_url": url,
+        "gists_url": url,
+        "starred_url": url,
+        "subscriptions_url": url,
+        "organizations_url": url,
+        "repos_url": url,
+        "events_url": url,
+        "received_events_url": url,
+        "type": if login.ends_with("[bot]") { "Bot" } else { "User" },
+        "site_admin": false,
+    })
+}
+
+/// Convert the GraphQL issue or PR into the REST issue, where a PR has the
+/// `pull_request` links to tell it apart from the issues.
+fn rest_issue(node: &Value, pull_request: bool) -> Value {
+    let url = &node["url"];
+    let mut issue = json!({
+        "id": node["databaseId"],
+        "node_id": node["id"],
+        "url": url,
+        "repository_url": url,
+        "labels_url": url,
+        "comments_url": url,
+        "events_url": url,
+        "html_url": url,
+        "number": node["number"],
+        // A merged PR is a closed issue.
+        "state": if node["state"] == "OPEN" { "open" } else { "closed" },
+        "title": node["title"],
+        "body": node["body"],
+        "user": rest_author(&node["author"]),
+        "labels": nodes(&node["labels"]).map(rest_label).collect::<Vec<_>>(),
+        "assignees": [],
+        "author_association": "NONE",
+        "locked": node["locked"],
+        "comments": node["comments"]["totalCount"],
+        "closed_at": node["closedAt"],
+        "created_at": node["createdAt"],
+        "updated_at": node["updatedAt"],
+    });
+    if pull_request {
+        issue["pull_request"] = json!({
+            "url": url,
+            "html_url": url,
+            "diff_url": url,
+            "patch_url": url,
+        });
+    }
+    issue
+}
+
+/// Convert the GraphQL label into the REST one. The GraphQL label has no database ID.
+fn rest_label(node: &Value) -> Value {
+    json!({
+        "id": 0,
+        "node_id": node["id"],
+        "url": node["url"],
+        "name": node["name"],
+        "description": node["description"],
+        "color": node["color"],
+        "default": node["isDefault"],
+    })
+}
+
+/// Convert the GraphQL issue comment into the REST one.
+fn rest_issue_comment(node: &Value) -> Value {
+    json!({
+        "id": node["databaseId"],
+        "node_id": node["id"],
+        "url": node["url"],
+        "html_url": node["url"],
+        "body": node["body"],
+        "user": rest_author(&node["author"]),
+        "created_at": node["createdAt"],
+        "updated_at": node["updatedAt"],
+    })
+}
+
+/// Convert the GraphQL PR review into the REST one. The pending reviews have no
+/// submitted time like the REST ones.
+fn rest_review(node: &Value) -> Value {
+    json!({
+        "id": node["databaseId"],
+        "node_id": node["id"],
+        "html_url": node["url"],
+        "user": (!node["author"].is_null()).then(|| rest_author(&node["author"])),
+        "body": node["body"],
+        "state": node["state"],
+        "submitted_at": node["submittedAt"],
+    })
+}
+
+/// Convert the GraphQL PR review comment of the review into the REST one.
+fn rest_review_comment(node: &Value, review: &Value) -> Value {
+    json!({
+        "url": node["url"],
+        "pull_request_review_id": review["databaseId"],
+        "id": node["databaseId"],
+        "node_id": node["id"],
+        "diff_hunk": node["diffHunk"],
+        "path": node["path"],
+        "position": node["position"],
+        "original_position": node["originalPosition"],
+        "commit_id": node["commit"]["oid"].as_str().unwrap_or_default(),
+        "original_commit_id": node["originalCommit"]["oid"].as_str().unwrap_or_default(),
+        "in_reply_to_id": node["replyTo"]["databaseId"],
+        "user": (!node["author"].is_null()).then(|| rest_author(&node["author"])),
+        "body": node["body"],
+        "created_at": node["createdAt"],
+        "updated_at": node["updatedAt"],
+        "html_url": node["url"],
+        "author_association": node["authorAssociation"],
+        "_links": {},
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, ConfigFormat};
+    use crate::stats::Stats;
+
+    fn label(name: &str) -> (Value, Value) {
+        let url = format!("https://github.com/tikv/tikv/labels/{}", name);
+        let graphql = json!({
+            "id": "LA_1",
+            "name": name,
+            "description": null,
+            "color": "d73a4a",
+            "isDefault": false,
+            "url": url,
+        });
+        let rest = json!({
+            "id": 1,
+            "node_id": "LA_1",
+            "url": url,
+            "name": name,
+            "description": null,
+            "color": "d73a4a",
+            "default": false,
+        });
+        (graphql, rest)
+    }
+
+    /// Get the node of the issue or PR in the GraphQL response along with the REST issue
+    /// of the same one.
+    fn issue(number: u64, login: &str, labels: &[&str], pull_request: bool) -> (Value, Value) {
+        let url = format!("https://github.com/tikv/tikv/issues/{}", number);
+        let (graphql_labels, rest_labels): (Vec<_>, Vec<_>) =
+            labels.iter().map(|name| label(name)).unzip();
+        let graphql = json!({
+            "databaseId": number,
+            "id": format!("I_{}", number),
+            "number": number,
+            "title": "",
+            "body": "",
+            "state": "OPEN",
+            "url": url,
+            "locked": false,
+            "createdAt": "2024-01-02T00:00:00Z",
+            "updatedAt": "2024-01-02T00:00:00Z",
+            "closedAt": null,
+            "labels": { "nodes": graphql_labels },
+            "author": { "login": login, "url": format!("https://github.com/{}", login) },
+            "comments": { "totalCount": 0, "pageInfo": { "hasNextPage": false }, "nodes": [] },
+        });
+        let user_url = format!("https://api.github.com/users/{}", login);
+        let mut rest = json!({
+            "id": number,
+            "node_id": format!("I_{}", number),
+            "url": url,
+            "repository_url": url,
+            "labels_url": url,
+            "comments_url": url,
+            "events_url": url,
+            "html_url": url,
+            "number": number,
+            "state": "open",
+            "title": "",
+            "body": "",
+            "user": {
+                "login": login,
+                "id": 1,
+                "node_id": "",
+                "avatar_url": user_url,
+                "gravatar_id": "",
+                "url": user_url,
+                "html_url": user_url,
+                "followers_url": user_url,
+                "following_url": user_url,
+                "gists_url": user_url,
+                "starred_url": user_url,
+                "subscriptions_url": user_url,
+                "organizations_url": user_url,
+                "repos_url": user_url,
+                "events_url": user_url,
+                "received_events_url": user_url,
+                "type": "User",
+                "site_admin": false,
+            },
+            "labels": rest_labels,
+            "assignees": [],
+            "author_association": "MEMBER",
+            "locked": false,
+            "comments": 0,
+            "created_at": "2024-01-02T00:00:00Z",
+            "updated_at": "2024-01-02T00:00:00Z",
+        });
+        if pull_request {
+            rest["pull_request"] = json!({
+                "url": url,
+                "html_url": url,
+                "diff_url": url,
+                "patch_url": url,
+            });
+        }
+        (graphql, rest)
+    }
+
+    #[test]
+    fn test_rest_issue_labels() {
+        let config = Config::from_reader(
+            "[review]\nusers = [\"alice\", \"bob\"]\nlgtm_comments = [\"lgtm\"]\n\
+             include_labels = [\"type/bug\"]\n"
+                .as_bytes(),
+            ConfigFormat::Toml,
+        )
+        .unwrap();
+        let issues = [
+            issue(1, "alice", &["type/bug"], false),
+            issue(2, "bob", &["type/bug", "size/xs"], true),
+            issue(3, "bob", &["type/enhancement"], false),
+            issue(4, "alice", &[], true),
+        ];
+        let (graphql, rest): (Vec<models::issues::Issue>, Vec<models::issues::Issue>) = issues
+            .iter()
+            .map(|(node, rest)| {
+                let pull_request = rest["pull_request"].is_object();
+                (
+                    parse(&rest_issue(node, pull_request)).unwrap(),
+                    parse(rest).unwrap(),
+                )
+            })
+            .unzip();
+        for (graphql, rest) in graphql.iter().zip(&rest) {
+            let names = |issue: &models::issues::Issue| -> Vec<String> {
+                issue
+                    .labels
+                    .iter()
+                    .map(|label| label.name.clone())
+                    .collect()
+            };
+            assert_eq!(names(graphql), names(rest));
+            assert_eq!(graphql.number, rest.number);
+            assert_eq!(graphql.user.login, rest.user.login);
+            assert_eq!(graphql.state, rest.state);
+            assert_eq!(graphql.created_at, rest.created_at);
+            assert_eq!(graphql.pull_request.is_some(), rest.pull_request.is_some());
+        }
+
+        // The label filter counts the same issues and PRs either way.
+        let time = |time: &str| time.parse::<DateTime<Utc>>().unwrap();
+        let counts = |issues| {
+            let mut stats = Stats::new(
+                &config,
+                "tikv/tikv",
+                time("2024-01-01T00:00:00Z"),
+                time("2024-01-08T00:00:00Z"),
+            );
+            stats.traverse_issues(issues);
+            stats.counts()
+        };
+        let (graphql, rest) = (counts(graphql), counts(rest));
+        assert_eq!(graphql.issues, rest.issues);
+        assert_eq!(graphql.prs, rest.prs);
+        assert_eq!(graphql.issues.get("alice"), Some(&1));
+        assert_eq!(graphql.issues.get("bob"), None);
+        assert_eq!(graphql.prs.get("bob"), Some(&1));
+        assert_eq!(graphql.prs.get("alice"), None);
+    }
+}
@@ -0,0 +1,474 @@
+//! Count the issues, PRs, comments and reviews of the users across the GitHub repos.
+//!
+//! The `gh-overseer` binary is a thin CLI over this library, whose commands all count the
+//! stats by `repo_stats`. The library can also be embedded into other tools, e.g.
+//!
+//! ```no_run
+//! # async fn example() -> gh_overseer::Result<()> {
+//! let config = gh_overseer::Config::load("config.toml")?;
+//! let end_time = chrono::Utc::now();
+//! let stats = gh_overseer::run(&config, end_time - chrono::Duration::days(7), end_time).await?;
+//! println!("{:?}", stats.counts());
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod baseline;
+pub mod bucket;
+pub mod cache;
+pub mod checkpoint;
+pub mod config;
+pub mod delivery;
+pub mod error;
+pub mod fetcher;
+pub mod notifier;
+pub mod output;
+pub mod relative_time;
+pub mod staleness;
+pub mod stats;
+
+use std::{collections::HashSet, fs, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt};
+use jsonwebtoken::EncodingKey;
+use log::{info, warn};
+use octocrab::models::{AppId, InstallationId};
+use octocrab::Octocrab;
+use tokio::sync::mpsc::UnboundedSender;
+
+pub use crate::config::Config;
+pub use crate::error::{Error, Result};
+pub use crate::fetcher::Fetcher;
+pub use crate::stats::Stats;
+
+use crate::cache::RepoCache;
+use crate::fetcher::{Api, IssueState, Progress, Reactable, Retry};
+use crate::stats::RawEvent;
+
+/// Build the GitHub client with the base URL of the config, authenticated as the
+/// installation of the GitHub App if configured, or with the personal token otherwise.
+pub fn build_client(config: &Config) -> Result<Octocrab> {
+    let github_app = config.github_app();
+    let mut builder = match &github_app {
+        Some(github_app) => {
+            let private_key =
+                fs::read(&github_app.private_key_file).map_err(|source| Error::ReadPrivateKey {
+                    path: github_app.private_key_file.clone(),
+                    source,
+                })?;
+            let key = EncodingKey::from_rsa_pem(&private_key).map_err(|source| {
+                Error::InvalidPrivateKey {
+                    path: github_app.private_key_file.clone(),
+                    source,
+                }
+            })?;
+            Octocrab::builder().app(AppId(github_app.app_id), key)
+        }
+        None => Octocrab::builder().personal_token(config.github_personal_token()?),
+    };
+    if let Some(base_url) = config.github_base_url() {
+        builder = builder.base_uri(base_url.as_str())?;
+        info!("github base url set to {}", base_url);
+    }
+    let octocrab = builder.build()?;
+    match github_app {
+        // The installation token is requested and refreshed by the client on demand.
+        Some(github_app) => {
+            info!(
+                "authenticated as installation {} of github app {}",
+                github_app.installation_id, github_app.app_id
+            );
+            Ok(octocrab.installation(InstallationId(github_app.installation_id)))
+        }
+        None => Ok(octocrab),
+    }
+}
+
+/// Get the retry policy of the requests configured in the config.
+pub fn retry_policy(config: &Config) -> Retry {
+    Retry {
+        max_retries: config.fetch_max_retries(),
+        base_delay: config.fetch_retry_base_delay(),
+        timeout: config.fetch_request_timeout(),
+    }
+}
+
+/// Get the configured repos along with the repos of the configured orgs, deduplicated
+/// in the configured order. The archived repos of the orgs are skipped unless included.
+pub async fn review_repos(
+    octocrab: &Octocrab,
+    config: &Config,
+    include_archived: bool,
+) -> Result<Vec<String>> {
+    let mut repos = config.review_repos();
+    for org in config.review_orgs() {
+        let org_repos = fetcher::fetch_org_repos(
+            octocrab.clone(),
+            &org,
+            retry_policy(config),
+            config.github_base_url().as_deref(),
+            config.fetch_page_size(),
+            include_archived,
+        )
+        .await
+        .map_err(|source| Error::FetchOrgRepos {
+            org: org.clone(),
+            source: Box::new(source),
+        })?;
+        info!("{} repos found in org '{}'", org_repos.len(), org);
+        for repo in org_repos {
+            if !repos.contains(&repo) {
+                repos.push(repo);
+            }
+        }
+    }
+    Ok(repos)
+}
+
+/// The options of fetching and counting the stats of each repo on top of the config, e.g.
+/// the optional metrics of the `report` command. The default only fetches what the config
+/// needs from GitHub, by the REST API.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// The GitHub API to fetch the issues, comments and reviews with.
+    pub api: Api,
+    /// The state of the issues and PRs to fetch.
+    pub issue_state: IssueState,
+    /// Only count the PRs targeting this base branch.
+    pub base_branch: Option<String>,
+    /// Also count the accepted code suggestions.
+    pub suggestions_accepted: bool,
+    /// Also count the PRs in the size buckets.
+    pub pr_sizes: bool,
+    /// Also count the PRs merged.
+    pub prs_merged: bool,
+    /// Also score the users with the activities decayed by this half-life.
+    pub decay_half_life: Option<chrono::Duration>,
+    /// Also fetch the archived repos of the orgs.
+    pub include_archived: bool,
+    /// Read the listings from the fixtures in this directory instead of GitHub, see
+    /// `Fetcher::set_fixtures_dir`. The orgs are ignored, and no token is needed.
+    pub fixtures_dir: Option<PathBuf>,
+    /// Cache the fetched issues, comments and reviews in this directory, see `RepoCache`.
+    pub cache_dir: Option<PathBuf>,
+    /// Ignore the existing cache, which is still rebuilt.
+    pub no_cache: bool,
+    /// Also count the stats of each of these time buckets within the time range.
+    pub buckets: Vec<(DateTime<Utc>, DateTime<Utc>)>,
+    /// Report the progress of each listing to this sender.
+    pub progress: Option<UnboundedSender<Progress>>,
+    /// Stream each counted item to this sender as it's traversed.
+    pub raw_events: Option<UnboundedSender<RawEvent>>,
+}
+
+/// Fetch and count the stats of all the configured repos within the time range, merged
+/// across the repos. A repo failing to be fetched is recorded in the stats rather than
+/// failing the run.
+pub async fn run(
+    config: &Config,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Result<Stats> {
+    run_with(config, start_time, end_time, &RunOptions::default()).await
+}
+
+/// Fetch and count the stats like `run` with the options, e.g. the optional metrics. The
+/// stats of the time buckets are dropped, see `repo_stats` to keep them.
+pub async fn run_with(
+    config: &Config,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    options: &RunOptions,
+) -> Result<Stats> {
+    config.validate()?;
+    let (octocrab, repos) = match &options.fixtures_dir {
+        // No token is needed to read the fixtures.
+        Some(_) => (Octocrab::builder().build()?, config.review_repos()),
+        None => {
+            let octocrab = build_client(config)?;
+            let repos = review_repos(&octocrab, config, options.include_archived).await?;
+            (octocrab, repos)
+        }
+    };
+    let repo_stats: Vec<Result<(Stats, Vec<Stats>)>> = stream::iter(repos)
+        .map(|repo| {
+            repo_stats(
+                octocrab.clone(),
+                config,
+                repo,
+                start_time,
+                end_time,
+                options,
+            )
+        })
+        .buffer_unordered(config.fetch_max_concurrency())
+        .collect()
+        .await;
+    let repo_stats: Vec<Stats> = repo_stats
+        .into_iter()
+        .map(|stats| stats.map(|(stats, _)| stats))
+        .collect::<Result<_, _>>()?;
+    Stats::reduce(repo_stats).ok_or(Error::NoRepos)
+}
+
+/// Fetch and count the stats of a single repo within the time range, along with the stats
+/// of each time bucket of the options. The cache of the repo is saved unless some data is
+/// missing.
+pub async fn repo_stats(
+    octocrab: Octocrab,
+    config: &Config,
+    repo: String,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    options: &RunOptions,
+) -> Result<(Stats, Vec<Stats>)> {
+    let repo_users = config.review_repo_users(&repo);
+    let mut cache = options.cache_dir.as_ref().map(|cache_dir| {
+        (!options.no_cache)
+            .then(|| RepoCache::load(cache_dir, &repo, start_time, repo_users.clone()))
+            .flatten()
+            .unwrap_or_else(|| RepoCache::new(start_time, repo_users.clone()))
+    });
+    // Only fetch the items updated since the watermark of the cache if any.
+    let since = cache.as_ref().map_or(start_time, |cache| cache.watermark());
+    let mut fetcher = Fetcher::new(octocrab, &repo, since, end_time, retry_policy(config))?;
+    fetcher.set_issue_comments_concurrency(config.fetch_issue_comments_concurrency());
+    fetcher.set_page_size(config.fetch_page_size());
+    if let Some(base_url) = config.github_base_url() {
+        fetcher.set_base_url(&base_url);
+    }
+    fetcher.set_api(options.api);
+    fetcher.set_issue_state(options.issue_state);
+    if let Some(fixtures_dir) = &options.fixtures_dir {
+        fetcher.set_fixtures_dir(fixtures_dir);
+    }
+    if let Some(progress) = &options.progress {
+        fetcher.set_progress(progress.clone());
+    }
+    let new_stats = |start_time, end_time| {
+        let mut stats = Stats::new(config, &repo, start_time, end_time);
+        if let Some(half_life) = options.decay_half_life {
+            stats.set_decay_half_life(half_life);
+        }
+        stats
+    };
+    let mut stats = new_stats(start_time, end_time);
+    stats.set_raw_events(options.raw_events.clone());
+    let mut bucket_stats: Vec<Stats> = options
+        .buckets
+        .iter()
+        .map(|(bucket_start, bucket_end)| new_stats(*bucket_start, *bucket_end))
+        .collect();
+
+    // Fetch the PRs targeting the base branch to filter the others out.
+    if let Some(base_branch) = &options.base_branch {
+        let pull_requests = fetcher
+            .fetch_pull_requests_by_base(base_branch.clone())
+            .recv()
+            .await
+            .unwrap_or_default();
+        let pull_requests: HashSet<u64> = pull_requests
+            .iter()
+            .map(|pull_request| pull_request.number)
+            .collect();
+        traverse_each(&mut stats, &mut bucket_stats, |stats| {
+            stats.set_base_branch_pull_requests(pull_requests.clone())
+        });
+    }
+
+    // Fetch the draft PRs to filter them out of the PRs created.
+    if config.review_exclude_draft_prs() {
+        let pull_requests = fetcher
+            .fetch_draft_pull_requests(start_time)
+            .recv()
+            .await
+            .unwrap_or_default();
+        let pull_requests: HashSet<u64> = pull_requests
+            .iter()
+            .map(|pull_request| pull_request.number)
+            .collect();
+        traverse_each(&mut stats, &mut bucket_stats, |stats| {
+            stats.set_draft_pull_requests(pull_requests.clone())
+        });
+    }
+
+    // Fetch all issues and PRs.
+    // The items updated before now are all fetched, except the ones created after the end
+    // time.
+    let watermark = Utc::now().min(end_time);
+    let updated_issues_and_prs = fetcher.fetch_issues().recv().await;
+    if updated_issues_and_prs.is_none() {
+        warn!("no issues and pull requests fetched for '{}'", repo);
+    }
+    let updated_issues_and_prs = updated_issues_and_prs.unwrap_or_default();
+    let issues_and_prs = match &mut cache {
+        Some(cache) => cache.merge_issues(updated_issues_and_prs.clone()),
+        None => updated_issues_and_prs.clone(),
+    };
+    traverse_each(&mut stats, &mut bucket_stats, |stats| {
+        stats.traverse_issues(issues_and_prs.clone())
+    });
+
+    // Fetch all comments for the updated issues and PRs.
+    let mut issue_comments_rx = fetcher.fetch_issue_comments(
+        updated_issues_and_prs
+            .iter()
+            .filter(|issue| issue.pull_request.is_none())
+            .map(|issue| issue.number)
+            .collect(),
+    );
+    let mut pull_request_comments_rx = fetcher.fetch_pull_request_comments();
+
+    // Fetch all reviews for the updated PRs.
+    let pull_requests = updated_issues_and_prs
+        .iter()
+        .filter(|issue| issue.pull_request.is_some())
+        .map(|pull_request| (pull_request.number, pull_request.updated_at));
+    let mut pull_request_reviews_rx = fetcher.fetch_pull_request_reviews(pull_requests.collect());
+
+    // The issues, PRs and comments to fetch the reactions to.
+    let mut reactables: Vec<Reactable> = issues_and_prs
+        .iter()
+        .map(|issue| Reactable::Issue(issue.number))
+        .collect();
+
+    // Wait for the fetcher to finish fetching all data.
+    if let Some(issue_comments) = issue_comments_rx.recv().await {
+        let issue_comments = match &mut cache {
+            Some(cache) => cache.merge_issue_comments(issue_comments),
+            None => issue_comments,
+        };
+        reactables.extend(
+            issue_comments
+                .iter()
+                .map(|comment| Reactable::IssueComment(comment.id.into_inner())),
+        );
+        traverse_each(&mut stats, &mut bucket_stats, |stats| {
+            stats.traverse_issue_comments(issue_comments.clone())
+        });
+    }
+    if let Some(pull_request_comments) = pull_request_comments_rx.recv().await {
+        let pull_request_comments = match &mut cache {
+            Some(cache) => cache.merge_pull_request_comments(pull_request_comments),
+            None => pull_request_comments,
+        };
+        reactables.extend(
+            pull_request_comments
+                .iter()
+                .map(|comment| Reactable::PullRequestComment(comment.id.into_inner())),
+        );
+        traverse_each(&mut stats, &mut bucket_stats, |stats| {
+            stats.traverse_pull_request_comments(pull_request_comments.clone())
+        });
+    }
+    if let Some(pull_request_reviews) = pull_request_reviews_rx.recv().await {
+        let pull_request_reviews = match &mut cache {
+            Some(cache) => cache.merge_pull_request_reviews(pull_request_reviews),
+            None => pull_request_reviews,
+        };
+        traverse_each(&mut stats, &mut bucket_stats, |stats| {
+            stats.traverse_pull_request_reviews(pull_request_reviews.clone())
+        });
+    }
+
+    // Save the cache unless some data is missing, which would never be fetched again once
+    // the watermark passes it.
+    if let (Some(mut cache), Some(cache_dir)) = (cache, &options.cache_dir) {
+        if fetcher.failed_endpoints().is_empty() {
+            cache.advance_watermark(watermark);
+            if let Err(err) = cache.save(cache_dir, &repo) {
+                warn!("failed to save cache of '{}': {}", repo, err);
+            }
+        } else {
+            warn!("skip saving cache of '{}' due to failed endpoints", repo);
+        }
+    }
+
+    // Fetch the details of the authored PRs to count their sizes.
+    if options.pr_sizes {
+        if let Some(pull_requests) = fetcher
+            .fetch_pull_requests(stats.authored_pull_requests())
+            .recv()
+            .await
+        {
+            traverse_each(&mut stats, &mut bucket_stats, |stats| {
+                stats.traverse_pull_request_sizes(pull_requests.clone())
+            });
+        }
+    }
+
+    // Fetch the details of the closed PRs to count the merged ones.
+    if options.prs_merged {
+        if let Some(pull_requests) = fetcher
+            .fetch_pull_requests(stats.closed_pull_requests())
+            .recv()
+            .await
+        {
+            traverse_each(&mut stats, &mut bucket_stats, |stats| {
+                stats.traverse_merged_pull_requests(pull_requests.clone())
+            });
+        }
+    }
+
+    // Fetch the commits of the PRs with suggestions to count the accepted ones.
+    if options.suggestions_accepted {
+        if let Some(commits) = fetcher
+            .fetch_pull_request_commits(stats.suggestion_pull_requests())
+            .recv()
+            .await
+        {
+            traverse_each(&mut stats, &mut bucket_stats, |stats| {
+                stats.traverse_suggestion_commits(commits.clone())
+            });
+        }
+    }
+
+    // Fetch the timeline events of issues and PRs, which must come after the reviews and
+    // comments to avoid double-counting LGTMs.
+    if stats.needs_timeline_events() {
+        let issues_and_prs = issues_and_prs.iter().map(|issue| issue.number);
+        if let Some(events) = fetcher
+            .fetch_timeline_events(issues_and_prs.collect())
+            .recv()
+            .await
+        {
+            traverse_each(&mut stats, &mut bucket_stats, |stats| {
+                stats.traverse_timeline_events(events.clone())
+            });
+        }
+    }
+
+    // Fetch the details of the PRs with LGTMs to weight them by the sizes, which must come
+    // after all the LGTMs are counted.
+    if config.review_weight_lgtm_by_size() {
+        if let Some(pull_requests) = fetcher
+            .fetch_pull_requests(stats.lgtm_pull_requests())
+            .recv()
+            .await
+        {
+            traverse_each(&mut stats, &mut bucket_stats, |stats| {
+                stats.traverse_lgtm_pull_request_sizes(pull_requests.clone())
+            });
+        }
+    }
+
+    // Fetch the reactions to the issues, PRs and comments.
+    if config.review_count_reactions() {
+        if let Some(reactions) = fetcher.fetch_reactions(reactables).recv().await {
+            traverse_each(&mut stats, &mut bucket_stats, |stats| {
+                stats.traverse_reactions(reactions.clone())
+            });
+        }
+    }
+    // Record the failed endpoints, the other metrics of the repo are still valid.
+    stats.set_failed_endpoints(fetcher.failed_endpoints(), fetcher.hard_failed());
+    stats.set_raw_events(None);
+    Ok((stats, bucket_stats))
+}
+
+/// Apply the traversal on the stats of the whole time range and of each time bucket.
+fn traverse_each(stats: &mut Stats, bucket_stats: &mut [Stats], traverse: impl Fn(&mut Stats)) {
+    bucket_stats.iter_mut().for_each(&traverse);
+    traverse(stats);
+}
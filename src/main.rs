@@ -1,184 +1,1379 @@
-mod config;
-mod fetcher;
-mod stats;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    io::{self, BufWriter, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::Arc,
+};
 
-use std::process;
-
-use chrono::{DateTime, Utc};
-use clap::Parser;
+use chrono::{DateTime, Duration, Utc};
+use clap::{Args as ClapArgs, Parser, Subcommand, ValueEnum};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use log::{error, info, warn};
 use octocrab::Octocrab;
-use tokio::sync::mpsc::unbounded_channel;
+use tokio::{
+    sync::{
+        mpsc::{unbounded_channel, UnboundedReceiver},
+        watch, Semaphore,
+    },
+    task::JoinHandle,
+    time::Instant,
+};
+
+use gh_overseer::bucket::{self, Bucket};
+use gh_overseer::checkpoint::{self, Checkpoint, RunKey};
+use gh_overseer::config::{Config, ConfigFormat};
+use gh_overseer::delivery::DeliveryState;
+use gh_overseer::fetcher::{self, Api, Fetcher, IssueState, Progress};
+use gh_overseer::stats::{Metric, RawEvent, Stats};
+use gh_overseer::{baseline, notifier, output, relative_time, staleness, RunOptions};
 
-use crate::config::Config;
-use crate::fetcher::Fetcher;
-use crate::stats::Stats;
+/// The time to wait for the repos being fetched once interrupted by Ctrl-C, after which
+/// they are aborted and missing from the stats.
+const INTERRUPT_GRACE_PERIOD: std::time::Duration = std::time::Duration::from_secs(10);
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    /// An aligned text table with a totals row.
+    Table,
+    /// The raw debug dump of the stats in the logs.
+    Debug,
+    /// The JSON object of the per-user counts of each metric and the time range.
+    Json,
+    /// The CSV of the per-user counts of each metric, one row per user.
+    Csv,
+    /// The Prometheus text exposition of the per-user counts of each metric in each repo,
+    /// to be scraped, e.g. through the textfile collector of the node exporter.
+    Prometheus,
+}
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// Path to the configuration file.
-    #[arg(short, long, default_value = "config.toml")]
+    /// Path to the configuration file, "-" to read it from the stdin.
+    #[arg(short, long, global = true, default_value = "config.toml")]
     config: String,
 
+    /// The format of the configuration file.
+    #[arg(long, global = true, value_enum, default_value_t = ConfigFormat::Toml)]
+    config_format: ConfigFormat,
+
     /// Log level. Should be the following values:
     ///   - error
     ///   - warn
     ///   - info
     ///   - debug
     ///   - trace
-    #[arg(short, long, default_value = "info")]
+    #[arg(short, long, global = true, default_value = "info")]
     log_level: String,
 
-    /// Start time should be in the RFC3339 format like "2015-09-21T00:00:00Z".
-    #[arg(short, long, required = true)]
-    start_time: String,
+    /// Only log the warnings and errors, overriding the log level. The logs always go to
+    /// the stderr, so the stdout keeps only the output, e.g. to be piped as JSON.
+    #[arg(short, long, global = true, default_value_t = false)]
+    quiet: bool,
 
-    /// End time should be in the RFC3339 format like "2015-09-21T00:00:00Z".
-    #[arg(short, long, required = false)]
+    /// Start time should be in the RFC3339 format like "2015-09-21T00:00:00Z", or relative
+    /// to now like "now", "30m", "12h", "7d" and "2w". Required by the `report` and
+    /// `notify` commands.
+    #[arg(short, long, global = true, required = false)]
+    start_time: Option<String>,
+
+    /// End time should be in the same format as the start time, defaults to now.
+    #[arg(short, long, global = true, required = false)]
     end_time: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The args of the `report` command, which runs if no command is given.
+    #[command(flatten)]
+    report: ReportArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Collect and output the stats of the users in the time range, the default command.
+    Report(ReportArgs),
+    /// Check the config file, then exit without any request to GitHub.
+    ValidateConfig,
+    /// Collect the stats like `report`, then send them to the configured notifiers.
+    Notify(ReportArgs),
+    /// Collect the stats of the time range and a previous one, then output the changes
+    /// of each metric of each user from the previous period to the current one.
+    Compare(CompareArgs),
+}
+
+#[derive(ClapArgs, Debug)]
+struct CompareArgs {
+    /// Start time of the previous period, in the same format as the start time.
+    #[arg(long)]
+    previous_start_time: String,
+
+    /// End time of the previous period, in the same format as the end time. Defaults to
+    /// the start time of the current period.
+    #[arg(long, required = false)]
+    previous_end_time: Option<String>,
+
+    #[command(flatten)]
+    fetch: FetchArgs,
+}
+
+/// The args of what to fetch and count, shared by the commands collecting the stats.
+#[derive(ClapArgs, Debug)]
+struct FetchArgs {
+    /// The GitHub API to fetch the issues, comments and reviews with. The GraphQL API
+    /// needs far fewer requests than the REST API for the repos with many issues and PRs.
+    #[arg(long, value_enum, default_value_t = Api::Rest)]
+    api: Api,
+
+    /// Only count the PRs targeting this base branch, e.g. "main". The reviews and
+    /// comments of a PR inherit its base branch, and the time range still applies on
+    /// top of it. The base branch of each PR is fetched before traversing, so it
+    /// costs extra requests per repo.
+    #[arg(short, long, required = false)]
+    base_branch: Option<String>,
+
+    /// Also count the accepted code suggestions of each user. Telling whether a suggestion
+    /// is accepted needs to fetch the commits of the PRs with suggestions, and is a
+    /// heuristic based on the "Apply suggestions from code review" commits.
+    #[arg(long, default_value_t = false)]
+    suggestions_accepted: bool,
+
+    /// Also count the PRs of each user in the XS/S/M/L/XL size buckets by lines changed,
+    /// which needs to fetch the details of each authored PR. The thresholds between the
+    /// buckets are configured by `pr_size_thresholds`, defaults to [10, 30, 100, 500].
+    /// The buckets are output as the `prs_xs` to `prs_xl` columns of the table and CSV,
+    /// and as the `pr_sizes` of each user from XS to XL in the JSON.
+    #[arg(long, default_value_t = false)]
+    pr_sizes: bool,
+
+    /// Also count the PRs of each user has got merged within the time range, which needs
+    /// to fetch the details of each closed PR.
+    #[arg(long, default_value_t = false)]
+    prs_merged: bool,
+
+    /// Also fetch the archived repos of the orgs configured by `review.orgs`, which are
+    /// excluded by default.
+    #[arg(long, default_value_t = false)]
+    include_archived: bool,
+
+    /// Read the issues, comments, reviews and the other listings of each repo from the JSON
+    /// fixture files in this directory instead of GitHub, e.g. `<dir>/owner/name/issues.json`,
+    /// for a deterministic output without any request or token. The orgs are ignored.
+    #[arg(long, required = false)]
+    fixtures_dir: Option<String>,
+}
+
+impl FetchArgs {
+    /// Get the options of the pipeline fetching and counting the stats of each repo.
+    fn run_options(&self) -> RunOptions {
+        RunOptions {
+            api: self.api,
+            base_branch: self.base_branch.clone(),
+            suggestions_accepted: self.suggestions_accepted,
+            pr_sizes: self.pr_sizes,
+            prs_merged: self.prs_merged,
+            include_archived: self.include_archived,
+            fixtures_dir: self.fixtures_dir.as_ref().map(PathBuf::from),
+            ..RunOptions::default()
+        }
+    }
+}
+
+#[derive(ClapArgs, Debug)]
+struct ReportArgs {
+    /// Output format of the stats.
+    #[arg(short = 'f', long, alias = "format", value_enum, default_value_t = Format::Table)]
+    output_format: Format,
+
+    /// Write the stats to this file instead of the stdout.
+    #[arg(short, long, required = false)]
+    output_file: Option<String>,
+
+    /// The metric to sort the users by in the table output, e.g. "pr_reviews".
+    #[arg(long, default_value_t = Metric::PrReviews)]
+    sort_by: Metric,
+
+    /// Summarize the mean, median and standard deviation of each metric across the
+    /// active users, to show how evenly the work is distributed. The summary is shown
+    /// below the table output and included in the JSON output.
+    #[arg(long, default_value_t = false)]
+    distribution: bool,
+
+    /// Also score each user with the time-decayed activities, where the contribution of
+    /// an activity halves every half-life days before the end time. Defaults to 7 days
+    /// if given without a value.
+    #[arg(long, num_args = 0..=1, default_missing_value = "7")]
+    decay_half_life: Option<f64>,
+
+    /// Only output the stats of the user, which can be repeated for multiple users. All
+    /// the allowed users are still counted, e.g. to dedupe the LGTMs across them.
+    #[arg(long = "user", value_name = "LOGIN")]
+    users: Vec<String>,
+
+    /// List the issues and PRs open as of the end time without any comment by the allowed
+    /// users in the given days before the end time, then exit without collecting any
+    /// stats. The start time is ignored, and the whole comment history of each open item
+    /// is scanned to find its last maintainer activity.
+    #[arg(long, required = false)]
+    stale_after: Option<u64>,
+
+    /// Validate that all the allowed users exist on GitHub with the exact same login,
+    /// then exit without collecting any stats.
+    #[arg(long, default_value_t = false)]
+    validate_users: bool,
+
+    /// Also print the users ranked by their contribution scores, i.e. the counts weighted
+    /// by the `[scoring]` section of the config, where each metric weighs 1.0 by default.
+    #[arg(long, default_value_t = false)]
+    rank: bool,
+
+    /// Also print the leaderboard of the top N users of each metric.
+    #[arg(long, required = false)]
+    top: Option<usize>,
+
+    /// Also break the stats down into the time buckets within the time range, to show
+    /// the trends. The JSON output is an array of the stats of each bucket instead.
+    #[arg(long, value_enum, required = false)]
+    bucket: Option<Bucket>,
+
+    /// Also output the stats of each repo separately before the grand total.
+    #[arg(long, default_value_t = false)]
+    by_repo: bool,
+
+    /// Send the merged stats to the Feishu/Lark bot configured by `feishu_bot_webhook_url`
+    /// and the other configured `[[notifiers]]`.
+    #[arg(long, default_value_t = false)]
+    notify: bool,
+
+    /// The file to track the delivery status of the notified stats to each notifier in,
+    /// so a re-run of the same time range only retries the notifiers failed to be sent
+    /// to. The time range should resolve to the same one, e.g. by the absolute times.
+    #[arg(long, required = false)]
+    notify_state: Option<String>,
+
+    /// Show the progress of fetching each repo. It's disabled if the stdout is not a
+    /// terminal or the log level is debug or trace, where the logs are verbose anyway.
+    #[arg(long, default_value_t = false)]
+    progress: bool,
+
+    /// Print the resolved repos, time range and allowed users, then exit without any
+    /// request to GitHub, to sanity-check the setup without burning the API quota.
+    #[arg(long, default_value_t = false)]
+    dry_run: bool,
+
+    /// Only fetch the issues and PRs in this state now, e.g. "open" for a snapshot of the
+    /// ongoing work. The issue comments and reviews of the others are not fetched either,
+    /// while the review comments are listed across the whole repo regardless.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = IssueState::All,
+        conflicts_with = "cache_dir"
+    )]
+    issue_state: IssueState,
+
+    /// Cache the fetched issues, comments and reviews of each repo in this directory, so
+    /// the next run only fetches the ones updated since the last run. The cache of a repo
+    /// is rebuilt once the allowed users change or the start time goes before it.
+    #[arg(long, required = false)]
+    cache_dir: Option<String>,
+
+    /// Ignore the existing cache and fetch everything again, then rebuild the cache if
+    /// `--cache-dir` is set.
+    #[arg(long, default_value_t = false)]
+    no_cache: bool,
+
+    /// Treat the problems found in the checks as errors and exit with a non-zero code.
+    #[arg(long, default_value_t = false)]
+    strict: bool,
+
+    /// Stream each counted issue, PR, comment and review as a JSON Lines record of its
+    /// type, repo, user, timestamp and PR number to the output file or the stdout as it's
+    /// traversed, instead of the aggregated stats.
+    #[arg(long, default_value_t = false)]
+    raw_events: bool,
+
+    /// Resume from the checkpoint of the last run dying halfway, which skips the repos
+    /// already fetched by it. Only the run with the same time range, users, repos and
+    /// bucket resumes the checkpoint, within the time range resolved by the last run.
+    #[arg(long, default_value_t = false, conflicts_with = "raw_events")]
+    resume: bool,
+
+    /// The file to checkpoint the stats of the fetched repos to as they complete, which is
+    /// removed once the run completes. Defaults to a file in the temporary directory.
+    #[arg(long, required = false)]
+    checkpoint_file: Option<String>,
+
+    /// Abort the repos still being fetched once the run takes this many seconds, e.g. for
+    /// a cron job with a hard SLA, and output the stats of the fetched repos as partial.
+    #[arg(long, required = false)]
+    deadline_secs: Option<u64>,
+
+    /// Show the users by their display names configured by `review.user_display_names`
+    /// instead of their logins in the table, CSV, ranking and leaderboard outputs. The
+    /// users without any display name keep their logins, and the JSON output always
+    /// keys the users by their logins.
+    #[arg(long, default_value_t = false)]
+    display_names: bool,
+
+    /// Combine the issue comments and the review comments of each user into a single
+    /// "comments" column in the table and CSV outputs, for the teams not telling them
+    /// apart. The other outputs keep them separated.
+    #[arg(long, default_value_t = false)]
+    merge_comment_metrics: bool,
+
+    /// Exit with a non-zero code if nothing is counted for any user, which usually means
+    /// a problem of the config or the token rather than the inactivity, e.g. in CI.
+    #[arg(long, default_value_t = false)]
+    fail_if_empty: bool,
+
+    #[command(flatten)]
+    fetch: FetchArgs,
 }
 
 #[tokio::main]
 async fn main() {
-    let args = Args::parse();
-    init_logger(&args.log_level);
+    let started_at = Instant::now();
+    let cli = Args::parse();
+    init_logger(if cli.quiet { "warn" } else { &cli.log_level });
 
     // TODO: support both configuration file and command line arguments.
-    let config = Config::load(&args.config).unwrap_or_else(|err| {
-        error!("failed to load config file from '{}': {}", args.config, err);
+    let config = Config::load_as(&cli.config, cli.config_format).unwrap_or_else(|err| {
+        error!("{}", err);
+        process::exit(1);
+    });
+    info!("config loaded from {}", cli.config);
+    config.validate().unwrap_or_else(|err| {
+        error!("invalid config in '{}': {}", cli.config, err);
         process::exit(1);
     });
-    info!("config loaded from {}", args.config);
+    let args = match cli.command {
+        None => cli.report,
+        Some(Command::Report(args)) => args,
+        Some(Command::Notify(args)) => ReportArgs {
+            notify: true,
+            ..args
+        },
+        Some(Command::ValidateConfig) => {
+            info!("config in '{}' is valid", cli.config);
+            return;
+        }
+        Some(Command::Compare(args)) => {
+            compare(
+                &config,
+                cli.start_time.as_deref(),
+                cli.end_time.as_deref(),
+                args,
+            )
+            .await;
+            return;
+        }
+    };
 
-    let octocrab = Octocrab::builder()
-        .personal_token(config.github_personal_token())
-        .build()
-        .unwrap_or_else(|err| {
-            error!("failed to build github client instance: {}", err);
-            process::exit(1);
-        });
+    // No token is needed to read the fixtures.
+    let octocrab = if args.fetch.fixtures_dir.is_some() {
+        Octocrab::builder()
+            .build()
+            .map_err(gh_overseer::Error::from)
+    } else {
+        gh_overseer::build_client(&config)
+    }
+    .unwrap_or_else(|err| {
+        error!("failed to build github client instance: {}", err);
+        process::exit(1);
+    });
     info!("github client instance built");
 
+    if args.validate_users {
+        let valid = validate_users(&octocrab, config.review_all_users()).await;
+        if !valid && args.strict {
+            process::exit(1);
+        }
+        return;
+    }
+
     let repos = config.review_repos();
-    let (start_time, end_time) = (
-        match DateTime::parse_from_rfc3339(&args.start_time) {
-            Ok(start_time) => start_time.to_utc(),
-            Err(err) => {
-                error!("failed to parse start time '{}': {}", args.start_time, err);
-                process::exit(1);
-            }
-        },
-        if let Some(end_time) = &args.end_time {
-            match DateTime::parse_from_rfc3339(&end_time) {
-                Ok(end_time) => end_time.to_utc(),
-                Err(err) => {
-                    error!("failed to parse end time '{}': {}", end_time, err);
+    let orgs = config.review_orgs();
+    if repos.is_empty() && orgs.is_empty() {
+        if args.strict {
+            error!("no repos configured in '{}', nothing to fetch", cli.config);
+            process::exit(1);
+        }
+        warn!("no repos configured in '{}', nothing to fetch", cli.config);
+        return;
+    }
+    let Some(start_time) = &cli.start_time else {
+        error!("the start time is required, set it by '--start-time'");
+        process::exit(1);
+    };
+    // The relative times are relative to the same now.
+    let now = Utc::now();
+    let start_time = parse_time("start time", start_time, now);
+    let end_time = cli
+        .end_time
+        .as_deref()
+        .map_or(now, |end_time| parse_time("end time", end_time, now));
+    // Nothing falls into an inverted time range, which would report all zeros silently.
+    if start_time > end_time {
+        error!(
+            "start time {} is after end time {}, nothing to count",
+            start_time, end_time
+        );
+        process::exit(1);
+    }
+    if end_time > now {
+        warn!(
+            "end time {} is in the future, the counts may change until then",
+            end_time
+        );
+    }
+    info!("time range: {} ~ {}", start_time, end_time);
+
+    let retry = gh_overseer::retry_policy(&config);
+    if args.dry_run {
+        for repo in &repos {
+            Fetcher::new(octocrab.clone(), repo, start_time, end_time, retry).unwrap_or_else(
+                |err| {
+                    error!("failed to init fetcher for '{}': {}", repo, err);
                     process::exit(1);
-                }
-            }
+                },
+            );
+        }
+        println!("repos: {}", repos.join(", "));
+        if !orgs.is_empty() {
+            println!("orgs: {}", orgs.join(", "));
+        }
+        println!("time range: {} ~ {}", start_time, end_time);
+        if config.review_track_all_users() {
+            println!("users: all");
         } else {
-            Utc::now()
-        },
-    );
-    info!("time range: {} ~ {}", start_time, end_time);
+            let user_exclusion = config.review_user_exclusion();
+            let users: Vec<String> = config
+                .review_users()
+                .into_iter()
+                .filter(|user| !user_exclusion.excludes(user))
+                .collect();
+            println!("users: {}", users.join(", "));
+        }
+        for repo in &repos {
+            let repo_users = config.review_repo_users(repo);
+            if repo_users != config.review_users() {
+                println!("users of {}: {}", repo, repo_users.join(", "));
+            }
+        }
+        return;
+    }
+
+    let repos = if args.fetch.fixtures_dir.is_some() {
+        if !orgs.is_empty() {
+            warn!("the orgs can't be expanded with the fixtures, ignore them");
+        }
+        repos
+    } else {
+        // A classic token without the `repo` scope only fails on the private repos, tell it
+        // before fetching any of them.
+        match fetcher::fetch_token_scopes(octocrab.clone(), retry).await {
+            Ok(Some(scopes)) if !scopes.iter().any(|scope| scope == "repo") => warn!(
+                "github token has no `repo` scope, the private repos can't be fetched, scopes: {:?}",
+                scopes
+            ),
+            Ok(_) => {}
+            Err(err) => warn!("failed to check the scopes of the github token: {}", err),
+        }
+
+        // Expand the orgs into their repos, which needs requests so it comes after the dry
+        // run.
+        gh_overseer::review_repos(&octocrab, &config, args.fetch.include_archived)
+            .await
+            .unwrap_or_else(|err| {
+                error!("{}", err);
+                process::exit(1);
+            })
+    };
+    let repo_count = repos.len();
+
+    if let Some(stale_after) = args.stale_after {
+        let stale_threshold = end_time - Duration::days(stale_after as i64);
+        report_stale_items(&octocrab, &config, repos, stale_threshold, end_time).await;
+        return;
+    }
 
+    // Checkpoint the stats of each repo as it's fetched, and resume from the checkpoint of
+    // the same run if asked, so a run dying halfway doesn't fetch everything again.
+    let checkpoint_path = args
+        .checkpoint_file
+        .as_ref()
+        .map_or_else(checkpoint::default_path, PathBuf::from);
+    let mut users = config.review_users();
+    users.sort();
+    let run_key = RunKey {
+        time_range: (
+            cli.start_time.clone().unwrap_or_default(),
+            cli.end_time.clone(),
+        ),
+        users,
+        repos: repos.clone(),
+        bucket: args.bucket.map(|bucket| format!("{:?}", bucket)),
+    };
+    let previous_checkpoint = Checkpoint::load(&checkpoint_path).unwrap_or_else(|err| {
+        warn!(
+            "failed to load checkpoint '{}', ignore it: {}",
+            checkpoint_path.display(),
+            err
+        );
+        None
+    });
+    let mut checkpoint = match previous_checkpoint {
+        Some(previous) if previous.key() == &run_key && args.resume => {
+            let previous = previous.with_config(&config);
+            let (start_time, end_time) = previous.time_range();
+            info!(
+                "resume from checkpoint '{}', {} repos already fetched, time range: {} ~ {}",
+                checkpoint_path.display(),
+                previous.len(),
+                start_time,
+                end_time
+            );
+            previous
+        }
+        Some(previous) if previous.key() == &run_key && !previous.is_empty() => {
+            info!(
+                "checkpoint '{}' of the same run found with {} repos fetched, resume it by '--resume'",
+                checkpoint_path.display(),
+                previous.len()
+            );
+            Checkpoint::new(run_key, start_time, end_time)
+        }
+        _ => Checkpoint::new(run_key, start_time, end_time),
+    };
+    // The resumed run keeps the time range of the checkpoint, e.g. for the relative times.
+    let (start_time, end_time) = checkpoint.time_range();
+    let repos: Vec<String> = repos
+        .into_iter()
+        .filter(|repo| !checkpoint.contains(repo))
+        .collect();
+    let fetch_count = repos.len();
+
+    let buckets = args
+        .bucket
+        .map(|bucket| bucket::split(bucket, start_time, end_time))
+        .unwrap_or_default();
+    let show_progress = args.progress
+        && std::io::stdout().is_terminal()
+        && !matches!(cli.log_level.as_str(), "debug" | "trace");
+    let (progress_tx, progress_rx) = unbounded_channel();
+    let progress_task = show_progress.then(|| show_fetch_progress(progress_rx));
+    let semaphore = Arc::new(Semaphore::new(config.fetch_max_concurrency()));
+    let interrupt_rx = handle_interrupt();
+    let deadline = args
+        .deadline_secs
+        .map(|deadline_secs| started_at + std::time::Duration::from_secs(deadline_secs));
+    let (raw_events_tx, raw_events_rx) = unbounded_channel();
+    let raw_events_task = args
+        .raw_events
+        .then(|| write_raw_events(raw_events_rx, args.output_file.clone()));
+    let options = Arc::new(RunOptions {
+        issue_state: args.issue_state,
+        decay_half_life: args
+            .decay_half_life
+            .map(|half_life| Duration::seconds((half_life * 86400.0) as i64)),
+        cache_dir: args.cache_dir.as_ref().map(PathBuf::from),
+        no_cache: args.no_cache,
+        buckets: buckets.clone(),
+        progress: show_progress.then(|| progress_tx.clone()),
+        raw_events: args.raw_events.then(|| raw_events_tx.clone()),
+        ..args.fetch.run_options()
+    });
+    let config = Arc::new(config);
     let (tx, mut rx) = unbounded_channel();
     let mut tasks = Vec::new();
     for repo in repos {
+        // Wait for a fetching repo to finish before fetching more, and stop fetching any
+        // more once interrupted or past the deadline.
+        let permit = tokio::select! {
+            permit = semaphore.clone().acquire_owned() => {
+                permit.expect("the semaphore should never be closed")
+            }
+            _ = interrupted(interrupt_rx.clone()) => break,
+            _ = deadline_passed(deadline) => break,
+        };
         let octocrab = octocrab.clone();
-        let fetcher = Fetcher::new(octocrab, &repo, start_time).unwrap_or_else(|err| {
-            error!("failed to init fetcher for '{}': {}", repo, err);
-            process::exit(1);
-        });
-        let mut stats = Stats::new(&config, start_time, end_time);
+        let config = config.clone();
+        let options = options.clone();
         let tx = tx.clone();
 
         tasks.push((
             repo.clone(),
             tokio::spawn(async move {
-                // Fetch all issues and PRs.
-                let issues_and_prs = match fetcher.fetch_issues().recv().await {
-                    Some(issues_and_prs) => {
-                        stats.traverse_issues(issues_and_prs.clone());
-                        issues_and_prs
-                    }
-                    None => {
-                        warn!("no issues and pull requests fetched for '{}'", repo);
-                        return;
-                    }
-                };
-
-                // Fetch all comments for issues and PRs.
-                let mut issue_comments_rx = fetcher.fetch_issue_comments(
-                    issues_and_prs
-                        .iter()
-                        .filter(|issue| issue.pull_request.is_none())
-                        .map(|issue| issue.number)
-                        .collect(),
-                );
-                let mut pull_request_comments_rx = fetcher.fetch_pull_request_comments();
-
-                // Fetch all reviews for PRs.
-                let pull_requests = issues_and_prs
-                    .iter()
-                    .filter(|issue| issue.pull_request.is_some())
-                    .map(|pull_request| pull_request.number);
-                let mut pull_request_reviews_rx =
-                    fetcher.fetch_pull_request_reviews(pull_requests.collect());
-
-                // Wait for the fetcher to finish fetching all data.
-                if let Some(issue_comments) = issue_comments_rx.recv().await {
-                    stats.traverse_issue_comments(issue_comments);
-                }
-                if let Some(pull_request_comments) = pull_request_comments_rx.recv().await {
-                    stats.traverse_pull_request_comments(pull_request_comments);
-                }
-                if let Some(pull_request_reviews) = pull_request_reviews_rx.recv().await {
-                    stats.traverse_pull_request_reviews(pull_request_reviews);
-                }
+                let stats = gh_overseer::repo_stats(
+                    octocrab,
+                    &config,
+                    repo.clone(),
+                    start_time,
+                    end_time,
+                    &options,
+                )
+                .await
+                .unwrap_or_else(|err| {
+                    error!("failed to fetch stats of '{}': {}", repo, err);
+                    process::exit(1);
+                });
+                drop(permit);
                 // Send back the stats to the main thread.
                 tx.send(stats).unwrap_or_else(|err| {
                     error!(
                         "failed to send stats back to the main thread for '{}': {}",
                         repo, err
                     );
-                    return;
                 });
             }),
         ));
     }
-    // Wait for all tasks to finish.
-    for (repo, task) in tasks {
-        task.await
-            .unwrap_or_else(|err| error!("failed to finish task for '{}': {}", repo, err));
+    // Wait for all tasks to finish, or for the grace period once interrupted or the
+    // deadline, after which the unfinished tasks are aborted.
+    let unfetched_repos = fetch_count - tasks.len();
+    let mut failed_tasks = 0;
+    let mut aborted_tasks = 0;
+    let grace_period_passed = async {
+        interrupted(interrupt_rx.clone()).await;
+        tokio::time::sleep(INTERRUPT_GRACE_PERIOD).await;
+    };
+    tokio::pin!(grace_period_passed);
+    let mut aborting = false;
+    for (repo, mut task) in tasks {
+        if !aborting {
+            tokio::select! {
+                result = &mut task => {
+                    if let Err(err) = result {
+                        error!("failed to finish task for '{}': {}", repo, err);
+                        failed_tasks += 1;
+                    }
+                    // Checkpoint the stats sent back so far.
+                    while let Ok((s, s_buckets)) = rx.try_recv() {
+                        checkpoint.record(s, s_buckets);
+                    }
+                    if let Err(err) = checkpoint.save(&checkpoint_path) {
+                        warn!(
+                            "failed to save checkpoint '{}': {}",
+                            checkpoint_path.display(),
+                            err
+                        );
+                    }
+                    continue;
+                }
+                _ = &mut grace_period_passed => aborting = true,
+                _ = deadline_passed(deadline) => aborting = true,
+            }
+        }
+        task.abort();
+        warn!("fetching '{}' aborted, it's missing from the stats", repo);
+        aborted_tasks += 1;
     }
+    let is_interrupted = *interrupt_rx.borrow();
+    let is_past_deadline =
+        deadline.is_some_and(|deadline| Instant::now() >= deadline) && !is_interrupted;
+    if is_interrupted {
+        warn!(
+            "interrupted, the stats are partial: {} repos aborted and {} repos not fetched",
+            aborted_tasks, unfetched_repos
+        );
+    } else if is_past_deadline && (aborted_tasks > 0 || unfetched_repos > 0) {
+        warn!(
+            "deadline of {}s passed, the stats are partial: {} repos aborted and {} repos not fetched",
+            args.deadline_secs.unwrap_or_default(),
+            aborted_tasks,
+            unfetched_repos
+        );
+    }
+    let is_partial = is_interrupted || aborted_tasks > 0 || unfetched_repos > 0;
     drop(tx);
+    drop(options);
+    drop(progress_tx);
+    drop(raw_events_tx);
+    if let Some(raw_events_task) = raw_events_task {
+        let _ = raw_events_task.await;
+    }
+    if let Some(progress_task) = progress_task {
+        // The aborted fetchers might never report their progress done.
+        if aborting {
+            progress_task.abort();
+        } else {
+            let _ = progress_task.await;
+        }
+    }
 
-    // Merge all stats from the tasks.
-    let mut stats: Option<Stats> = None;
-    loop {
-        match rx.recv().await {
-            Some(s) => {
-                if let Some(ref mut stats) = stats {
-                    stats.merge(s);
+    // Merge all stats from the tasks and the checkpoint, and keep the stats of each repo.
+    while let Some((s, s_buckets)) = rx.recv().await {
+        checkpoint.record(s, s_buckets);
+    }
+    let mut repo_stats: BTreeMap<String, Stats> = BTreeMap::new();
+    let mut repo_bucket_stats: Vec<Vec<Stats>> = Vec::new();
+    for (repo, (s, s_buckets)) in checkpoint.into_repos() {
+        repo_stats.insert(repo, s);
+        repo_bucket_stats.push(s_buckets);
+    }
+    let mut stats = Stats::reduce(repo_stats.values().cloned());
+    // Merge the stats of each bucket across the repos.
+    let mut bucket_stats: Vec<Stats> = (0..buckets.len())
+        .filter_map(|bucket| {
+            Stats::reduce(
+                repo_bucket_stats
+                    .iter()
+                    .map(|s_buckets| s_buckets[bucket].clone()),
+            )
+        })
+        .collect();
+    if !args.users.is_empty() {
+        let users: HashSet<String> = args.users.iter().cloned().collect();
+        for stats in stats
+            .iter_mut()
+            .chain(repo_stats.values_mut())
+            .chain(&mut bucket_stats)
+        {
+            stats.retain_users(&users);
+        }
+    }
+    match stats {
+        Some(stats) => {
+            for (repo, endpoints) in stats.failed_endpoints() {
+                warn!(
+                    "stats of '{}' are partial, failed to fetch: {}",
+                    repo,
+                    endpoints.join(", ")
+                );
+            }
+            let counts = stats.counts();
+            let user_teams = config.review_user_teams();
+            let team_counts = (!user_teams.is_empty()).then(|| counts.group_by_team(&user_teams));
+            let repo_counts: Option<BTreeMap<String, _>> = args.by_repo.then(|| {
+                repo_stats
+                    .iter()
+                    .map(|(repo, stats)| (repo.clone(), stats.counts()))
+                    .collect()
+            });
+            info!("totals: {}", output::render_totals(&stats.totals()));
+            info!(
+                "unique_active_users: {}, active_repos: {}",
+                stats.unique_active_users(),
+                stats.active_repos()
+            );
+            let display_names = if args.display_names {
+                config.review_user_display_names()
+            } else {
+                HashMap::new()
+            };
+            let display_name = |user: String| display_names.get(&user).cloned().unwrap_or(user);
+            let columns = output::columns(
+                args.merge_comment_metrics,
+                args.fetch.pr_sizes,
+                config.review_weight_lgtm_by_size(),
+            );
+            let rendered = match args.output_format {
+                // The raw events have been written to the output sink instead.
+                _ if args.raw_events => None,
+                Format::Table => {
+                    // Only color the table printed to a terminal, see https://no-color.org.
+                    let color = args.output_file.is_none()
+                        && std::io::stdout().is_terminal()
+                        && std::env::var_os("NO_COLOR").is_none_or(|no_color| no_color.is_empty());
+                    let mut rendered = String::new();
+                    for bucket in &bucket_stats {
+                        let (bucket_start, bucket_end) = bucket.time_range();
+                        rendered.push_str(&format!("{} ~ {}:\n", bucket_start, bucket_end));
+                        let counts = bucket.counts().with_display_names(&display_names);
+                        rendered.push_str(&output::render_table(
+                            &counts,
+                            "user",
+                            args.sort_by,
+                            &columns,
+                            color,
+                        ));
+                        rendered.push('\n');
+                    }
+                    for (repo, counts) in repo_counts.iter().flatten() {
+                        rendered.push_str(&format!("{}:\n", repo));
+                        let counts = counts.with_display_names(&display_names);
+                        rendered.push_str(&output::render_table(
+                            &counts,
+                            "user",
+                            args.sort_by,
+                            &columns,
+                            color,
+                        ));
+                        rendered.push('\n');
+                    }
+                    if repo_counts.is_some() {
+                        rendered.push_str("total:\n");
+                    }
+                    let user_counts = counts.with_display_names(&display_names);
+                    rendered.push_str(&output::render_table(
+                        &user_counts,
+                        "user",
+                        args.sort_by,
+                        &columns,
+                        color,
+                    ));
+                    if let Some(team_counts) = &team_counts {
+                        let team_table = output::render_table(
+                            team_counts,
+                            "team",
+                            args.sort_by,
+                            &columns,
+                            color,
+                        );
+                        rendered.push_str(&team_table);
+                    }
+                    if args.distribution {
+                        rendered.push_str("\ndistribution:\n");
+                        rendered.push_str(&output::render_distribution(&stats));
+                    }
+                    Some(rendered)
+                }
+                Format::Csv => {
+                    for bucket in &bucket_stats {
+                        info!("stats of {:?}: {:?}", bucket.time_range(), bucket.counts());
+                    }
+                    for (repo, counts) in repo_counts.iter().flatten() {
+                        info!("stats of '{}': {:?}", repo, counts);
+                    }
+                    if let Some(team_counts) = &team_counts {
+                        info!("team stats: {:?}", team_counts);
+                    }
+                    Some(output::render_csv(
+                        &counts.with_display_names(&display_names),
+                        "user",
+                        &columns,
+                    ))
+                }
+                Format::Debug => {
+                    for bucket in &bucket_stats {
+                        info!("stats of {:?}: {:?}", bucket.time_range(), bucket);
+                    }
+                    if args.by_repo {
+                        for (repo, stats) in &repo_stats {
+                            info!("stats of '{}': {:?}", repo, stats);
+                        }
+                    }
+                    info!("all stats merged: {:?}", stats);
+                    if let Some(team_counts) = &team_counts {
+                        info!("team stats: {:?}", team_counts);
+                    }
+                    None
+                }
+                Format::Prometheus => {
+                    let repo_counts: BTreeMap<String, _> = repo_stats
+                        .iter()
+                        .map(|(repo, stats)| (repo.clone(), stats.counts()))
+                        .collect();
+                    let (start_time, end_time) = stats.time_range();
+                    Some(output::render_prometheus(
+                        &repo_counts,
+                        start_time,
+                        end_time,
+                    ))
+                }
+                Format::Json if !bucket_stats.is_empty() => {
+                    match output::render_buckets_json(&bucket_stats, args.distribution) {
+                        Ok(rendered) => Some(rendered),
+                        Err(err) => {
+                            error!("failed to render stats as json: {}", err);
+                            process::exit(1);
+                        }
+                    }
+                }
+                Format::Json => {
+                    match output::render_json(
+                        &stats,
+                        team_counts.as_ref(),
+                        repo_counts.as_ref(),
+                        args.distribution,
+                    ) {
+                        Ok(rendered) => Some(rendered),
+                        Err(err) => {
+                            error!("failed to render stats as json: {}", err);
+                            process::exit(1);
+                        }
+                    }
+                }
+            };
+            match (rendered, &args.output_file) {
+                (Some(rendered), Some(output_file)) => {
+                    fs::write(output_file, rendered).unwrap_or_else(|err| {
+                        error!("failed to write stats to '{}': {}", output_file, err);
+                        process::exit(1);
+                    });
+                    info!("stats written to {}", output_file);
+                }
+                (Some(rendered), None) => print!("{}", rendered),
+                (None, Some(_)) if !args.raw_events => {
+                    warn!("the debug output only goes to the logs, ignore the output file")
+                }
+                (None, _) => {}
+            }
+            // Keep the structured output on the stdout parsable as a whole.
+            let extra_to_stderr = (args.raw_events
+                || matches!(
+                    args.output_format,
+                    Format::Json | Format::Csv | Format::Prometheus
+                ))
+                && args.output_file.is_none();
+            let print_extra = |extra: String| {
+                if extra_to_stderr {
+                    eprint!("{}", extra);
                 } else {
-                    stats = Some(s);
+                    print!("{}", extra);
                 }
+            };
+            if args.rank {
+                let scores = stats
+                    .score()
+                    .into_iter()
+                    .map(|(user, score)| (display_name(user), score))
+                    .collect();
+                print_extra(output::render_ranking(&scores));
             }
-            None => break,
+            if let Some(top) = args.top {
+                for metric in Metric::ALL {
+                    let leaderboard: Vec<(String, u64)> = stats
+                        .leaderboard(metric, top)
+                        .into_iter()
+                        .map(|(user, count)| (display_name(user), count))
+                        .collect();
+                    if !leaderboard.is_empty() {
+                        print_extra(output::render_leaderboard(metric, &leaderboard));
+                    }
+                }
+            }
+            info!("first seen: {:?}", stats.first_seen());
+            info!("last seen: {:?}", stats.last_seen());
+            if let Some(response_time) = stats.time_to_first_response() {
+                info!(
+                    "time to first response of {} PRs: min {}, median {}, max {}",
+                    response_time.responded_prs,
+                    response_time.min,
+                    response_time.median,
+                    response_time.max
+                );
+            }
+            for (repo, coverage) in stats.review_coverage() {
+                info!(
+                    "review coverage of '{}': {}/{} PRs reviewed ({:.1}%)",
+                    repo,
+                    coverage.reviewed_prs,
+                    coverage.total_prs,
+                    coverage.ratio() * 100.0
+                );
+            }
+            if args.decay_half_life.is_some() {
+                info!("decayed scores: {:?}", stats.decayed_scores());
+            }
+            if args.distribution {
+                for metric in Metric::ALL {
+                    info!(
+                        "{:?} distribution: {:?}",
+                        metric,
+                        stats.distribution(metric)
+                    );
+                }
+            }
+            if let Some(baseline_path) = config.report_baseline() {
+                if let Some(deltas) = render_baseline_deltas(&baseline_path, &stats) {
+                    print_extra(deltas);
+                }
+            }
+            if args.notify && is_partial {
+                warn!("skip notifying the partial stats");
+            } else if args.notify {
+                let notifiers = config.notifiers();
+                if notifiers.is_empty() {
+                    error!("no notifiers configured, skip the notification");
+                }
+                let mut delivery_state = match &args.notify_state {
+                    Some(state_path) => {
+                        DeliveryState::load(state_path, &stats).unwrap_or_else(|err| {
+                            warn!(
+                                "failed to load notify state '{}', send to all the webhooks: {}",
+                                state_path, err
+                            );
+                            DeliveryState::new(&stats)
+                        })
+                    }
+                    None => DeliveryState::new(&stats),
+                };
+                delivery_state
+                    .deliver(
+                        &stats,
+                        notifiers,
+                        notifier::new,
+                        args.notify_state.as_deref().map(Path::new),
+                    )
+                    .await;
+            }
+            // Fail the run if any repo is missing from the stats rather than partial.
+            let mut hard_failed_repos: Vec<&String> = stats.hard_failed_repos().iter().collect();
+            hard_failed_repos.sort();
+            if !hard_failed_repos.is_empty() || failed_tasks > 0 || is_partial {
+                error!(
+                    "stats are incomplete, {} repos failed to be fetched {:?} and {} tasks failed",
+                    hard_failed_repos.len(),
+                    hard_failed_repos,
+                    failed_tasks
+                );
+                process::exit(1);
+            }
+            // The run completes, nothing to resume.
+            if checkpoint_path.exists() {
+                if let Err(err) = fs::remove_file(&checkpoint_path) {
+                    warn!(
+                        "failed to remove checkpoint '{}': {}",
+                        checkpoint_path.display(),
+                        err
+                    );
+                }
+            }
+            if args.fail_if_empty && stats.is_empty() {
+                error!(
+                    "no activity counted for any user in any of the {} repos, check the config and the token",
+                    repo_count
+                );
+                process::exit(1);
+            }
+            // Only a successful run updates the baseline, a partial one would skew the
+            // deltas of the next run.
+            if let Some(baseline_path) = config.report_baseline() {
+                save_baseline(&baseline_path, &stats);
+            }
+        }
+        None => {
+            error!(
+                "no stats generated from any of the {} configured repos, all of them failed to be fetched",
+                repo_count
+            );
+            process::exit(1);
         }
     }
-    match stats {
-        Some(stats) => info!("all stats merged: {:?}", stats),
-        None => info!("no stats generated at all"),
+}
+
+/// Install the Ctrl-C handler, which tells the run to be interrupted through the returned
+/// receiver. A second Ctrl-C exits right away.
+fn handle_interrupt() -> watch::Receiver<bool> {
+    let (interrupt_tx, interrupt_rx) = watch::channel(false);
+    tokio::spawn(async move {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            warn!("failed to listen for ctrl-c: {}", err);
+            return;
+        }
+        warn!(
+            "interrupted, wait {:?} for the repos being fetched, press ctrl-c again to exit now",
+            INTERRUPT_GRACE_PERIOD
+        );
+        let _ = interrupt_tx.send(true);
+        if tokio::signal::ctrl_c().await.is_ok() {
+            process::exit(130);
+        }
+    });
+    interrupt_rx
+}
+
+/// Parse the time relative to the given now, or exit if it's invalid.
+fn parse_time(name: &str, time: &str, now: DateTime<Utc>) -> DateTime<Utc> {
+    relative_time::parse(time, now).unwrap_or_else(|err| {
+        error!("failed to parse {}: {}", name, err);
+        process::exit(1);
+    })
+}
+
+/// Collect the stats of the current and the previous periods, then print the changes of
+/// each user between them.
+async fn compare(
+    config: &Config,
+    start_time: Option<&str>,
+    end_time: Option<&str>,
+    args: CompareArgs,
+) {
+    let Some(start_time) = start_time else {
+        error!("the start time is required, set it by '--start-time'");
+        process::exit(1);
+    };
+    // The relative times are relative to the same now.
+    let now = Utc::now();
+    let start_time = parse_time("start time", start_time, now);
+    let end_time = end_time.map_or(now, |end_time| parse_time("end time", end_time, now));
+    let previous_start_time = parse_time("previous start time", &args.previous_start_time, now);
+    let previous_end_time = args
+        .previous_end_time
+        .as_deref()
+        .map_or(start_time, |end_time| {
+            parse_time("previous end time", end_time, now)
+        });
+    for (start_time, end_time) in [
+        (start_time, end_time),
+        (previous_start_time, previous_end_time),
+    ] {
+        if start_time > end_time {
+            error!(
+                "start time {} is after end time {}, nothing to count",
+                start_time, end_time
+            );
+            process::exit(1);
+        }
+    }
+    info!(
+        "comparing {} ~ {} against {} ~ {}",
+        start_time, end_time, previous_start_time, previous_end_time
+    );
+
+    // Count both periods by the same pipeline as `report`.
+    let options = args.fetch.run_options();
+    let (current, previous) = tokio::join!(
+        gh_overseer::run_with(config, start_time, end_time, &options),
+        gh_overseer::run_with(config, previous_start_time, previous_end_time, &options)
+    );
+    let (current, previous) = match (current, previous) {
+        (Ok(current), Ok(previous)) => (current, previous),
+        (Err(err), _) | (_, Err(err)) => {
+            error!("failed to collect the stats: {}", err);
+            process::exit(1);
+        }
+    };
+    print!(
+        "{}",
+        output::render_comparison(&current.counts().compare(&previous.counts()))
+    );
+}
+
+/// Wait until the deadline of the run passes, which never happens without a deadline.
+async fn deadline_passed(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Wait until the run is interrupted, which never happens if the Ctrl-C handler is gone.
+async fn interrupted(mut interrupt_rx: watch::Receiver<bool>) {
+    if interrupt_rx
+        .wait_for(|interrupted| *interrupted)
+        .await
+        .is_err()
+    {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Write the raw events as JSON Lines to the output file or the stdout as they come in,
+/// until all the senders are gone.
+fn write_raw_events(
+    mut rx: UnboundedReceiver<RawEvent>,
+    output_file: Option<String>,
+) -> JoinHandle<()> {
+    let mut sink: Box<dyn Write + Send> = match &output_file {
+        Some(output_file) => match fs::File::create(output_file) {
+            Ok(file) => Box::new(BufWriter::new(file)),
+            Err(err) => {
+                error!(
+                    "failed to create raw events file '{}': {}",
+                    output_file, err
+                );
+                process::exit(1);
+            }
+        },
+        None => Box::new(io::stdout()),
+    };
+    tokio::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            let written = serde_json::to_string(&event)
+                .map_err(io::Error::from)
+                .and_then(|line| writeln!(sink, "{}", line));
+            if let Err(err) = written {
+                error!("failed to write raw event: {}", err);
+                return;
+            }
+        }
+        if let Err(err) = sink.flush() {
+            error!("failed to flush raw events: {}", err);
+        }
+        if let Some(output_file) = output_file {
+            info!("raw events written to {}", output_file);
+        }
+    })
+}
+
+/// Show a spinner per repo with the number of the items fetched of each listing, until
+/// all the fetchers are done.
+fn show_fetch_progress(mut rx: UnboundedReceiver<Progress>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let multi = MultiProgress::new();
+        let style = ProgressStyle::with_template("{spinner} {prefix}: {wide_msg}")
+            .expect("the progress template should be valid");
+        let mut bars: HashMap<String, (ProgressBar, BTreeMap<&'static str, usize>)> =
+            HashMap::new();
+        while let Some(progress) = rx.recv().await {
+            let (bar, fetched) = bars.entry(progress.repo.clone()).or_insert_with(|| {
+                let bar = multi.add(ProgressBar::new_spinner().with_style(style.clone()));
+                bar.set_prefix(progress.repo.clone());
+                (bar, BTreeMap::new())
+            });
+            *fetched.entry(progress.listing).or_insert(0) += progress.fetched;
+            let message: Vec<String> = fetched
+                .iter()
+                .map(|(listing, fetched)| format!("{} {}", listing, fetched))
+                .collect();
+            bar.set_message(message.join(", "));
+            bar.tick();
+        }
+        for (bar, _) in bars.values() {
+            bar.finish();
+        }
+    })
+}
+
+/// Find and print the stale issues and PRs of each repo.
+async fn report_stale_items(
+    octocrab: &Octocrab,
+    config: &Config,
+    repos: Vec<String>,
+    stale_threshold: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) {
+    let retry = gh_overseer::retry_policy(config);
+    let semaphore = Arc::new(Semaphore::new(config.fetch_max_concurrency()));
+    let mut tasks = Vec::new();
+    for repo in repos {
+        let permit = semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the semaphore should never be closed");
+        let mut fetcher = Fetcher::new(octocrab.clone(), &repo, stale_threshold, end_time, retry)
+            .unwrap_or_else(|err| {
+                error!("failed to init fetcher for '{}': {}", repo, err);
+                process::exit(1);
+            });
+        fetcher.set_issue_comments_concurrency(config.fetch_issue_comments_concurrency());
+        fetcher.set_page_size(config.fetch_page_size());
+        if let Some(base_url) = config.github_base_url() {
+            fetcher.set_base_url(&base_url);
+        }
+        let allowed_users: HashSet<String> = config.review_repo_users(&repo).into_iter().collect();
+        tasks.push((
+            repo,
+            tokio::spawn(async move {
+                let open_issues = fetcher.fetch_open_issues().recv().await.unwrap_or_default();
+                let comments = fetcher
+                    .fetch_issue_comments_since(
+                        open_issues.iter().map(|issue| issue.number).collect(),
+                        None,
+                    )
+                    .recv()
+                    .await
+                    .unwrap_or_default();
+                drop(permit);
+                staleness::find_stale_items(
+                    &open_issues,
+                    &comments,
+                    &allowed_users,
+                    stale_threshold,
+                    end_time,
+                )
+            }),
+        ));
+    }
+    for (repo, task) in tasks {
+        let stale_items = match task.await {
+            Ok(stale_items) => stale_items,
+            Err(err) => {
+                error!("failed to finish task for '{}': {}", repo, err);
+                continue;
+            }
+        };
+        println!(
+            "{}: {} stale items without maintainer activity since {}",
+            repo,
+            stale_items.len(),
+            stale_threshold
+        );
+        for item in stale_items {
+            println!(
+                "  #{} [{}] {} (last maintainer activity: {})",
+                item.number,
+                if item.is_pull_request { "pr" } else { "issue" },
+                item.title,
+                item.last_maintainer_activity
+                    .map_or("never".to_string(), |last_activity| last_activity
+                        .to_string())
+            );
+        }
+    }
+}
+
+/// Look up each user on GitHub and report the ones that do not exist or have a different
+/// login, e.g. renamed accounts. Returns whether all users are valid.
+async fn validate_users(octocrab: &Octocrab, users: Vec<String>) -> bool {
+    let mut valid = true;
+    for user in users {
+        match octocrab
+            .get::<octocrab::models::Author, _, ()>(format!("/users/{}", user), None)
+            .await
+        {
+            Ok(author) if author.login == user => info!("user '{}' is valid", user),
+            Ok(author) => {
+                warn!(
+                    "user '{}' resolves to a different login '{}', it might have been renamed",
+                    user, author.login
+                );
+                valid = false;
+            }
+            Err(err) => {
+                warn!(
+                    "user '{}' does not exist or failed to look up: {}",
+                    user, err
+                );
+                valid = false;
+            }
+        }
     }
+    valid
+}
+
+/// Render the changes of the current counts against the baseline, or `None` if there is
+/// no baseline yet.
+fn render_baseline_deltas(baseline_path: &str, stats: &Stats) -> Option<String> {
+    match baseline::load(baseline_path) {
+        Ok(Some(baseline)) => Some(format!(
+            "\ndeltas against baseline '{}':\n{}",
+            baseline_path,
+            output::render_comparison(&stats.counts().compare(&baseline))
+        )),
+        Ok(None) => {
+            info!(
+                "no baseline found at '{}', it will be created once this run succeeds",
+                baseline_path
+            );
+            None
+        }
+        Err(err) => {
+            error!("{}", err);
+            process::exit(1);
+        }
+    }
+}
+
+/// Overwrite the baseline with the current counts.
+fn save_baseline(baseline_path: &str, stats: &Stats) {
+    baseline::save(baseline_path, &stats.counts()).unwrap_or_else(|err| {
+        error!("{}", err);
+        process::exit(1);
+    });
+    info!("baseline saved to '{}'", baseline_path);
 }
 
 fn init_logger(log_level: &str) {
     let mut builder = env_logger::Builder::from_default_env();
     builder
-        .target(env_logger::Target::Stdout)
+        .target(env_logger::Target::Stderr)
         .filter_level(get_log_level(log_level))
         .init();
 }
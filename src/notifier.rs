@@ -0,0 +1,322 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    error::Error,
+    fmt,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::stats::{Metric, Stats};
+
+/// The title of the messages.
+const TITLE: &str = "GitHub Overseer Stats";
+
+/// The metrics shown in the messages.
+const MESSAGE_METRICS: [Metric; 9] = [
+    Metric::Issues,
+    Metric::Prs,
+    Metric::IssueComments,
+    Metric::PrReviews,
+    Metric::ReviewComments,
+    Metric::Lgtms,
+    Metric::ChangesRequested,
+    Metric::CommentedReviews,
+    Metric::Labels,
+];
+
+/// The chat platforms the stats can be sent to by their incoming webhooks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Platform {
+    Feishu,
+    Slack,
+    Discord,
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Platform::Feishu => write!(f, "feishu"),
+            Platform::Slack => write!(f, "slack"),
+            Platform::Discord => write!(f, "discord"),
+        }
+    }
+}
+
+/// The notifier sending the stats to a chat platform, formatted into its message schema.
+#[async_trait]
+pub trait Notifier {
+    async fn send(&self, stats: &Stats) -> Result<(), Box<dyn Error>>;
+}
+
+/// Create the notifier of the platform sending to the webhook, where the Feishu notifier
+/// @-mentions the users by their open IDs.
+pub fn new(
+    platform: Platform,
+    webhook_url: String,
+    feishu_open_ids: HashMap<String, String>,
+) -> Box<dyn Notifier> {
+    match platform {
+        Platform::Feishu => {
+            Box::new(FeishuNotifier::new(webhook_url).with_open_ids(feishu_open_ids))
+        }
+        Platform::Slack => Box::new(SlackNotifier::new(webhook_url)),
+        Platform::Discord => Box::new(DiscordNotifier::new(webhook_url)),
+    }
+}
+
+/// The notifier sending the stats to a Feishu/Lark bot by its webhook.
+#[derive(Debug)]
+pub struct FeishuNotifier {
+    webhook_url: String,
+    client: reqwest::Client,
+    // The open ID of each login to @-mention in the card.
+    open_ids: HashMap<String, String>,
+}
+
+impl FeishuNotifier {
+    pub fn new(webhook_url: String) -> Self {
+        Self {
+            webhook_url,
+            client: reqwest::Client::new(),
+            open_ids: HashMap::new(),
+        }
+    }
+
+    /// @-mention the users with the open IDs by their logins in the card, so they see
+    /// their numbers. The users without any open ID are shown as plain text.
+    pub fn with_open_ids(mut self, open_ids: HashMap<String, String>) -> Self {
+        self.open_ids = open_ids;
+        self
+    }
+}
+
+#[async_trait]
+impl Notifier for FeishuNotifier {
+    /// Send the stats as an interactive card to the webhook.
+    async fn send(&self, stats: &Stats) -> Result<(), Box<dyn Error>> {
+        let response: Value = self
+            .client
+            .post(&self.webhook_url)
+            .json(&card(stats, &self.open_ids))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        // The webhook responds with a non-zero code on failures, e.g. an invalid card.
+        match response.get("code").and_then(Value::as_i64) {
+            Some(0) | None => Ok(()),
+            Some(code) => Err(format!(
+                "feishu webhook responded with code {}: {}",
+                code,
+                response
+                    .get("msg")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+            )
+            .into()),
+        }
+    }
+}
+
+/// The notifier sending the stats to a Slack channel by its incoming webhook.
+#[derive(Debug)]
+pub struct SlackNotifier {
+    webhook_url: String,
+    client: reqwest::Client,
+}
+
+impl SlackNotifier {
+    pub fn new(webhook_url: String) -> Self {
+        Self {
+            webhook_url,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    /// Send the stats as a message of blocks to the webhook, which responds with a plain
+    /// "ok" on success.
+    async fn send(&self, stats: &Stats) -> Result<(), Box<dyn Error>> {
+        self.client
+            .post(&self.webhook_url)
+            .json(&slack_message(stats))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// The notifier sending the stats to a Discord channel by its webhook.
+#[derive(Debug)]
+pub struct DiscordNotifier {
+    webhook_url: String,
+    client: reqwest::Client,
+}
+
+impl DiscordNotifier {
+    pub fn new(webhook_url: String) -> Self {
+        Self {
+            webhook_url,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for DiscordNotifier {
+    /// Send the stats as a message with an embed to the webhook, which responds with no
+    /// content on success.
+    async fn send(&self, stats: &Stats) -> Result<(), Box<dyn Error>> {
+        self.client
+            .post(&self.webhook_url)
+            .json(&discord_message(stats))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Build the interactive card message of the stats for Feishu/Lark, with the `<at>` tags
+/// of the users with the open IDs.
+fn card(stats: &Stats, open_ids: &HashMap<String, String>) -> Value {
+    let (start_time, end_time) = stats.time_range();
+    let mentions: HashMap<String, String> = open_ids
+        .iter()
+        .map(|(user, open_id)| (user.clone(), format!("<at id={}></at>", open_id)))
+        .collect();
+    json!({
+        "msg_type": "interactive",
+        "card": {
+            "config": {
+                "wide_screen_mode": true,
+            },
+            "header": {
+                "template": "blue",
+                "title": {
+                    "tag": "plain_text",
+                    "content": TITLE,
+                },
+            },
+            "elements": [
+                {
+                    "tag": "div",
+                    "text": {
+                        "tag": "lark_md",
+                        "content": format!("{} ~ {}", time(start_time), time(end_time)),
+                    },
+                },
+                {
+                    "tag": "hr",
+                },
+                {
+                    "tag": "div",
+                    "text": {
+                        "tag": "lark_md",
+                        "content": lines(stats, "**", &mentions),
+                    },
+                },
+            ],
+        },
+    })
+}
+
+/// Build the message of the stats for Slack, whose bold text is `*text*`.
+fn slack_message(stats: &Stats) -> Value {
+    let (start_time, end_time) = stats.time_range();
+    let time_range = format!("{} ~ {}", time(start_time), time(end_time));
+    json!({
+        "text": format!("{}: {}", TITLE, time_range),
+        "blocks": [
+            {
+                "type": "header",
+                "text": {
+                    "type": "plain_text",
+                    "text": TITLE,
+                },
+            },
+            {
+                "type": "context",
+                "elements": [
+                    {
+                        "type": "mrkdwn",
+                        "text": time_range,
+                    },
+                ],
+            },
+            {
+                "type": "section",
+                "text": {
+                    "type": "mrkdwn",
+                    "text": lines(stats, "*", &HashMap::new()),
+                },
+            },
+        ],
+    })
+}
+
+/// Build the message of the stats for Discord, whose bold text is `**text**`.
+fn discord_message(stats: &Stats) -> Value {
+    let (start_time, end_time) = stats.time_range();
+    json!({
+        "embeds": [
+            {
+                "title": TITLE,
+                "description": lines(stats, "**", &HashMap::new()),
+                "footer": {
+                    "text": format!("{} ~ {}", time(start_time), time(end_time)),
+                },
+            },
+        ],
+    })
+}
+
+/// Build the markdown lines of the stats, with one line per user in bold listing the
+/// counts of each metric over the time range. The users with a mention markup are
+/// mentioned after their logins.
+fn lines(stats: &Stats, bold: &str, mentions: &HashMap<String, String>) -> String {
+    let counts = stats.counts();
+    let users: BTreeSet<&String> = MESSAGE_METRICS
+        .iter()
+        .flat_map(|metric| counts.metric(*metric).keys())
+        .collect();
+    let lines: Vec<String> = users
+        .into_iter()
+        .map(|user| {
+            let user_counts = MESSAGE_METRICS
+                .iter()
+                .map(|metric| {
+                    format!(
+                        "{} {}",
+                        metric,
+                        counts.metric(*metric).get(user).copied().unwrap_or(0)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            match mentions.get(user) {
+                Some(mention) => {
+                    format!("{}{}{} {}: {}", bold, user, bold, mention, user_counts)
+                }
+                None => format!("{}{}{}: {}", bold, user, bold, user_counts),
+            }
+        })
+        .collect();
+    if lines.is_empty() {
+        "No activity within the time range.".to_string()
+    } else {
+        lines.join("\n")
+    }
+}
+
+fn time(date_time: DateTime<Utc>) -> String {
+    date_time.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+}
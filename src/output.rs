@@ -0,0 +1,763 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::stats::{Change, Counts, DistributionSummary, Metric, Stats};
+
+const ANSI_BOLD: &str = "\x1b[1m";
+const ANSI_DIM: &str = "\x1b[2m";
+const ANSI_CYAN: &str = "\x1b[36m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+/// The public view of the stats in the structured outputs.
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+    start_time: String,
+    end_time: String,
+    #[serde(flatten)]
+    counts: Counts,
+    first_seen: BTreeMap<String, String>,
+    last_seen: BTreeMap<String, String>,
+    review_coverage: BTreeMap<String, f64>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    decayed_scores: BTreeMap<String, f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_to_first_response: Option<ResponseTimeReport>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    distribution: BTreeMap<&'static str, DistributionSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    teams: Option<&'a Counts>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repos: Option<&'a BTreeMap<String, Counts>>,
+}
+
+/// The time the PRs waited before the first response, in seconds.
+#[derive(Debug, Serialize)]
+struct ResponseTimeReport {
+    responded_prs: usize,
+    min_secs: i64,
+    median_secs: i64,
+    max_secs: i64,
+}
+
+impl<'a> Report<'a> {
+    fn new(
+        stats: &Stats,
+        team_counts: Option<&'a Counts>,
+        repo_counts: Option<&'a BTreeMap<String, Counts>>,
+        distribution: bool,
+    ) -> Self {
+        let (start_time, end_time) = stats.time_range();
+        let rfc3339 = |times: &HashMap<String, DateTime<Utc>>| {
+            times
+                .iter()
+                .map(|(user, time)| (user.clone(), time.to_rfc3339()))
+                .collect()
+        };
+        Self {
+            start_time: start_time.to_rfc3339(),
+            end_time: end_time.to_rfc3339(),
+            counts: stats.counts(),
+            first_seen: rfc3339(&stats.first_seen()),
+            last_seen: rfc3339(&stats.last_seen()),
+            review_coverage: stats
+                .review_coverage()
+                .into_iter()
+                .map(|(repo, coverage)| (repo, coverage.ratio()))
+                .collect(),
+            decayed_scores: stats.decayed_scores().into_iter().collect(),
+            time_to_first_response: stats.time_to_first_response().map(|response_time| {
+                ResponseTimeReport {
+                    responded_prs: response_time.responded_prs,
+                    min_secs: response_time.min.num_seconds(),
+                    median_secs: response_time.median.num_seconds(),
+                    max_secs: response_time.max.num_seconds(),
+                }
+            }),
+            distribution: if distribution {
+                Metric::ALL
+                    .iter()
+                    .map(|metric| (metric.name(), stats.distribution(*metric)))
+                    .collect()
+            } else {
+                BTreeMap::new()
+            },
+            teams: team_counts,
+            repos: repo_counts,
+        }
+    }
+}
+
+/// Render the stats as a JSON object with the time range, the per-user counts of each
+/// metric, the weighted LGTMs, the average comment length, the repos touched and the
+/// first and last activity times of each user, the review coverage of each repo and the
+/// time to first response of the PRs, plus the decayed score of each user, the per-team
+/// and per-repo counts if any. The distribution of each metric across the active users
+/// is included if asked.
+pub fn render_json(
+    stats: &Stats,
+    team_counts: Option<&Counts>,
+    repo_counts: Option<&BTreeMap<String, Counts>>,
+    distribution: bool,
+) -> serde_json::Result<String> {
+    let report = Report::new(stats, team_counts, repo_counts, distribution);
+    let mut json = serde_json::to_string_pretty(&report)?;
+    json.push('\n');
+    Ok(json)
+}
+
+/// Render the stats of each time bucket as a JSON array in the chronological order, where
+/// each element is the same as `render_json` without the teams and repos.
+pub fn render_buckets_json(
+    bucket_stats: &[Stats],
+    distribution: bool,
+) -> serde_json::Result<String> {
+    let reports: Vec<Report> = bucket_stats
+        .iter()
+        .map(|stats| Report::new(stats, None, None, distribution))
+        .collect();
+    let mut json = serde_json::to_string_pretty(&reports)?;
+    json.push('\n');
+    Ok(json)
+}
+
+/// The names of the columns of the PR size buckets from XS to XL.
+const PR_SIZE_COLUMNS: [&str; 5] = ["prs_xs", "prs_s", "prs_m", "prs_l", "prs_xl"];
+
+/// A column of the per-key counts in the table and CSV outputs.
+#[derive(Debug, Clone)]
+pub struct Column {
+    name: &'static str,
+    source: ColumnSource,
+}
+
+/// Where the counts of a column come from.
+#[derive(Debug, Clone)]
+enum ColumnSource {
+    /// A single metric or the sum of several ones.
+    Metrics(Vec<Metric>),
+    /// The PRs created in the size bucket at the index.
+    PrSize(usize),
+    /// The LGTMs weighted by the PR sizes.
+    WeightedLgtms,
+    /// The average comment length, "-" for the keys without any comment and the ones
+    /// rolled up, e.g. the teams, and for the total.
+    AvgCommentLength,
+    /// The distinct repos touched, "-" for the keys without any activity and the ones
+    /// rolled up, and for the total.
+    ReposTouched,
+}
+
+impl Column {
+    /// Get the cell of the key in the column, zero if missing from all the metrics.
+    fn cell(&self, counts: &Counts, key: &str) -> String {
+        match &self.source {
+            ColumnSource::Metrics(metrics) => metrics
+                .iter()
+                .map(|metric| counts.metric(*metric).get(key).copied().unwrap_or(0))
+                .sum::<u64>()
+                .to_string(),
+            ColumnSource::PrSize(bucket) => counts
+                .pr_sizes
+                .get(key)
+                .map_or(0, |sizes| sizes[*bucket])
+                .to_string(),
+            ColumnSource::WeightedLgtms => format!(
+                "{:.1}",
+                counts.weighted_lgtms.get(key).copied().unwrap_or(0.0)
+            ),
+            ColumnSource::AvgCommentLength => counts
+                .avg_comment_length
+                .get(key)
+                .map_or("-".to_string(), |length| format!("{:.1}", length)),
+            ColumnSource::ReposTouched => counts
+                .repos_touched
+                .get(key)
+                .map_or("-".to_string(), |repos| repos.to_string()),
+        }
+    }
+
+    /// Get the total of all the keys in the column, "-" if it can't be summed up.
+    fn total(&self, counts: &Counts) -> String {
+        match &self.source {
+            ColumnSource::Metrics(metrics) => metrics
+                .iter()
+                .map(|metric| counts.metric(*metric).values().sum::<u64>())
+                .sum::<u64>()
+                .to_string(),
+            ColumnSource::PrSize(bucket) => counts
+                .pr_sizes
+                .values()
+                .map(|sizes| sizes[*bucket])
+                .sum::<u64>()
+                .to_string(),
+            ColumnSource::WeightedLgtms => {
+                format!("{:.1}", counts.weighted_lgtms.values().sum::<f64>())
+            }
+            ColumnSource::AvgCommentLength | ColumnSource::ReposTouched => "-".to_string(),
+        }
+    }
+}
+
+/// Get the columns of the table and CSV outputs in the metric order, one per metric. The
+/// issue comments and the review comments are combined into a single "comments" column
+/// in place of the issue comments if merged, for the teams not telling them apart. The
+/// PRs created in each size bucket follow the PRs column, and the weighted LGTMs follow
+/// the LGTMs column, if asked. The average comment length and the repos touched come
+/// last.
+pub fn columns(merge_comment_metrics: bool, pr_sizes: bool, weighted_lgtms: bool) -> Vec<Column> {
+    Metric::ALL
+        .iter()
+        .flat_map(|metric| {
+            let column = match metric {
+                Metric::IssueComments if merge_comment_metrics => Some(Column {
+                    name: "comments",
+                    source: ColumnSource::Metrics(vec![
+                        Metric::IssueComments,
+                        Metric::ReviewComments,
+                    ]),
+                }),
+                Metric::ReviewComments if merge_comment_metrics => None,
+                _ => Some(Column {
+                    name: metric.name(),
+                    source: ColumnSource::Metrics(vec![*metric]),
+                }),
+            };
+            let pr_size_columns = PR_SIZE_COLUMNS
+                .iter()
+                .enumerate()
+                .filter(|_| pr_sizes && *metric == Metric::Prs)
+                .map(|(bucket, name)| Column {
+                    name,
+                    source: ColumnSource::PrSize(bucket),
+                });
+            let weighted_lgtms_column =
+                (weighted_lgtms && *metric == Metric::Lgtms).then_some(Column {
+                    name: "weighted_lgtms",
+                    source: ColumnSource::WeightedLgtms,
+                });
+            column
+                .into_iter()
+                .chain(pr_size_columns)
+                .chain(weighted_lgtms_column)
+        })
+        .chain([
+            Column {
+                name: "avg_comment_length",
+                source: ColumnSource::AvgCommentLength,
+            },
+            Column {
+                name: "repos_touched",
+                source: ColumnSource::ReposTouched,
+            },
+        ])
+        .collect()
+}
+
+/// Get all the keys of the counts, sorted.
+fn keys(counts: &Counts) -> BTreeSet<&String> {
+    Metric::ALL
+        .iter()
+        .flat_map(|metric| counts.metric(*metric).keys())
+        .chain(counts.pr_sizes.keys())
+        .chain(counts.weighted_lgtms.keys())
+        .chain(counts.avg_comment_length.keys())
+        .chain(counts.repos_touched.keys())
+        .collect()
+}
+
+/// Render the counts as CSV, with a header row and one row per key (e.g. user or team)
+/// sorted by the key, so the diffs between runs are meaningful. Keys missing from some
+/// metrics are counted as zero.
+pub fn render_csv(counts: &Counts, key: &str, columns: &[Column]) -> String {
+    let keys = keys(counts);
+    let mut csv = std::iter::once(key.to_string())
+        .chain(columns.iter().map(|column| column.name.to_string()))
+        .collect::<Vec<_>>()
+        .join(",");
+    csv.push('\n');
+    for key in keys {
+        let row = std::iter::once(csv_field(key))
+            .chain(columns.iter().map(|column| column.cell(counts, key)))
+            .collect::<Vec<_>>()
+            .join(",");
+        csv.push_str(&row);
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Quote the CSV field if it contains any special character, e.g. a team name with comma.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Render the users ranked by their scores in descending order, one line per user.
+pub fn render_ranking(scores: &HashMap<String, f64>) -> String {
+    let mut scores: Vec<(&String, &f64)> = scores.iter().collect();
+    scores.sort_by(|(user_a, score_a), (user_b, score_b)| {
+        score_b.total_cmp(score_a).then_with(|| user_a.cmp(user_b))
+    });
+    let width = scores
+        .iter()
+        .map(|(user, _)| user.chars().count())
+        .max()
+        .unwrap_or(0);
+    scores
+        .into_iter()
+        .enumerate()
+        .map(|(index, (user, score))| {
+            format!(
+                "{:>3}. {:<width$} {:.2}\n",
+                index + 1,
+                user,
+                score,
+                width = width
+            )
+        })
+        .collect()
+}
+
+/// Render the leaderboard of the metric, with a title line and one line per user.
+pub fn render_leaderboard(metric: Metric, leaderboard: &[(String, u64)]) -> String {
+    let width = leaderboard
+        .iter()
+        .map(|(user, _)| user.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut output = format!("Top {} by {}:\n", leaderboard.len(), metric.name());
+    for (index, (user, count)) in leaderboard.iter().enumerate() {
+        output.push_str(&format!(
+            "{:>3}. {:<width$} {}\n",
+            index + 1,
+            user,
+            count,
+            width = width
+        ));
+    }
+    output
+}
+
+/// Render the per-user counts of each repo in the Prometheus text exposition format, one
+/// gauge per metric named like `gh_overseer_prs`, labeled by the user and the repo. The
+/// time range goes into the `gh_overseer_start_time_seconds` and
+/// `gh_overseer_end_time_seconds` gauges as the Unix timestamps.
+pub fn render_prometheus(
+    repo_counts: &BTreeMap<String, Counts>,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> String {
+    let mut output = String::new();
+    for (name, help, time) in [
+        (
+            "start_time_seconds",
+            "The start of the time range",
+            start_time,
+        ),
+        ("end_time_seconds", "The end of the time range", end_time),
+    ] {
+        output.push_str(&format!("# HELP gh_overseer_{} {}.\n", name, help));
+        output.push_str(&format!("# TYPE gh_overseer_{} gauge\n", name));
+        output.push_str(&format!("gh_overseer_{} {}\n", name, time.timestamp()));
+    }
+    for metric in Metric::ALL {
+        output.push_str(&format!(
+            "# HELP gh_overseer_{} The {} of each user in each repo within the time range.\n",
+            metric,
+            metric.name().replace('_', " ")
+        ));
+        output.push_str(&format!("# TYPE gh_overseer_{} gauge\n", metric));
+        for (repo, counts) in repo_counts {
+            let users: BTreeMap<&String, &u64> = counts.metric(metric).iter().collect();
+            for (user, count) in users {
+                output.push_str(&format!(
+                    "gh_overseer_{}{{user=\"{}\",repo=\"{}\"}} {}\n",
+                    metric,
+                    prometheus_label_value(user),
+                    prometheus_label_value(repo),
+                    count
+                ));
+            }
+        }
+    }
+    output
+}
+
+/// Escape the backslash, double-quote and line feed in the Prometheus label value.
+fn prometheus_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Render the totals of the metrics in a single line in the metric order, e.g.
+/// "issues=42 prs=17 issue_comments=300".
+pub fn render_totals(totals: &HashMap<&'static str, u64>) -> String {
+    let totals: Vec<String> = Metric::ALL
+        .iter()
+        .map(|metric| format!("{}={}", metric, totals.get(metric.name()).unwrap_or(&0)))
+        .collect();
+    totals.join(" ")
+}
+
+/// Render the counts as an aligned text table, with a header row, one row per key (e.g.
+/// user or team) and a totals row at the bottom. The rows are sorted by the given metric
+/// in descending order, and then by the key. If colored, the header and totals rows are
+/// bold, the keys are cyan and the zero counts are dimmed by the ANSI escape codes, e.g.
+/// for a terminal.
+pub fn render_table(
+    counts: &Counts,
+    key: &str,
+    sort_by: Metric,
+    columns: &[Column],
+    color: bool,
+) -> String {
+    let mut users: Vec<&String> = keys(counts).into_iter().collect();
+    users.sort_by_key(|user| {
+        std::cmp::Reverse(counts.metric(sort_by).get(*user).copied().unwrap_or(0))
+    });
+
+    let mut rows: Vec<Vec<String>> = Vec::with_capacity(users.len() + 2);
+    rows.push(
+        std::iter::once(key.to_string())
+            .chain(columns.iter().map(|column| column.name.to_string()))
+            .collect(),
+    );
+    for user in users {
+        rows.push(
+            std::iter::once(user.clone())
+                .chain(columns.iter().map(|column| column.cell(counts, user)))
+                .collect(),
+        );
+    }
+    rows.push(
+        std::iter::once("total".to_string())
+            .chain(columns.iter().map(|column| column.total(counts)))
+            .collect(),
+    );
+
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let separator = widths
+        .iter()
+        .map(|width| "-".repeat(*width))
+        .collect::<Vec<_>>()
+        .join("-+-");
+    let last = rows.len() - 1;
+    let mut table = String::new();
+    for (index, row) in rows.iter().enumerate() {
+        if index == last {
+            table.push_str(&separator);
+            table.push('\n');
+        }
+        let line = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(column, (cell, width))| {
+                // Left-align the user column and right-align the numbers.
+                let aligned = if column == 0 {
+                    format!("{:<width$}", cell, width = width)
+                } else {
+                    format!("{:>width$}", cell, width = width)
+                };
+                // Style the aligned cells, the escape codes take no width.
+                let style = match (index, column) {
+                    _ if !color => None,
+                    (0, _) => Some(ANSI_BOLD),
+                    _ if index == last => Some(ANSI_BOLD),
+                    (_, 0) => Some(ANSI_CYAN),
+                    _ if cell == "0" => Some(ANSI_DIM),
+                    _ => None,
+                };
+                match style {
+                    Some(style) => format!("{}{}{}", style, aligned, ANSI_RESET),
+                    None => aligned,
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" | ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+        if index == 0 {
+            table.push_str(&separator);
+            table.push('\n');
+        }
+    }
+    table
+}
+
+/// Render the distribution of each metric across the active users as an aligned text
+/// table, one row per metric in the metric order, as a summary below the counts.
+pub fn render_distribution(stats: &Stats) -> String {
+    let mut rows: Vec<[String; 5]> = vec![[
+        "metric".to_string(),
+        "active_users".to_string(),
+        "mean".to_string(),
+        "median".to_string(),
+        "std_dev".to_string(),
+    ]];
+    for metric in Metric::ALL {
+        let summary = stats.distribution(metric);
+        rows.push([
+            metric.to_string(),
+            summary.active_users.to_string(),
+            format!("{:.2}", summary.mean),
+            format!("{:.2}", summary.median),
+            format!("{:.2}", summary.std_dev),
+        ]);
+    }
+
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let mut table = String::new();
+    for row in &rows {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(column, (cell, width))| {
+                // Left-align the metric column and right-align the numbers.
+                if column == 0 {
+                    format!("{:<width$}", cell, width = width)
+                } else {
+                    format!("{:>width$}", cell, width = width)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" | ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Render the changes of each user from the previous period to the current one, one
+/// line per user and metric. The percentage change of a metric zero in the previous
+/// period is shown as "new".
+pub fn render_comparison(changes: &BTreeMap<String, Vec<(Metric, Change)>>) -> String {
+    let mut rows: Vec<[String; 6]> = vec![[
+        "user".to_string(),
+        "metric".to_string(),
+        "previous".to_string(),
+        "current".to_string(),
+        "delta".to_string(),
+        "change".to_string(),
+    ]];
+    for (user, user_changes) in changes {
+        for (metric, change) in user_changes {
+            rows.push([
+                user.clone(),
+                metric.to_string(),
+                change.previous.to_string(),
+                change.current.to_string(),
+                format!("{:+}", change.delta()),
+                change.percentage().map_or("new".to_string(), |percentage| {
+                    format!("{:+.1}%", percentage)
+                }),
+            ]);
+        }
+    }
+
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let mut table = String::new();
+    for row in &rows {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(column, (cell, width))| {
+                // Left-align the user and metric columns and right-align the numbers.
+                if column < 2 {
+                    format!("{:<width$}", cell, width = width)
+                } else {
+                    format!("{:>width$}", cell, width = width)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" | ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts() -> Counts {
+        Counts {
+            prs: HashMap::from([("alice".to_string(), 3), ("bob, jr.".to_string(), 1)]),
+            issue_comments: HashMap::from([("alice".to_string(), 2)]),
+            review_comments: HashMap::from([("bob, jr.".to_string(), 5)]),
+            pr_sizes: HashMap::from([
+                ("alice".to_string(), [1, 2, 0, 0, 0]),
+                ("bob, jr.".to_string(), [0, 0, 0, 0, 1]),
+            ]),
+            // Bob has no comment counted in the average, e.g. all of them are LGTMs.
+            weighted_lgtms: HashMap::from([("alice".to_string(), 2.5)]),
+            avg_comment_length: HashMap::from([("alice".to_string(), 12.25)]),
+            repos_touched: HashMap::from([("alice".to_string(), 2), ("bob, jr.".to_string(), 1)]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_csv() {
+        let csv = render_csv(&counts(), "user", &columns(true, true, true));
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            "user,issues,prs,prs_xs,prs_s,prs_m,prs_l,prs_xl,comments,pr_reviews,lgtms,\
+             weighted_lgtms,labels,reopens,suggestions_made,suggestions_accepted,changes_requested,commented_reviews,\
+             reactions_given,prs_merged,issues_closed,prs_closed,avg_comment_length,repos_touched"
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            "alice,0,3,1,2,0,0,0,2,0,0,2.5,0,0,0,0,0,0,0,0,0,0,12.2,2"
+        );
+        assert_eq!(
+            lines.next().unwrap(),
+            "\"bob, jr.\",0,1,0,0,0,0,1,5,0,0,0.0,0,0,0,0,0,0,0,0,0,0,-,1"
+        );
+        assert_eq!(lines.next(), None);
+        assert!(csv.ends_with('\n'));
+    }
+
+    #[test]
+    fn test_columns() {
+        let names = |columns: Vec<Column>| -> Vec<&str> {
+            columns.iter().map(|column| column.name).collect()
+        };
+        assert_eq!(
+            names(columns(false, false, false)),
+            Metric::ALL
+                .iter()
+                .map(|metric| metric.name())
+                .chain(["avg_comment_length", "repos_touched"])
+                .collect::<Vec<_>>()
+        );
+        let merged = names(columns(true, false, false));
+        assert!(merged.contains(&"comments"));
+        assert!(!merged.contains(&"issue_comments") && !merged.contains(&"review_comments"));
+        assert!(!merged.contains(&"prs_xs"));
+        assert!(!merged.contains(&"weighted_lgtms"));
+        let weighted = names(columns(false, false, true));
+        let lgtms = weighted.iter().position(|name| *name == "lgtms").unwrap();
+        assert_eq!(weighted[lgtms + 1], "weighted_lgtms");
+    }
+
+    #[test]
+    fn test_prometheus_label_value() {
+        assert_eq!(prometheus_label_value("alice"), "alice");
+        assert_eq!(
+            prometheus_label_value("a\\b \"c\"\nd"),
+            "a\\\\b \\\"c\\\"\\nd"
+        );
+    }
+
+    #[test]
+    fn test_render_prometheus() {
+        let repo_counts = BTreeMap::from([
+            ("tikv/tikv".to_string(), counts()),
+            (
+                "tikv/\"pd\"".to_string(),
+                Counts {
+                    prs: HashMap::from([("carol\\\n".to_string(), 2)]),
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let time = |time: &str| time.parse::<DateTime<Utc>>().unwrap();
+        let output = render_prometheus(
+            &repo_counts,
+            time("2024-01-01T00:00:00Z"),
+            time("2024-01-08T00:00:00Z"),
+        );
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines.contains(&"gh_overseer_start_time_seconds 1704067200"));
+        assert!(lines.contains(&"gh_overseer_end_time_seconds 1704672000"));
+        // Each metric is declared once as a gauge, even if no user has any count of it.
+        for metric in Metric::ALL {
+            let type_line = format!("# TYPE gh_overseer_{} gauge", metric);
+            let position = |line: &str| lines.iter().position(|other| *other == line);
+            assert_eq!(
+                lines.iter().filter(|line| **line == type_line).count(),
+                1,
+                "{}",
+                metric
+            );
+            // The help line comes right before the type line.
+            assert!(lines[position(&type_line).unwrap() - 1]
+                .starts_with(&format!("# HELP gh_overseer_{} ", metric)));
+        }
+        // One sample per user per repo of each metric, with the labels escaped.
+        let samples: Vec<&str> = lines
+            .iter()
+            .copied()
+            .filter(|line| line.starts_with("gh_overseer_prs{"))
+            .collect();
+        assert_eq!(
+            samples,
+            [
+                "gh_overseer_prs{user=\"carol\\\\\\n\",repo=\"tikv/\\\"pd\\\"\"} 2",
+                "gh_overseer_prs{user=\"alice\",repo=\"tikv/tikv\"} 3",
+                "gh_overseer_prs{user=\"bob, jr.\",repo=\"tikv/tikv\"} 1",
+            ]
+        );
+        assert!(!output.contains("gh_overseer_issues{"));
+        // Every sample is a single line.
+        assert!(lines
+            .iter()
+            .all(|line| line.starts_with("# ") || line.starts_with("gh_overseer_")));
+    }
+
+    #[test]
+    fn test_render_table() {
+        let table = render_table(
+            &counts(),
+            "user",
+            Metric::Prs,
+            &columns(true, true, false),
+            false,
+        );
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[0].starts_with("user     | issues | prs | prs_xs |"));
+        // Sorted by the PRs in descending order.
+        assert!(lines[2].starts_with("alice    |      0 |   3 |      1 |"));
+        assert!(lines[3].starts_with("bob, jr. |      0 |   1 |      0 |"));
+        assert!(lines[5].starts_with("total    |      0 |   4 |      1 |"));
+        assert!(lines[0].ends_with("| avg_comment_length | repos_touched"));
+        assert!(lines[2].ends_with("|               12.2 |             2"));
+        assert!(lines[3].ends_with("|                  - |             1"));
+        // Neither the average nor the distinct repos has a total.
+        assert!(lines[5].ends_with("|                  - |             -"));
+        assert!(!table.contains('\x1b'));
+    }
+}
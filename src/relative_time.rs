@@ -0,0 +1,77 @@
+use chrono::{DateTime, Duration, Utc};
+
+/// Parse the time either in the RFC3339 format like "2015-09-21T00:00:00Z", or relative
+/// to now like "now", "30m", "12h", "7d" and "2w", which are the minutes, hours, days
+/// and weeks before now.
+pub fn parse(time: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let time = time.trim();
+    if time == "now" {
+        return Ok(now);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(time) {
+        return Ok(time.to_utc());
+    }
+    let invalid = || {
+        format!(
+            "'{}' is neither an RFC3339 time nor a relative time like \"now\", \"12h\" or \"7d\"",
+            time
+        )
+    };
+    let unit_start = time.len() - time.chars().last().map_or(0, char::len_utf8);
+    let (amount, unit) = time.split_at(unit_start);
+    // Never overflows the duration, which goes up to about 292 million years.
+    let amount: u32 = amount.parse().map_err(|_| invalid())?;
+    let amount = i64::from(amount);
+    let ago = match unit {
+        "m" => Duration::minutes(amount),
+        "h" => Duration::hours(amount),
+        "d" => Duration::days(amount),
+        "w" => Duration::weeks(amount),
+        _ => return Err(invalid()),
+    };
+    now.checked_sub_signed(ago).ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let now: DateTime<Utc> = "2024-01-08T12:00:00Z".parse().unwrap();
+        let time = |time: &str| time.parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(parse("now", now), Ok(now));
+        assert_eq!(parse(" now ", now), Ok(now));
+        assert_eq!(
+            parse("2015-09-21T00:00:00Z", now),
+            Ok(time("2015-09-21T00:00:00Z"))
+        );
+        assert_eq!(
+            parse("2015-09-21T08:00:00+08:00", now),
+            Ok(time("2015-09-21T00:00:00Z"))
+        );
+        assert_eq!(parse("30m", now), Ok(time("2024-01-08T11:30:00Z")));
+        assert_eq!(parse("12h", now), Ok(time("2024-01-08T00:00:00Z")));
+        assert_eq!(parse("7d", now), Ok(time("2024-01-01T12:00:00Z")));
+        assert_eq!(parse("2w", now), Ok(time("2023-12-25T12:00:00Z")));
+        assert_eq!(parse("0d", now), Ok(now));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let now = Utc::now();
+        for time in [
+            "",
+            "d",
+            "7",
+            "7y",
+            "-7d",
+            "1.5d",
+            "7 d",
+            "7日",
+            "2015-09-21",
+        ] {
+            assert!(parse(time, now).is_err(), "{:?} should be invalid", time);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use log::debug;
+use octocrab::models;
+
+/// An issue or PR without any comment by the allowed users for a while.
+#[derive(Debug, Clone)]
+pub struct StaleItem {
+    pub number: u64,
+    pub title: String,
+    pub is_pull_request: bool,
+    // The last comment time by the allowed users as of the end time, `None` if never.
+    pub last_maintainer_activity: Option<DateTime<Utc>>,
+}
+
+/// Find the stale items among the issues (including PRs) open as of the end time, whose
+/// last comment by the allowed users is earlier than the stale threshold or never exists.
+/// The issues created after or closed before the end time are skipped.
+pub fn find_stale_items(
+    issues: &[models::issues::Issue],
+    comments: &[models::issues::Comment],
+    allowed_users: &HashSet<String>,
+    stale_threshold: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Vec<StaleItem> {
+    let mut last_activities: HashMap<u64, DateTime<Utc>> = HashMap::new();
+    for comment in comments {
+        if !allowed_users.contains(&comment.user.login) || comment.created_at > end_time {
+            continue;
+        }
+        let Some(number) = comment
+            .issue_url
+            .as_ref()
+            .and_then(|url| url.path_segments()?.next_back()?.parse::<u64>().ok())
+        else {
+            continue;
+        };
+        let last_activity = last_activities.entry(number).or_insert(comment.created_at);
+        if *last_activity < comment.created_at {
+            *last_activity = comment.created_at;
+        }
+    }
+
+    let mut stale_items: Vec<StaleItem> = issues
+        .iter()
+        .filter(|issue| {
+            issue.created_at <= end_time
+                && issue.closed_at.is_none_or(|closed_at| closed_at > end_time)
+        })
+        .filter_map(|issue| {
+            let last_activity = last_activities.get(&issue.number).copied();
+            debug!(
+                "check stale issue #{} {:?} last maintainer activity {:?}",
+                issue.number, issue.title, last_activity
+            );
+            match last_activity {
+                Some(last_activity) if last_activity >= stale_threshold => None,
+                _ => Some(StaleItem {
+                    number: issue.number,
+                    title: issue.title.clone(),
+                    is_pull_request: issue.pull_request.is_some(),
+                    last_maintainer_activity: last_activity,
+                }),
+            }
+        })
+        .collect();
+    stale_items.sort_by_key(|item| item.number);
+    stale_items
+}
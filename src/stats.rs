@@ -1,69 +1,1011 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use log::debug;
-use octocrab::models::{self, pulls::ReviewState};
+use octocrab::models::{self, pulls::ReviewState, reactions::ReactionContent, Event};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::UnboundedSender;
 
-use crate::config::Config;
+use crate::config::{BusinessHours, Config, LabelFilter, UserExclusion};
 
-#[derive(Debug)]
+/// The stats of the users. Only the collected data is (de)serialized, e.g. to checkpoint
+/// the stats of each repo, while the config and the traversal states are skipped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Stats {
-    // Issues of each user has created.
-    issues: HashMap<String, u64>,
-    // PRs of each user has created.
-    prs: HashMap<String, u64>,
-    // Issue comments of each user has given.
-    issue_comments: HashMap<String, u64>,
-    // PR review comments of each user has given.
-    pr_reviews: HashMap<String, u64>,
-    // LGTMs of each user has given.
-    lgtms: HashMap<String, u64>,
-    // labels of each user has added.
-    labels: HashMap<String, u64>,
+    // The metrics of each user.
+    user_metrics: HashMap<String, UserMetrics>,
+    // The creation time and the author of each PR created within the time range, keyed
+    // by the repo and the PR number to be merged across repos.
+    #[serde(with = "map_entries")]
+    opened_prs: HashMap<(String, u64), (DateTime<Utc>, String)>,
+    // The time of the first review or comment on each PR by an allowed user other than
+    // the author, keyed like `opened_prs`.
+    #[serde(with = "map_entries")]
+    first_responses: HashMap<(String, u64), DateTime<Utc>>,
+    // The PRs reviewed by any allowed user other than the author, keyed like `opened_prs`.
+    reviewed_prs: HashSet<(String, u64)>,
+
+    // The repo of the stats, which is the first one if merged from multiple repos.
+    repo: String,
+    // The endpoints failed to be fetched of each repo, whose metrics might be partial.
+    failed_endpoints: HashMap<String, Vec<String>>,
+    // The repos with any listing of the whole repo failed to be fetched.
+    hard_failed_repos: HashSet<String>,
 
     // The allow list of users.
+    #[serde(skip)]
     allowed_users: HashSet<String>,
+    // The canonical logins of the aliased users.
+    #[serde(skip)]
+    user_aliases: HashMap<String, String>,
+    // Whether to count all the users except the excluded ones.
+    #[serde(skip)]
+    track_all_users: bool,
+    // The users excluded even if they are allowed.
+    #[serde(skip)]
+    user_exclusion: UserExclusion,
     // The allow list of LGTM comments.
-    lgtm_comments: Vec<String>,
+    #[serde(skip)]
+    lgtm_comments: Vec<LgtmComment>,
+    // The regexes matching the LGTM comments.
+    #[serde(skip)]
+    lgtm_patterns: Vec<Regex>,
+    // Whether to credit the co-authors of the PRs from the `Co-authored-by:` trailers.
+    #[serde(skip)]
+    credit_coauthors: bool,
+    // The labels that are considered as a LGTM approval.
+    #[serde(skip)]
+    approval_labels: HashSet<String>,
+    // The (PR, user) pairs that have been counted as LGTMs by reviews or comments.
+    #[serde(skip)]
+    lgtm_prs: HashSet<(u64, String)>,
+    // The half-life of the time decay, `None` means no decayed scores.
+    #[serde(skip)]
+    decay_half_life: Option<Duration>,
+    // The (PR, user) pairs that have code suggestions made within the time range.
+    #[serde(skip)]
+    suggestion_prs: HashSet<(u64, String)>,
+    // Whether to count the review comments of a user in a thread once.
+    #[serde(skip)]
+    collapse_review_threads: bool,
+    // The (root comment, user) pairs of the review threads that have been counted.
+    #[serde(skip)]
+    review_threads: HashSet<(u64, String)>,
+    // The (repo, number) pairs of the issues and PRs that have been traversed, so the ones
+    // listed twice, e.g. by the overlapping pages, are only counted once.
+    #[serde(skip)]
+    traversed_issues: HashSet<(String, u64)>,
+    // The PRs created within the time range by the allowed users.
+    #[serde(skip)]
+    authored_prs: Vec<u64>,
+    // The PRs closed within the time range, which may be merged.
+    #[serde(skip)]
+    closed_prs: Vec<u64>,
+    // The lines changed thresholds between the PR size buckets.
+    #[serde(skip)]
+    pr_size_thresholds: [u64; 4],
+    // The min length in characters of the comments to be counted.
+    #[serde(skip)]
+    min_comment_length: usize,
+    // Whether to count the reopened issues and PRs.
+    #[serde(skip)]
+    count_reopens: bool,
+    // Whether to count the closed issues and PRs.
+    #[serde(skip)]
+    count_closes: bool,
+    // Whether to count the labels added to issues and PRs.
+    #[serde(skip)]
+    count_labels: bool,
+    // The numbers of all traversed PRs, to tell them apart from issues in the events.
+    #[serde(skip)]
+    pull_requests: HashSet<u64>,
+    // The PRs targeting the filtered base branch, `None` means no filtering.
+    #[serde(skip)]
+    base_branch_prs: Option<HashSet<u64>>,
+    // The labels filtering the issues and PRs.
+    #[serde(skip)]
+    label_filter: LabelFilter,
+    // The business hours to measure the time to first response in, `None` means the
+    // wall-clock time.
+    #[serde(skip)]
+    business_hours: Option<BusinessHours>,
+    // The issues and PRs matching the label filter.
+    #[serde(skip)]
+    label_matched_issues: HashSet<u64>,
+    // The draft PRs, whose creation is not counted.
+    #[serde(skip)]
+    draft_prs: HashSet<u64>,
     // The start time of the stats.
     start_time: DateTime<Utc>,
     // The end time of the stats.
     end_time: DateTime<Utc>,
+    // The weight of each metric in the score, 1.0 if not configured.
+    #[serde(skip)]
+    scoring_weights: HashMap<Metric, f64>,
+    // The sink to stream the counted activities as raw events to, if any.
+    #[serde(skip)]
+    raw_events: Option<UnboundedSender<RawEvent>>,
+}
+
+/// (De)serialize a map with the non-string keys, e.g. tuples, as a list of the entries,
+/// since the keys of a JSON object must be strings.
+mod map_entries {
+    use std::{collections::HashMap, hash::Hash};
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<K, V, S>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize,
+        V: Serialize,
+        S: Serializer,
+    {
+        serializer.collect_seq(map.iter())
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<HashMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Eq + Hash,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Vec::<(K, V)>::deserialize(deserializer).map(|entries| entries.into_iter().collect())
+    }
+}
+
+/// A counted activity of a user, e.g. an issue created or a review submitted, to be
+/// streamed as is for the aggregation elsewhere.
+#[derive(Debug, Clone, Serialize)]
+pub struct RawEvent {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub repo: String,
+    pub user: String,
+    pub timestamp: DateTime<Utc>,
+    pub pr_number: Option<u64>,
+}
+
+/// The metrics counted per user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    Issues,
+    Prs,
+    IssueComments,
+    PrReviews,
+    Lgtms,
+    Labels,
+    Reopens,
+    SuggestionsMade,
+    SuggestionsAccepted,
+    ChangesRequested,
+    CommentedReviews,
+    ReactionsGiven,
+    PrsMerged,
+    ReviewComments,
+    IssuesClosed,
+    PrsClosed,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 16] = [
+        Metric::Issues,
+        Metric::Prs,
+        Metric::IssueComments,
+        Metric::PrReviews,
+        Metric::Lgtms,
+        Metric::Labels,
+        Metric::Reopens,
+        Metric::SuggestionsMade,
+        Metric::SuggestionsAccepted,
+        Metric::ChangesRequested,
+        Metric::CommentedReviews,
+        Metric::ReactionsGiven,
+        Metric::PrsMerged,
+        Metric::ReviewComments,
+        Metric::IssuesClosed,
+        Metric::PrsClosed,
+    ];
+
+    /// Get the name of the metric, which is used in the output and the arguments.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::Issues => "issues",
+            Metric::Prs => "prs",
+            Metric::IssueComments => "issue_comments",
+            Metric::PrReviews => "pr_reviews",
+            Metric::Lgtms => "lgtms",
+            Metric::Labels => "labels",
+            Metric::Reopens => "reopens",
+            Metric::SuggestionsMade => "suggestions_made",
+            Metric::SuggestionsAccepted => "suggestions_accepted",
+            Metric::ChangesRequested => "changes_requested",
+            Metric::CommentedReviews => "commented_reviews",
+            Metric::ReactionsGiven => "reactions_given",
+            Metric::PrsMerged => "prs_merged",
+            Metric::ReviewComments => "review_comments",
+            Metric::IssuesClosed => "issues_closed",
+            Metric::PrsClosed => "prs_closed",
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Metric {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Metric::ALL
+            .into_iter()
+            .find(|metric| metric.name() == s)
+            .ok_or_else(|| format!("unknown metric '{}'", s))
+    }
+}
+
+/// The metrics of a user, which are merged field by field across the repos.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UserMetrics {
+    /// Issues created.
+    pub issues: u64,
+    /// PRs created.
+    pub prs: u64,
+    /// Issue comments given.
+    pub issue_comments: u64,
+    /// PR reviews submitted, i.e. the approvals, the change requests and the comment-only
+    /// reviews, including the ones dismissed later.
+    pub pr_reviews: u64,
+    /// LGTMs given.
+    pub lgtms: u64,
+    /// Labels added.
+    pub labels: u64,
+    /// Issues and PRs reopened.
+    pub reopens: u64,
+    /// Code suggestions made.
+    pub suggestions_made: u64,
+    /// Code suggestions made and accepted.
+    pub suggestions_accepted: u64,
+    /// The reviews requesting changes submitted.
+    pub changes_requested: u64,
+    /// The reviews only commenting submitted.
+    pub commented_reviews: u64,
+    /// Reactions given to issues, PRs and comments.
+    pub reactions_given: u64,
+    /// PRs got merged.
+    pub prs_merged: u64,
+    /// Inline PR review comments other than LGTMs given.
+    pub review_comments: u64,
+    /// Issues closed.
+    pub issues_closed: u64,
+    /// PRs closed, including the merged ones.
+    pub prs_closed: u64,
+    /// PRs created in each size bucket.
+    pub pr_sizes: [u64; 5],
+    /// Total comment length in characters and comment count.
+    pub comment_length: (u64, u64),
+    /// Time-decayed activity score, `None` unless the decay half-life is set.
+    pub decayed_score: Option<f64>,
+    /// The LGTMs weighted by the sizes of the approved PRs, `None` unless the sizes of
+    /// the approved PRs are traversed.
+    pub weighted_lgtms: Option<f64>,
+    /// The time of the first activity within the time range.
+    pub first_seen: Option<DateTime<Utc>>,
+    /// The time of the last activity within the time range.
+    pub last_seen: Option<DateTime<Utc>>,
+    /// Distinct repos active in.
+    pub repos_touched: HashSet<String>,
+}
+
+impl UserMetrics {
+    /// Get the count of the given metric.
+    pub fn metric(&self, metric: Metric) -> u64 {
+        match metric {
+            Metric::Issues => self.issues,
+            Metric::Prs => self.prs,
+            Metric::IssueComments => self.issue_comments,
+            Metric::PrReviews => self.pr_reviews,
+            Metric::Lgtms => self.lgtms,
+            Metric::Labels => self.labels,
+            Metric::Reopens => self.reopens,
+            Metric::SuggestionsMade => self.suggestions_made,
+            Metric::SuggestionsAccepted => self.suggestions_accepted,
+            Metric::ChangesRequested => self.changes_requested,
+            Metric::CommentedReviews => self.commented_reviews,
+            Metric::ReactionsGiven => self.reactions_given,
+            Metric::PrsMerged => self.prs_merged,
+            Metric::ReviewComments => self.review_comments,
+            Metric::IssuesClosed => self.issues_closed,
+            Metric::PrsClosed => self.prs_closed,
+        }
+    }
+
+    /// Consume and merge the other metrics of the same user into self.
+    pub fn merge(&mut self, other: Self) {
+        // Destructure all the fields, so a newly added field can't be silently dropped.
+        let Self {
+            issues,
+            prs,
+            issue_comments,
+            pr_reviews,
+            lgtms,
+            labels,
+            reopens,
+            suggestions_made,
+            suggestions_accepted,
+            changes_requested,
+            commented_reviews,
+            reactions_given,
+            prs_merged,
+            review_comments,
+            issues_closed,
+            prs_closed,
+            pr_sizes,
+            comment_length,
+            decayed_score,
+            weighted_lgtms,
+            first_seen,
+            last_seen,
+            repos_touched,
+        } = other;
+        self.issues += issues;
+        self.prs += prs;
+        self.issue_comments += issue_comments;
+        self.pr_reviews += pr_reviews;
+        self.lgtms += lgtms;
+        self.labels += labels;
+        self.reopens += reopens;
+        self.suggestions_made += suggestions_made;
+        self.suggestions_accepted += suggestions_accepted;
+        self.changes_requested += changes_requested;
+        self.commented_reviews += commented_reviews;
+        self.reactions_given += reactions_given;
+        self.prs_merged += prs_merged;
+        self.review_comments += review_comments;
+        self.issues_closed += issues_closed;
+        self.prs_closed += prs_closed;
+        for (count, delta) in self.pr_sizes.iter_mut().zip(pr_sizes) {
+            *count += delta;
+        }
+        self.comment_length.0 += comment_length.0;
+        self.comment_length.1 += comment_length.1;
+        merge_option(&mut self.decayed_score, decayed_score, |a, b| a + b);
+        merge_option(&mut self.weighted_lgtms, weighted_lgtms, |a, b| a + b);
+        merge_option(&mut self.first_seen, first_seen, Ord::min);
+        merge_option(&mut self.last_seen, last_seen, Ord::max);
+        self.repos_touched.extend(repos_touched);
+    }
+}
+
+/// Merge the optional values, taking the present one if only one of them is present.
+fn merge_option<T>(base: &mut Option<T>, other: Option<T>, merge: impl FnOnce(T, T) -> T) {
+    *base = match (base.take(), other) {
+        (Some(base), Some(other)) => Some(merge(base, other)),
+        (base, other) => base.or(other),
+    };
+}
+
+/// The distribution of a metric across the active users, i.e. the users with a
+/// non-zero count of the metric.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct DistributionSummary {
+    pub active_users: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub std_dev: f64,
+}
+
+/// The time the PRs waited before the first response, i.e. the first review or comment
+/// by an allowed user other than the author, across the PRs responded to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResponseTimeSummary {
+    pub responded_prs: usize,
+    pub min: Duration,
+    pub median: Duration,
+    pub max: Duration,
+}
+
+/// How many of the PRs created within the time range of a repo got at least one review.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReviewCoverage {
+    pub reviewed_prs: usize,
+    pub total_prs: usize,
+}
+
+impl ReviewCoverage {
+    /// Get the fraction of the PRs reviewed, zero if no PR is created.
+    pub fn ratio(&self) -> f64 {
+        if self.total_prs == 0 {
+            0.0
+        } else {
+            self.reviewed_prs as f64 / self.total_prs as f64
+        }
+    }
+}
+
+/// The raw counts of each metric, which can be persisted and compared across runs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Counts {
+    #[serde(default)]
+    pub issues: HashMap<String, u64>,
+    #[serde(default)]
+    pub prs: HashMap<String, u64>,
+    #[serde(default)]
+    pub issue_comments: HashMap<String, u64>,
+    #[serde(default)]
+    pub pr_reviews: HashMap<String, u64>,
+    #[serde(default)]
+    pub lgtms: HashMap<String, u64>,
+    #[serde(default)]
+    pub labels: HashMap<String, u64>,
+    #[serde(default)]
+    pub reopens: HashMap<String, u64>,
+    #[serde(default)]
+    pub suggestions_made: HashMap<String, u64>,
+    #[serde(default)]
+    pub suggestions_accepted: HashMap<String, u64>,
+    #[serde(default)]
+    pub changes_requested: HashMap<String, u64>,
+    #[serde(default)]
+    pub commented_reviews: HashMap<String, u64>,
+    #[serde(default)]
+    pub reactions_given: HashMap<String, u64>,
+    #[serde(default)]
+    pub prs_merged: HashMap<String, u64>,
+    #[serde(default)]
+    pub review_comments: HashMap<String, u64>,
+    #[serde(default)]
+    pub issues_closed: HashMap<String, u64>,
+    #[serde(default)]
+    pub prs_closed: HashMap<String, u64>,
+    /// The PRs created in each size bucket from XS to XL, empty unless the sizes of the
+    /// PRs are traversed.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub pr_sizes: HashMap<String, [u64; 5]>,
+    /// The LGTMs weighted by the sizes of the approved PRs, empty unless the LGTMs are
+    /// weighted.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub weighted_lgtms: HashMap<String, f64>,
+    /// The average comment length in characters of each user with any comment, which
+    /// can't be rolled up by the teams.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub avg_comment_length: HashMap<String, f64>,
+    /// The distinct repos each user has been active in, which can't be rolled up by the
+    /// teams either as the repos of the members overlap.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub repos_touched: HashMap<String, u64>,
+}
+
+/// The changes of each metric against a baseline.
+#[derive(Debug, Clone, Default)]
+pub struct Deltas {
+    pub issues: HashMap<String, i64>,
+    pub prs: HashMap<String, i64>,
+    pub issue_comments: HashMap<String, i64>,
+    pub pr_reviews: HashMap<String, i64>,
+    pub lgtms: HashMap<String, i64>,
+    pub labels: HashMap<String, i64>,
+    pub reopens: HashMap<String, i64>,
+    pub suggestions_made: HashMap<String, i64>,
+    pub suggestions_accepted: HashMap<String, i64>,
+    pub changes_requested: HashMap<String, i64>,
+    pub commented_reviews: HashMap<String, i64>,
+    pub reactions_given: HashMap<String, i64>,
+    pub prs_merged: HashMap<String, i64>,
+    pub review_comments: HashMap<String, i64>,
+    pub issues_closed: HashMap<String, i64>,
+    pub prs_closed: HashMap<String, i64>,
+}
+
+/// The change of a metric of a user from the previous period to the current one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Change {
+    pub previous: u64,
+    pub current: u64,
+}
+
+impl Change {
+    /// Get the current count minus the previous one.
+    pub fn delta(&self) -> i64 {
+        self.current as i64 - self.previous as i64
+    }
+
+    /// Get the percentage change against the previous count, or `None` if the previous
+    /// count is zero.
+    pub fn percentage(&self) -> Option<f64> {
+        (self.previous > 0).then(|| self.delta() as f64 / self.previous as f64 * 100.0)
+    }
+}
+
+/// The team of the users without any team mapping.
+pub const UNASSIGNED_TEAM: &str = "unassigned";
+
+impl Counts {
+    /// Get the counts of the given metric.
+    pub fn metric(&self, metric: Metric) -> &HashMap<String, u64> {
+        match metric {
+            Metric::Issues => &self.issues,
+            Metric::Prs => &self.prs,
+            Metric::IssueComments => &self.issue_comments,
+            Metric::PrReviews => &self.pr_reviews,
+            Metric::Lgtms => &self.lgtms,
+            Metric::Labels => &self.labels,
+            Metric::Reopens => &self.reopens,
+            Metric::SuggestionsMade => &self.suggestions_made,
+            Metric::SuggestionsAccepted => &self.suggestions_accepted,
+            Metric::ChangesRequested => &self.changes_requested,
+            Metric::CommentedReviews => &self.commented_reviews,
+            Metric::ReactionsGiven => &self.reactions_given,
+            Metric::PrsMerged => &self.prs_merged,
+            Metric::ReviewComments => &self.review_comments,
+            Metric::IssuesClosed => &self.issues_closed,
+            Metric::PrsClosed => &self.prs_closed,
+        }
+    }
+
+    /// Roll up the counts of each user by the team they belong to, users without any
+    /// team go into the "unassigned" team.
+    pub fn group_by_team(&self, user_teams: &HashMap<String, String>) -> Counts {
+        self.group_by(|user| {
+            user_teams
+                .get(user)
+                .map_or(UNASSIGNED_TEAM, |team| team.as_str())
+                .to_string()
+        })
+    }
+
+    /// Replace the login of each user by the display name, e.g. the real name for the
+    /// reports shown to non-engineers. The users without any display name keep their
+    /// logins.
+    pub fn with_display_names(&self, display_names: &HashMap<String, String>) -> Counts {
+        let display_name = |user: &str| {
+            display_names
+                .get(user)
+                .map_or(user, |display_name| display_name.as_str())
+                .to_string()
+        };
+        Counts {
+            avg_comment_length: self
+                .avg_comment_length
+                .iter()
+                .map(|(user, length)| (display_name(user), *length))
+                .collect(),
+            repos_touched: self
+                .repos_touched
+                .iter()
+                .map(|(user, repos)| (display_name(user), *repos))
+                .collect(),
+            ..self.group_by(display_name)
+        }
+    }
+
+    /// Roll up the counts of each user by the key of the user. The per-user values which
+    /// can't be summed up, e.g. the averages, are left out.
+    fn group_by(&self, key: impl Fn(&str) -> String) -> Counts {
+        let group = |counts: &HashMap<String, u64>| {
+            let mut grouped = HashMap::new();
+            for (user, count) in counts {
+                *grouped.entry(key(user)).or_insert(0) += count;
+            }
+            grouped
+        };
+        let mut pr_sizes: HashMap<String, [u64; 5]> = HashMap::new();
+        for (user, sizes) in &self.pr_sizes {
+            let grouped = pr_sizes.entry(key(user)).or_default();
+            for (count, delta) in grouped.iter_mut().zip(sizes) {
+                *count += delta;
+            }
+        }
+        let mut weighted_lgtms: HashMap<String, f64> = HashMap::new();
+        for (user, weighted) in &self.weighted_lgtms {
+            *weighted_lgtms.entry(key(user)).or_insert(0.0) += weighted;
+        }
+        Counts {
+            issues: group(&self.issues),
+            prs: group(&self.prs),
+            issue_comments: group(&self.issue_comments),
+            pr_reviews: group(&self.pr_reviews),
+            lgtms: group(&self.lgtms),
+            labels: group(&self.labels),
+            reopens: group(&self.reopens),
+            suggestions_made: group(&self.suggestions_made),
+            suggestions_accepted: group(&self.suggestions_accepted),
+            changes_requested: group(&self.changes_requested),
+            commented_reviews: group(&self.commented_reviews),
+            reactions_given: group(&self.reactions_given),
+            prs_merged: group(&self.prs_merged),
+            review_comments: group(&self.review_comments),
+            issues_closed: group(&self.issues_closed),
+            prs_closed: group(&self.prs_closed),
+            pr_sizes,
+            weighted_lgtms,
+            avg_comment_length: HashMap::new(),
+            repos_touched: HashMap::new(),
+        }
+    }
+
+    /// Calculate the deltas of the counts against the baseline. Users only present in
+    /// either side are treated as zero on the other side.
+    pub fn deltas(&self, baseline: &Counts) -> Deltas {
+        Deltas {
+            issues: Self::delta_map(&self.issues, &baseline.issues),
+            prs: Self::delta_map(&self.prs, &baseline.prs),
+            issue_comments: Self::delta_map(&self.issue_comments, &baseline.issue_comments),
+            pr_reviews: Self::delta_map(&self.pr_reviews, &baseline.pr_reviews),
+            lgtms: Self::delta_map(&self.lgtms, &baseline.lgtms),
+            labels: Self::delta_map(&self.labels, &baseline.labels),
+            reopens: Self::delta_map(&self.reopens, &baseline.reopens),
+            suggestions_made: Self::delta_map(&self.suggestions_made, &baseline.suggestions_made),
+            suggestions_accepted: Self::delta_map(
+                &self.suggestions_accepted,
+                &baseline.suggestions_accepted,
+            ),
+            changes_requested: Self::delta_map(
+                &self.changes_requested,
+                &baseline.changes_requested,
+            ),
+            commented_reviews: Self::delta_map(
+                &self.commented_reviews,
+                &baseline.commented_reviews,
+            ),
+            reactions_given: Self::delta_map(&self.reactions_given, &baseline.reactions_given),
+            prs_merged: Self::delta_map(&self.prs_merged, &baseline.prs_merged),
+            review_comments: Self::delta_map(&self.review_comments, &baseline.review_comments),
+            issues_closed: Self::delta_map(&self.issues_closed, &baseline.issues_closed),
+            prs_closed: Self::delta_map(&self.prs_closed, &baseline.prs_closed),
+        }
+    }
+
+    /// Compare the counts against the ones of the previous period, per user and metric.
+    /// Users only present in either period are treated as zero in the other one, and the
+    /// metrics zero in both periods are skipped.
+    pub fn compare(&self, previous: &Counts) -> BTreeMap<String, Vec<(Metric, Change)>> {
+        let mut changes: BTreeMap<String, Vec<(Metric, Change)>> = BTreeMap::new();
+        for metric in Metric::ALL {
+            let (current, previous) = (self.metric(metric), previous.metric(metric));
+            let users: BTreeSet<&String> = current.keys().chain(previous.keys()).collect();
+            for user in users {
+                let change = Change {
+                    previous: previous.get(user).copied().unwrap_or(0),
+                    current: current.get(user).copied().unwrap_or(0),
+                };
+                if change.previous > 0 || change.current > 0 {
+                    changes
+                        .entry(user.clone())
+                        .or_default()
+                        .push((metric, change));
+                }
+            }
+        }
+        changes
+    }
+
+    fn delta_map(
+        current: &HashMap<String, u64>,
+        baseline: &HashMap<String, u64>,
+    ) -> HashMap<String, i64> {
+        current
+            .keys()
+            .chain(baseline.keys())
+            .map(|user| {
+                let current = current.get(user).copied().unwrap_or(0) as i64;
+                let baseline = baseline.get(user).copied().unwrap_or(0) as i64;
+                (user.clone(), current - baseline)
+            })
+            .collect()
+    }
 }
 
 impl Stats {
-    pub fn new(config: &Config, start_time: DateTime<Utc>, end_time: DateTime<Utc>) -> Self {
-        let allowed_users: HashSet<String> = config.review_users().into_iter().collect();
+    pub fn new(
+        config: &Config,
+        repo: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Self {
+        let user_exclusion = config.review_user_exclusion();
+        let user_aliases = config.review_user_aliases();
+        let allowed_users: HashSet<String> = config
+            .review_repo_users(repo)
+            .into_iter()
+            .filter(|user| !user_exclusion.excludes(user))
+            .map(|user| user_aliases.get(&user).cloned().unwrap_or(user))
+            .collect();
         Self {
-            issues: HashMap::with_capacity(allowed_users.len()),
-            prs: HashMap::with_capacity(allowed_users.len()),
-            issue_comments: HashMap::with_capacity(allowed_users.len()),
-            pr_reviews: HashMap::with_capacity(allowed_users.len()),
-            lgtms: HashMap::with_capacity(allowed_users.len()),
-            labels: HashMap::with_capacity(allowed_users.len()),
+            user_metrics: HashMap::with_capacity(allowed_users.len()),
+            opened_prs: HashMap::new(),
+            first_responses: HashMap::new(),
+            reviewed_prs: HashSet::new(),
+            repo: repo.to_string(),
+            failed_endpoints: HashMap::new(),
+            hard_failed_repos: HashSet::new(),
             allowed_users,
-            lgtm_comments: config.review_lgtm_comments(),
+            user_aliases,
+            track_all_users: config.review_track_all_users(),
+            user_exclusion,
+            lgtm_comments: config
+                .review_lgtm_comments()
+                .iter()
+                .filter_map(|lgtm| LgtmComment::new(lgtm))
+                .collect(),
+            lgtm_patterns: config.review_lgtm_patterns(),
+            credit_coauthors: config.review_credit_coauthors(),
+            approval_labels: config.review_approval_labels().into_iter().collect(),
+            lgtm_prs: HashSet::new(),
+            decay_half_life: None,
+            suggestion_prs: HashSet::new(),
+            collapse_review_threads: config.review_collapse_review_threads(),
+            review_threads: HashSet::new(),
+            traversed_issues: HashSet::new(),
+            authored_prs: Vec::new(),
+            closed_prs: Vec::new(),
+            pr_size_thresholds: config.review_pr_size_thresholds(),
+            min_comment_length: config.review_min_comment_length(),
+            count_reopens: config.review_count_reopens(),
+            count_closes: config.review_count_closes(),
+            count_labels: config.review_count_labels(),
+            pull_requests: HashSet::new(),
+            base_branch_prs: None,
+            label_filter: config.review_label_filter(),
+            business_hours: config.review_business_hours(),
+            label_matched_issues: HashSet::new(),
+            draft_prs: HashSet::new(),
             start_time,
             end_time,
+            scoring_weights: config.scoring_weights(),
+            raw_events: None,
+        }
+    }
+
+    /// Restrict the PRs (and their reviews and comments) to the given ones, which are
+    /// the PRs targeting the filtered base branch. Issues are not affected.
+    pub fn set_base_branch_pull_requests(&mut self, pull_requests: HashSet<u64>) {
+        self.base_branch_prs = Some(pull_requests);
+    }
+
+    /// Set the draft PRs to exclude from the PRs created. A PR is told by whether it's a
+    /// draft now, so a PR created as a draft and marked as ready later is still counted.
+    pub fn set_draft_pull_requests(&mut self, pull_requests: HashSet<u64>) {
+        self.draft_prs = pull_requests;
+    }
+
+    /// Record the endpoints failed to be fetched for the repo of the stats, the metrics
+    /// traversed from the other endpoints are still valid unless it's hard-failed, i.e.
+    /// any listing of the whole repo failed.
+    pub fn set_failed_endpoints(&mut self, endpoints: Vec<String>, hard_failed: bool) {
+        if !endpoints.is_empty() {
+            self.failed_endpoints.insert(self.repo.clone(), endpoints);
         }
+        if hard_failed {
+            self.hard_failed_repos.insert(self.repo.clone());
+        }
+    }
+
+    /// Get the endpoints failed to be fetched of each repo.
+    pub fn failed_endpoints(&self) -> &HashMap<String, Vec<String>> {
+        &self.failed_endpoints
+    }
+
+    /// Get the repos hard-failed to be fetched.
+    pub fn hard_failed_repos(&self) -> &HashSet<String> {
+        &self.hard_failed_repos
+    }
+
+    /// Stream the issues, PRs, comments and reviews counted from now on as raw events to
+    /// the sink, or stop streaming with `None`, which closes the sink once all its
+    /// senders are gone.
+    pub fn set_raw_events(&mut self, raw_events: Option<UnboundedSender<RawEvent>>) {
+        self.raw_events = raw_events;
+    }
+
+    /// Enable the time-decayed score of each user with the given half-life. Each counted
+    /// activity contributes `0.5 ^ (age / half_life)` to the score, where `age` is the
+    /// elapsed time from the activity to the end time, so an activity at the end time
+    /// counts as 1 and one a half-life earlier counts as 0.5.
+    pub fn set_decay_half_life(&mut self, half_life: Duration) {
+        self.decay_half_life = Some(half_life);
+    }
+
+    /// Whether the timeline events are needed, i.e. the approval labels, the reopens, the
+    /// closes or the labels are configured to be counted.
+    pub fn needs_timeline_events(&self) -> bool {
+        !self.approval_labels.is_empty()
+            || self.count_reopens
+            || self.count_closes
+            || self.count_labels
     }
 
     /// Traverse the issues (including PRs) to collect the PRs and issues created by each user.
+    /// An issue listed more than once, e.g. by the retried or overlapping pages, is only
+    /// traversed once. The issues not matching the label filter are skipped along with
+    /// their comments, reviews and events traversed later, so this should be called first.
     pub fn traverse_issues(&mut self, issues: Vec<models::issues::Issue>) {
         issues.iter().for_each(|issue| {
+            if !self
+                .traversed_issues
+                .insert((self.repo.clone(), issue.number))
+            {
+                debug!("skip duplicated issue: #{}", issue.number);
+                return;
+            }
+            if !self.label_filter.is_empty() {
+                let labels = issue.labels.iter().map(|label| label.name.as_str());
+                if !self.label_filter.matches(labels) {
+                    debug!(
+                        "skip issue not matching the label filter: #{}",
+                        issue.number
+                    );
+                    if issue.pull_request.is_some() {
+                        self.pull_requests.insert(issue.number);
+                    }
+                    return;
+                }
+                self.label_matched_issues.insert(issue.number);
+            }
+            if issue.pull_request.is_some() {
+                self.pull_requests.insert(issue.number);
+                if self.within_time_range(issue.created_at)
+                    && self.is_pull_request_allowed(Some(issue.number))
+                {
+                    self.opened_prs.insert(
+                        (self.repo.clone(), issue.number),
+                        (issue.created_at, self.canonical_user(&issue.user.login)),
+                    );
+                }
+                // A PR merged within the time range may be created before it.
+                if issue
+                    .closed_at
+                    .is_some_and(|closed_at| self.within_time_range(closed_at))
+                    && self.is_pull_request_allowed(Some(issue.number))
+                    && self.is_user_allowed(&issue.user.login)
+                {
+                    self.closed_prs.push(issue.number);
+                }
+                if self.credit_coauthors
+                    && self.within_time_range(issue.created_at)
+                    && self.is_pull_request_allowed(Some(issue.number))
+                    && !self.draft_prs.contains(&issue.number)
+                {
+                    for user in self.co_authors(issue) {
+                        debug!(
+                            "traverse co-authored pull request: {} by {}",
+                            issue_into_string(issue),
+                            user
+                        );
+                        self.add_pr(&user);
+                        self.add_activity(&user, issue.created_at);
+                    }
+                }
+            }
             if self.filter_issues(issue) {
                 return;
             }
             match issue.pull_request {
                 Some(_) => {
                     debug!("traverse pull request: {}", issue_into_string(issue));
-                    self.add_pr(&issue.user.login)
+                    self.add_pr(&issue.user.login);
+                    self.authored_prs.push(issue.number);
+                    self.emit_raw_event(
+                        "pull_request",
+                        &issue.user.login,
+                        issue.created_at,
+                        Some(issue.number),
+                    )
                 }
                 None => {
                     debug!("traverse issue: {}", issue_into_string(issue));
-                    self.add_issue(&issue.user.login)
+                    self.add_issue(&issue.user.login);
+                    self.emit_raw_event("issue", &issue.user.login, issue.created_at, None)
                 }
             }
+            self.add_activity(&issue.user.login, issue.created_at)
+        })
+    }
+
+    /// Get the PRs created within the time range by the allowed users.
+    pub fn authored_pull_requests(&self) -> Vec<u64> {
+        self.authored_prs.clone()
+    }
+
+    /// Traverse the details of the authored PRs to collect the PRs of each user has created
+    /// in each size bucket by the lines changed, i.e. the additions plus the deletions.
+    pub fn traverse_pull_request_sizes(&mut self, pull_requests: Vec<models::pulls::PullRequest>) {
+        pull_requests.iter().for_each(|pull_request| {
+            let user = pull_request.user.as_ref().map_or("", |user| &user.login);
+            // The author may be deleted since the PR is traversed.
+            if !self.is_user_allowed(user) {
+                return;
+            }
+            let lines_changed =
+                pull_request.additions.unwrap_or(0) + pull_request.deletions.unwrap_or(0);
+            let bucket = self
+                .pr_size_thresholds
+                .iter()
+                .take_while(|threshold| lines_changed >= **threshold)
+                .count();
+            debug!(
+                "traverse pull request size: #{} {} lines changed [{}] by {}",
+                pull_request.number, lines_changed, PR_SIZE_BUCKETS[bucket], user
+            );
+            self.user_metrics_mut(user).pr_sizes[bucket] += 1;
+        })
+    }
+
+    /// Get the PRs with any LGTM counted, whose details tell the sizes to weight the LGTMs.
+    pub fn lgtm_pull_requests(&self) -> Vec<u64> {
+        let pull_requests: HashSet<u64> = self.lgtm_prs.iter().map(|(number, _)| *number).collect();
+        pull_requests.into_iter().collect()
+    }
+
+    /// Traverse the details of the PRs with any LGTM to weight the LGTMs of each user by
+    /// the PR sizes. A LGTM weighs `ln(1 + lines_changed)`, but at least 1, so approving
+    /// a typo fix counts as 1 while approving a 2000-line PR counts as about 7.6. The
+    /// LGTMs without a known PR, which are not deduped per PR either, weigh 1.
+    pub fn traverse_lgtm_pull_request_sizes(
+        &mut self,
+        pull_requests: Vec<models::pulls::PullRequest>,
+    ) {
+        let weights: HashMap<u64, f64> = pull_requests
+            .iter()
+            .map(|pull_request| {
+                let lines_changed =
+                    pull_request.additions.unwrap_or(0) + pull_request.deletions.unwrap_or(0);
+                (pull_request.number, lgtm_weight(lines_changed))
+            })
+            .collect();
+        for metrics in self.user_metrics.values_mut() {
+            metrics.weighted_lgtms = (metrics.lgtms > 0).then_some(metrics.lgtms as f64);
+        }
+        for (number, user) in &self.lgtm_prs {
+            let weighted = self
+                .user_metrics
+                .get_mut(user)
+                .and_then(|metrics| metrics.weighted_lgtms.as_mut());
+            if let (Some(weight), Some(weighted)) = (weights.get(number), weighted) {
+                debug!(
+                    "traverse lgtm pull request size: #{} weighs {:.2} by {}",
+                    number, weight, user
+                );
+                *weighted += weight - 1.0;
+            }
+        }
+    }
+
+    /// Get the PRs closed within the time range, whose details tell whether they are merged.
+    pub fn closed_pull_requests(&self) -> Vec<u64> {
+        self.closed_prs.clone()
+    }
+
+    /// Traverse the details of the closed PRs to collect the PRs of each user has got
+    /// merged within the time range. The PRs closed without merging are not counted.
+    pub fn traverse_merged_pull_requests(
+        &mut self,
+        pull_requests: Vec<models::pulls::PullRequest>,
+    ) {
+        pull_requests.iter().for_each(|pull_request| {
+            let Some(merged_at) = pull_request.merged_at else {
+                return;
+            };
+            let user = pull_request.user.as_ref().map_or("", |user| &user.login);
+            if !self.is_user_allowed(user)
+                || !self.within_time_range(merged_at)
+                || !self.is_pull_request_allowed(Some(pull_request.number))
+            {
+                return;
+            }
+            debug!(
+                "traverse merged pull request: #{} merged at {} by {}",
+                pull_request.number, merged_at, user
+            );
+            self.add_pr_merged(user);
         })
     }
 
@@ -77,11 +1019,24 @@ impl Stats {
                 "traverse issue comment: {}",
                 issue_comment_into_string(comment)
             );
-            self.add_issue_comment(&comment.user.login)
+            self.add_issue_comment(&comment.user.login);
+            self.emit_raw_event(
+                "issue_comment",
+                &comment.user.login,
+                comment.created_at,
+                None,
+            );
+            self.add_comment_length(
+                &comment.user.login,
+                comment.body.as_deref().unwrap_or_default(),
+            );
+            self.add_activity(&comment.user.login, comment.created_at)
         })
     }
 
-    /// Traverse the PR comments to collect the PR reviews given by each user.
+    /// Traverse the PR review comments, i.e. the inline comments on the code, to collect
+    /// the LGTMs and the other review comments given by each user. A LGTM comment is
+    /// counted once per PR and user, no matter how many times it's repeated.
     pub fn traverse_pull_request_comments(
         &mut self,
         pull_request_comments: Vec<models::pulls::Comment>,
@@ -95,16 +1050,104 @@ impl Stats {
                 "traverse pull request comment: #{} {:?} by {}",
                 comment.id, comment.body, user
             );
+            self.record_response(
+                pull_request_number(&comment.html_url),
+                user,
+                comment.created_at,
+            );
+            self.emit_raw_event(
+                "review_comment",
+                user,
+                comment.created_at,
+                pull_request_number(&comment.html_url),
+            );
             if self.is_comment_lgtm(comment.body.trim()) {
-                self.add_lgtm(user)
-            } else {
-                self.add_pr_review(user)
+                if self.record_lgtm_pr(pull_request_number(&comment.html_url), user) {
+                    self.add_lgtm(user);
+                }
+            } else if self.record_review_thread(comment, user) {
+                self.add_review_comment(user)
+            }
+            self.add_comment_length(user, &comment.body);
+            self.add_activity(user, comment.created_at);
+            if comment.body.contains(SUGGESTION_BLOCK) {
+                self.add_suggestion_made(user);
+                if let Some(number) = pull_request_number(&comment.html_url) {
+                    self.suggestion_prs.insert((number, user.to_string()));
+                }
+            }
+        })
+    }
+
+    /// Record the user has commented in the review thread of the comment, and return
+    /// whether the comment should be counted, i.e. it's the first one of the user in the
+    /// thread or the threads are not collapsed. A reply always refers to the root comment
+    /// of its thread by `in_reply_to_id`.
+    fn record_review_thread(&mut self, comment: &models::pulls::Comment, user: &str) -> bool {
+        if !self.collapse_review_threads {
+            return true;
+        }
+        let thread = comment.in_reply_to_id.unwrap_or(comment.id).into_inner();
+        self.review_threads.insert((thread, user.to_string()))
+    }
+
+    /// Get the PRs with code suggestions made within the time range, whose commits are
+    /// needed to tell whether the suggestions are accepted.
+    pub fn suggestion_pull_requests(&self) -> Vec<u64> {
+        let pull_requests: HashSet<u64> = self
+            .suggestion_prs
+            .iter()
+            .map(|(number, _)| *number)
+            .collect();
+        pull_requests.into_iter().collect()
+    }
+
+    /// Traverse the commits of the PRs with code suggestions to collect the accepted ones
+    /// of each user. This is a heuristic: GitHub commits an applied suggestion with a
+    /// message like "Apply suggestions from code review" and the suggester as a
+    /// co-author, so each such commit co-authored by a user who has made suggestions on
+    /// the same PR counts as one accepted suggestion, even if it applies a batch of them.
+    pub fn traverse_suggestion_commits(&mut self, commits: Vec<(u64, models::repos::RepoCommit)>) {
+        commits.iter().for_each(|(number, commit)| {
+            let message = &commit.commit.message;
+            if !message.starts_with("Apply suggestion") {
+                return;
+            }
+            let co_authors: Vec<&str> = message
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix("Co-authored-by:"))
+                .collect();
+            let suggesters: Vec<String> = self
+                .suggestion_prs
+                .iter()
+                .filter(|(pr, user)| {
+                    pr == number
+                        && co_authors
+                            .iter()
+                            .any(|co_author| is_co_author(co_author, user))
+                })
+                .map(|(_, user)| user.clone())
+                .collect();
+            for user in suggesters {
+                debug!(
+                    "traverse accepted suggestion commit: #{} {} by {}",
+                    number, commit.sha, user
+                );
+                self.add_suggestion_accepted(&user);
             }
         })
     }
 
-    /// Traverse the PR reviews to collect the PR approvals given by each user.
-    pub fn traverse_pull_request_reviews(&mut self, reviews: Vec<models::pulls::Review>) {
+    /// Traverse the PR reviews to collect the reviews submitted and the PR approvals given
+    /// by each user. Only the approvals still standing are counted, i.e. the approvals of
+    /// a user on a PR followed by a dismissed review of the same user on the same PR are
+    /// not counted. The standing approvals of a user on a PR count as one LGTM, and none
+    /// if the user has already LGTMed the PR by a comment, so this should be called after
+    /// traversing the PR comments.
+    pub fn traverse_pull_request_reviews(&mut self, mut reviews: Vec<models::pulls::Review>) {
+        reviews.sort_by_key(|review| review.submitted_at);
+        // The submitted time of the standing approvals of each (PR, user) pair.
+        let mut approvals: HashMap<(Option<u64>, String), Vec<DateTime<Utc>>> = HashMap::new();
         reviews.iter().for_each(|review| {
             if self.filter_pull_request_review(review) {
                 return;
@@ -114,23 +1157,485 @@ impl Stats {
                 "traverse pull request review: #{} [{:?}] {:?} by {}",
                 review.id, review.state, review.body, user
             );
+            let pull_request = pull_request_number(review.html_url.as_str());
+            if let Some(submitted_at) = review.submitted_at {
+                self.record_response(pull_request, user, submitted_at);
+            }
+            self.record_reviewed_pr(pull_request, user);
+            self.add_pr_review(user);
+            self.emit_raw_event(
+                "review",
+                user,
+                review.submitted_at.unwrap_or(self.end_time),
+                pull_request,
+            );
             if let Some(state) = review.state {
                 match state {
-                    ReviewState::Approved => self.add_lgtm(user),
+                    ReviewState::Approved => approvals
+                        .entry((pull_request, user.to_string()))
+                        .or_default()
+                        .push(review.submitted_at.unwrap_or(self.end_time)),
+                    ReviewState::Dismissed => {
+                        if let Some(dismissed) = approvals.remove(&(pull_request, user.to_string()))
+                        {
+                            debug!(
+                                "{} approvals dismissed: #{:?} by {}",
+                                dismissed.len(),
+                                pull_request,
+                                user
+                            );
+                        }
+                    }
+                    ReviewState::ChangesRequested => {
+                        self.add_changes_requested(user);
+                        if let Some(submitted_at) = review.submitted_at {
+                            self.add_activity(user, submitted_at)
+                        }
+                    }
+                    ReviewState::Commented => {
+                        self.add_commented_review(user);
+                        if let Some(submitted_at) = review.submitted_at {
+                            self.add_activity(user, submitted_at)
+                        }
+                    }
                     _ => {}
                 }
             }
+        });
+        for ((pull_request, user), submitted_times) in approvals {
+            if let Some(submitted_at) = submitted_times.first() {
+                if self.record_lgtm_pr(pull_request, &user) {
+                    self.add_lgtm(&user);
+                    self.add_activity(&user, *submitted_at)
+                }
+            }
+        }
+    }
+
+    /// Traverse the reactions given to the issues, PRs and comments to collect the thumbs-up
+    /// (+1) reactions given by each user, other reactions are not counted.
+    pub fn traverse_reactions(&mut self, reactions: Vec<models::reactions::Reaction>) {
+        reactions.iter().for_each(|reaction| {
+            // Only the thumbs-up reactions are a signal of agreement.
+            if reaction.content != ReactionContent::PlusOne || self.filter_reaction(reaction) {
+                return;
+            }
+            debug!(
+                "traverse reaction: #{} {:?} by {}",
+                reaction.id, reaction.content, reaction.user.login
+            );
+            self.add_reaction_given(&reaction.user.login);
+            self.add_activity(&reaction.user.login, reaction.created_at)
+        })
+    }
+
+    /// Traverse the timeline events of issues and PRs to collect the labels added, the
+    /// approval labels applied and the issues and PRs reopened by each user. An approval
+    /// label applied by a user who has already approved the same PR with a review or a
+    /// LGTM comment is not counted again, so this should be called after traversing the
+    /// PR reviews and comments.
+    pub fn traverse_timeline_events(
+        &mut self,
+        events: Vec<(u64, models::timelines::TimelineEvent)>,
+    ) {
+        events.iter().for_each(|(number, event)| {
+            if self.filter_timeline_event(*number, event) {
+                return;
+            }
+            let user = event.actor.as_ref().map_or("", |actor| &actor.login);
+            match event.event {
+                Event::Labeled => {
+                    let label = event.label.as_ref().map_or("", |label| &label.name);
+                    if self.count_labels {
+                        debug!(
+                            "traverse labeled event: #{} {:?} by {}",
+                            number, label, user
+                        );
+                        self.add_label(user);
+                        self.add_activity(user, event.created_at.unwrap_or(self.end_time))
+                    }
+                    if !self.pull_requests.contains(number) || !self.approval_labels.contains(label)
+                    {
+                        return;
+                    }
+                    debug!(
+                        "traverse approval label event: #{} {:?} by {}",
+                        number, label, user
+                    );
+                    if self.lgtm_prs.insert((*number, self.canonical_user(user))) {
+                        self.add_lgtm(user);
+                        self.add_activity(user, event.created_at.unwrap_or(self.end_time))
+                    }
+                }
+                Event::Reopened if self.count_reopens => {
+                    debug!("traverse reopened event: #{} by {}", number, user);
+                    self.add_reopen(user);
+                    self.add_activity(user, event.created_at.unwrap_or(self.end_time))
+                }
+                Event::Closed if self.count_closes => {
+                    debug!("traverse closed event: #{} by {}", number, user);
+                    if self.pull_requests.contains(number) {
+                        self.add_pr_closed(user);
+                    } else {
+                        self.add_issue_closed(user);
+                    }
+                    self.add_activity(user, event.created_at.unwrap_or(self.end_time))
+                }
+                _ => {}
+            }
         })
     }
 
     /// Consume and merge the other stats into self.
     pub fn merge(&mut self, other: Self) {
-        Self::merge_map(&mut self.issues, &other.issues);
-        Self::merge_map(&mut self.prs, &other.prs);
-        Self::merge_map(&mut self.issue_comments, &other.issue_comments);
-        Self::merge_map(&mut self.pr_reviews, &other.pr_reviews);
-        Self::merge_map(&mut self.lgtms, &other.lgtms);
-        Self::merge_map(&mut self.labels, &other.labels);
+        // Destructure all the fields, so a newly added field can't be silently dropped.
+        let Self {
+            user_metrics,
+            opened_prs,
+            first_responses,
+            reviewed_prs,
+            failed_endpoints,
+            hard_failed_repos,
+            // The repo and the time range are kept as the first ones, and the config and
+            // the traversal states are only meaningful within a single repo.
+            repo: _,
+            allowed_users: _,
+            user_aliases: _,
+            track_all_users: _,
+            user_exclusion: _,
+            lgtm_comments: _,
+            lgtm_patterns: _,
+            credit_coauthors: _,
+            approval_labels: _,
+            lgtm_prs: _,
+            decay_half_life: _,
+            suggestion_prs: _,
+            collapse_review_threads: _,
+            review_threads: _,
+            traversed_issues: _,
+            authored_prs: _,
+            closed_prs: _,
+            pr_size_thresholds: _,
+            min_comment_length: _,
+            count_reopens: _,
+            count_closes: _,
+            count_labels: _,
+            pull_requests: _,
+            base_branch_prs: _,
+            label_filter: _,
+            business_hours: _,
+            label_matched_issues: _,
+            draft_prs: _,
+            start_time: _,
+            end_time: _,
+            scoring_weights: _,
+            raw_events: _,
+        } = other;
+        // The users are canonicalized again, since the other stats may be counted with
+        // other aliases, e.g. loaded from a checkpoint of an older config.
+        for (user, metrics) in user_metrics {
+            let user = self.canonical_user(&user);
+            self.user_metrics.entry(user).or_default().merge(metrics);
+        }
+        for (repo, endpoints) in failed_endpoints {
+            self.failed_endpoints
+                .entry(repo)
+                .or_default()
+                .extend(endpoints);
+        }
+        self.hard_failed_repos.extend(hard_failed_repos);
+        self.opened_prs.extend(opened_prs);
+        for (pull_request, responded_at) in first_responses {
+            let first = self
+                .first_responses
+                .entry(pull_request)
+                .or_insert(responded_at);
+            *first = (*first).min(responded_at);
+        }
+        self.reviewed_prs.extend(reviewed_prs);
+    }
+
+    /// Keep only the given users in the stats, e.g. to focus the output on some of them.
+    /// The traversal states are left as is, so this should only be called once the stats
+    /// are traversed and merged.
+    pub fn retain_users(&mut self, users: &HashSet<String>) {
+        self.user_metrics.retain(|user, _| users.contains(user));
+        // The time to first response only covers the PRs authored by the users.
+        self.opened_prs
+            .retain(|_, (_, author)| users.contains(author));
+    }
+
+    /// Rebuild the stats deserialized from a checkpoint with the config, which is skipped in
+    /// the serialization.
+    pub fn with_config(self, config: &Config) -> Self {
+        let mut stats = Stats::new(config, &self.repo, self.start_time, self.end_time);
+        stats.merge(self);
+        stats
+    }
+
+    /// Merge all the stats into the first one, e.g. the stats of each repo. `None` if
+    /// there are no stats at all. The merge is associative, so the result is the same
+    /// no matter how the stats are grouped.
+    pub fn reduce(stats: impl IntoIterator<Item = Stats>) -> Option<Stats> {
+        stats.into_iter().reduce(|mut merged, stats| {
+            merged.merge(stats);
+            merged
+        })
+    }
+
+    /// Get the metrics of each user.
+    pub fn user_metrics(&self) -> &HashMap<String, UserMetrics> {
+        &self.user_metrics
+    }
+
+    /// Get the time-decayed activity score of each user, which is empty unless the decay
+    /// half-life is set.
+    pub fn decayed_scores(&self) -> HashMap<String, f64> {
+        self.map_users(|metrics| metrics.decayed_score)
+    }
+
+    /// Get the time of the first activity of each user within the time range.
+    pub fn first_seen(&self) -> HashMap<String, DateTime<Utc>> {
+        self.map_users(|metrics| metrics.first_seen)
+    }
+
+    /// Get the time of the last activity of each user within the time range.
+    pub fn last_seen(&self) -> HashMap<String, DateTime<Utc>> {
+        self.map_users(|metrics| metrics.last_seen)
+    }
+
+    /// Get the LGTMs of each user weighted by the sizes of the approved PRs, which is
+    /// empty unless the sizes of the approved PRs are traversed.
+    pub fn weighted_lgtms(&self) -> HashMap<String, f64> {
+        self.map_users(|metrics| metrics.weighted_lgtms)
+    }
+
+    /// Get the PRs of each user has created in each size bucket.
+    pub fn pr_sizes(&self) -> HashMap<String, Vec<(&'static str, u64)>> {
+        self.map_users(|metrics| {
+            (metrics.pr_sizes != [0; 5]).then(|| {
+                PR_SIZE_BUCKETS
+                    .iter()
+                    .copied()
+                    .zip(metrics.pr_sizes.iter().copied())
+                    .collect()
+            })
+        })
+    }
+
+    /// Get the contribution score of each user, i.e. the sum of the counts of each metric
+    /// multiplied by its weight. The weights may be negative to penalize some activities.
+    pub fn score(&self) -> HashMap<String, f64> {
+        let mut scores = HashMap::new();
+        for metric in Metric::ALL {
+            let weight = self.scoring_weights.get(&metric).copied().unwrap_or(1.0);
+            for (user, count) in self.metric_map(metric) {
+                *scores.entry(user).or_insert(0.0) += count as f64 * weight;
+            }
+        }
+        scores
+    }
+
+    /// Get the top N users of the given metric, sorted by the count in descending order
+    /// and then by the user, so the ties are always in the same order.
+    pub fn leaderboard(&self, metric: Metric, n: usize) -> Vec<(String, u64)> {
+        let mut counts: Vec<(String, u64)> = self
+            .metric_map(metric)
+            .iter()
+            .filter(|(_, count)| **count > 0)
+            .map(|(user, count)| (user.clone(), *count))
+            .collect();
+        counts.sort_by(|(user_a, count_a), (user_b, count_b)| {
+            count_b.cmp(count_a).then_with(|| user_a.cmp(user_b))
+        });
+        counts.truncate(n);
+        counts
+    }
+
+    /// Get the total of each metric across all the users, keyed by the metric name.
+    pub fn totals(&self) -> HashMap<&'static str, u64> {
+        Metric::ALL
+            .iter()
+            .map(|metric| (metric.name(), self.metric_map(*metric).values().sum()))
+            .collect()
+    }
+
+    /// Whether nothing is counted for any user in any metric.
+    pub fn is_empty(&self) -> bool {
+        Metric::ALL
+            .iter()
+            .all(|metric| self.metric_map(*metric).values().all(|count| *count == 0))
+    }
+
+    /// Get the repo of the stats, which is the first one if merged from multiple repos.
+    pub fn repo(&self) -> &str {
+        &self.repo
+    }
+
+    /// Get the time range of the stats.
+    pub fn time_range(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        (self.start_time, self.end_time)
+    }
+
+    /// Get the number of distinct repos of each user has been active in.
+    pub fn repos_touched(&self) -> HashMap<String, usize> {
+        self.map_users(|metrics| {
+            (!metrics.repos_touched.is_empty()).then_some(metrics.repos_touched.len())
+        })
+    }
+
+    /// Get the number of distinct users active in any repo.
+    pub fn unique_active_users(&self) -> usize {
+        self.user_metrics
+            .values()
+            .filter(|metrics| !metrics.repos_touched.is_empty())
+            .count()
+    }
+
+    /// Get the number of distinct repos any user has been active in.
+    pub fn active_repos(&self) -> usize {
+        self.user_metrics
+            .values()
+            .flat_map(|metrics| &metrics.repos_touched)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Get the distribution of the given metric across the active users. The standard
+    /// deviation is the population one, and all values are zero if nobody is active.
+    pub fn distribution(&self, metric: Metric) -> DistributionSummary {
+        let mut counts: Vec<u64> = self
+            .metric_map(metric)
+            .values()
+            .copied()
+            .filter(|count| *count > 0)
+            .collect();
+        if counts.is_empty() {
+            return DistributionSummary::default();
+        }
+        counts.sort_unstable();
+        let n = counts.len();
+        let mean = counts.iter().sum::<u64>() as f64 / n as f64;
+        let median = if n.is_multiple_of(2) {
+            (counts[n / 2 - 1] + counts[n / 2]) as f64 / 2.0
+        } else {
+            counts[n / 2] as f64
+        };
+        let variance = counts
+            .iter()
+            .map(|count| (*count as f64 - mean).powi(2))
+            .sum::<f64>()
+            / n as f64;
+        DistributionSummary {
+            active_users: n,
+            mean,
+            median,
+            std_dev: variance.sqrt(),
+        }
+    }
+
+    /// Get the min, median and max time the PRs created within the time range waited
+    /// before the first response, in the business hours if configured or the wall-clock
+    /// time otherwise. `None` if no PR has been responded to.
+    pub fn time_to_first_response(&self) -> Option<ResponseTimeSummary> {
+        let mut waits: Vec<Duration> = self
+            .first_responses
+            .iter()
+            .filter_map(|(pull_request, responded_at)| {
+                let (created_at, _) = self.opened_prs.get(pull_request)?;
+                Some(match &self.business_hours {
+                    Some(business_hours) => business_hours.duration(*created_at, *responded_at),
+                    None => *responded_at - *created_at,
+                })
+            })
+            .collect();
+        if waits.is_empty() {
+            return None;
+        }
+        waits.sort_unstable();
+        let n = waits.len();
+        let median = if n.is_multiple_of(2) {
+            (waits[n / 2 - 1] + waits[n / 2]) / 2
+        } else {
+            waits[n / 2]
+        };
+        Some(ResponseTimeSummary {
+            responded_prs: n,
+            min: waits[0],
+            median,
+            max: waits[n - 1],
+        })
+    }
+
+    /// Get the review coverage of each repo, i.e. how many of the PRs created within the
+    /// time range got at least one review from an allowed user other than the author.
+    /// The repos without any PR created are absent.
+    pub fn review_coverage(&self) -> BTreeMap<String, ReviewCoverage> {
+        let mut coverage: BTreeMap<String, ReviewCoverage> = BTreeMap::new();
+        for pull_request in self.opened_prs.keys() {
+            let repo_coverage = coverage.entry(pull_request.0.clone()).or_default();
+            repo_coverage.total_prs += 1;
+            if self.reviewed_prs.contains(pull_request) {
+                repo_coverage.reviewed_prs += 1;
+            }
+        }
+        coverage
+    }
+
+    /// Get the non-zero counts of the given metric of each user.
+    fn metric_map(&self, metric: Metric) -> HashMap<String, u64> {
+        self.map_users(|metrics| {
+            let count = metrics.metric(metric);
+            (count > 0).then_some(count)
+        })
+    }
+
+    /// Map the metrics of each user, omitting the users mapped to `None`.
+    fn map_users<T>(&self, f: impl Fn(&UserMetrics) -> Option<T>) -> HashMap<String, T> {
+        self.user_metrics
+            .iter()
+            .filter_map(|(user, metrics)| Some((user.clone(), f(metrics)?)))
+            .collect()
+    }
+
+    /// Get a snapshot of the raw counts of each metric.
+    pub fn counts(&self) -> Counts {
+        Counts {
+            issues: self.metric_map(Metric::Issues),
+            prs: self.metric_map(Metric::Prs),
+            issue_comments: self.metric_map(Metric::IssueComments),
+            pr_reviews: self.metric_map(Metric::PrReviews),
+            lgtms: self.metric_map(Metric::Lgtms),
+            labels: self.metric_map(Metric::Labels),
+            reopens: self.metric_map(Metric::Reopens),
+            suggestions_made: self.metric_map(Metric::SuggestionsMade),
+            suggestions_accepted: self.metric_map(Metric::SuggestionsAccepted),
+            changes_requested: self.metric_map(Metric::ChangesRequested),
+            commented_reviews: self.metric_map(Metric::CommentedReviews),
+            reactions_given: self.metric_map(Metric::ReactionsGiven),
+            prs_merged: self.metric_map(Metric::PrsMerged),
+            review_comments: self.metric_map(Metric::ReviewComments),
+            issues_closed: self.metric_map(Metric::IssuesClosed),
+            prs_closed: self.metric_map(Metric::PrsClosed),
+            pr_sizes: self
+                .map_users(|metrics| (metrics.pr_sizes != [0; 5]).then_some(metrics.pr_sizes)),
+            weighted_lgtms: self.weighted_lgtms(),
+            avg_comment_length: self.avg_comment_length(),
+            repos_touched: self
+                .repos_touched()
+                .into_iter()
+                .map(|(user, repos)| (user, repos as u64))
+                .collect(),
+        }
+    }
+
+    /// Get the average comment length in characters of each user. Users without
+    /// any comment within the time range are omitted.
+    pub fn avg_comment_length(&self) -> HashMap<String, f64> {
+        self.map_users(|metrics| {
+            let (length, count) = metrics.comment_length;
+            (count > 0).then(|| length as f64 / count as f64)
+        })
     }
 
     fn within_time_range(&self, date_time: DateTime<Utc>) -> bool {
@@ -140,14 +1645,19 @@ impl Stats {
     fn filter_issues(&self, issue: &models::issues::Issue) -> bool {
         let user_allowed = self.is_user_allowed(&issue.user.login);
         let within_time_range = self.within_time_range(issue.created_at);
+        let base_branch_matched =
+            issue.pull_request.is_none() || self.is_pull_request_allowed(Some(issue.number));
+        let draft = issue.pull_request.is_some() && self.draft_prs.contains(&issue.number);
         debug!(
-            "filter issue {} [user_allowed]: {}, [crated_at {} within_time_range] {}",
+            "filter issue {} [user_allowed]: {}, [crated_at {} within_time_range] {}, [base_branch_matched] {}, [draft] {}",
             issue_into_string(issue),
             user_allowed,
             issue.created_at,
-            within_time_range
+            within_time_range,
+            base_branch_matched,
+            draft
         );
-        !user_allowed || !within_time_range
+        !user_allowed || !within_time_range || !base_branch_matched || draft
     }
 
     fn filter_issue_comment(&self, comment: &models::issues::Comment) -> bool {
@@ -155,16 +1665,20 @@ impl Stats {
         let within_time_range = self.within_time_range(comment.created_at)
             || comment
                 .updated_at
-                .map_or(false, |updated_at| self.within_time_range(updated_at));
+                .is_some_and(|updated_at| self.within_time_range(updated_at));
+        let long_enough = self.is_comment_long_enough(comment.body.as_deref().unwrap_or_default());
+        let label_matched = self.is_issue_label_matched(issue_number(comment.html_url.as_str()));
         debug!(
-            "filter issue comment {} [user_allowed]: {}, [created_at {} updated_at {:?} within_time_range] {}",
+            "filter issue comment {} [user_allowed]: {}, [created_at {} updated_at {:?} within_time_range] {}, [long_enough] {}, [label_matched] {}",
             issue_comment_into_string(comment),
             user_allowed,
             comment.created_at,
             comment.updated_at,
-            within_time_range
+            within_time_range,
+            long_enough,
+            label_matched
         );
-        !user_allowed || !within_time_range
+        !user_allowed || !within_time_range || !long_enough || !label_matched
     }
 
     fn filter_pull_request_comment(&self, comment: &models::pulls::Comment) -> bool {
@@ -172,15 +1686,28 @@ impl Stats {
         let user_allowed = self.is_user_allowed(user);
         let within_time_range = self.within_time_range(comment.created_at)
             || self.within_time_range(comment.updated_at);
+        let base_branch_matched =
+            self.is_pull_request_allowed(pull_request_number(&comment.html_url));
+        // The LGTM comments are short by nature, so they are always long enough.
+        let long_enough =
+            self.is_comment_long_enough(&comment.body) || self.is_comment_lgtm(comment.body.trim());
+        let label_matched = self.is_issue_label_matched(pull_request_number(&comment.html_url));
         debug!(
-            "filter pull request comment {} [user_allowed]: {}, [created_at {} updated_at {:?} within_time_range] {}",
+            "filter pull request comment {} [user_allowed]: {}, [created_at {} updated_at {:?} within_time_range] {}, [base_branch_matched] {}, [long_enough] {}, [label_matched] {}",
             pull_comment_into_string(comment),
             user_allowed,
             comment.created_at,
             comment.updated_at,
-            within_time_range
+            within_time_range,
+            base_branch_matched,
+            long_enough,
+            label_matched
         );
-        !user_allowed || !within_time_range
+        !user_allowed
+            || !within_time_range
+            || !base_branch_matched
+            || !long_enough
+            || !label_matched
     }
 
     fn filter_pull_request_review(&self, review: &models::pulls::Review) -> bool {
@@ -188,70 +1715,461 @@ impl Stats {
         let user_allowed = self.is_user_allowed(user);
         let within_time_range = review
             .submitted_at
-            .map_or(false, |submitted_at| self.within_time_range(submitted_at));
+            .is_some_and(|submitted_at| self.within_time_range(submitted_at));
+        let base_branch_matched =
+            self.is_pull_request_allowed(pull_request_number(review.html_url.as_str()));
+        let label_matched =
+            self.is_issue_label_matched(pull_request_number(review.html_url.as_str()));
         debug!(
-            "filter pull request review {} [user_allowed]: {}, [submitted_at {:?} within_time_range] {}",
+            "filter pull request review {} [user_allowed]: {}, [submitted_at {:?} within_time_range] {}, [base_branch_matched] {}, [label_matched] {}",
             pull_review_into_string(review),
             user_allowed,
             review.submitted_at,
-            within_time_range
+            within_time_range,
+            base_branch_matched,
+            label_matched
+        );
+        !user_allowed || !within_time_range || !base_branch_matched || !label_matched
+    }
+
+    fn filter_timeline_event(&self, number: u64, event: &models::timelines::TimelineEvent) -> bool {
+        let user = event.actor.as_ref().map_or("", |actor| &actor.login);
+        let user_allowed = self.is_user_allowed(user);
+        let within_time_range = event
+            .created_at
+            .is_some_and(|created_at| self.within_time_range(created_at));
+        let base_branch_matched =
+            !self.pull_requests.contains(&number) || self.is_pull_request_allowed(Some(number));
+        let label_matched = self.is_issue_label_matched(Some(number));
+        debug!(
+            "filter timeline event #{} {:?} by {} [user_allowed]: {}, [created_at {:?} within_time_range] {}, [base_branch_matched] {}, [label_matched] {}",
+            number, event.event, user, user_allowed, event.created_at, within_time_range, base_branch_matched, label_matched
+        );
+        !user_allowed || !within_time_range || !base_branch_matched || !label_matched
+    }
+
+    fn filter_reaction(&self, reaction: &models::reactions::Reaction) -> bool {
+        let user_allowed = self.is_user_allowed(&reaction.user.login);
+        let within_time_range = self.within_time_range(reaction.created_at);
+        debug!(
+            "filter reaction #{} by {} [user_allowed]: {}, [created_at {} within_time_range] {}",
+            reaction.id, reaction.user.login, user_allowed, reaction.created_at, within_time_range
         );
         !user_allowed || !within_time_range
     }
 
+    /// Whether the user is allowed to be counted. The deleted users are never counted,
+    /// which are missing or shown as the "ghost" user by GitHub.
     #[inline(always)]
     fn is_user_allowed(&self, user: &str) -> bool {
-        self.allowed_users.contains(user)
+        if is_ghost(user) {
+            return false;
+        }
+        let user = self.canonical_user(user);
+        if self.track_all_users {
+            !self.user_exclusion.excludes(&user)
+        } else {
+            self.allowed_users.contains(&user)
+        }
+    }
+
+    /// Get the canonical login of the user, which is the login aliased to in
+    /// `review.user_aliases` if any, e.g. the new login of a renamed account.
+    #[inline(always)]
+    fn canonical_user(&self, user: &str) -> String {
+        self.user_aliases
+            .get(user)
+            .cloned()
+            .unwrap_or_else(|| user.to_string())
+    }
+
+    /// Get the allowed co-authors of the PR from the `Co-authored-by:` trailers of its
+    /// body, other than the author. With all the users tracked, the co-authors are told
+    /// by the logins in their GitHub noreply emails or their names.
+    fn co_authors(&self, issue: &models::issues::Issue) -> Vec<String> {
+        let co_authors: Vec<&str> = issue
+            .body
+            .as_deref()
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("Co-authored-by:"))
+            .collect();
+        let mut users: Vec<String> = if self.track_all_users {
+            co_authors
+                .iter()
+                .filter_map(|co_author| co_author_login(co_author))
+                .filter(|user| self.is_user_allowed(user))
+                .collect()
+        } else {
+            self.allowed_users
+                .iter()
+                .filter(|user| {
+                    co_authors
+                        .iter()
+                        .any(|co_author| is_co_author(co_author, user))
+                })
+                .cloned()
+                .collect()
+        };
+        users.retain(|user| !user.eq_ignore_ascii_case(&issue.user.login));
+        users.sort();
+        users.dedup();
+        users
+    }
+
+    #[inline(always)]
+    fn is_pull_request_allowed(&self, pull_request: Option<u64>) -> bool {
+        match (&self.base_branch_prs, pull_request) {
+            (None, _) => true,
+            (Some(prs), Some(number)) => prs.contains(&number),
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Whether the issue or PR matches the label filter, which is always true without any
+    /// label filter. An unknown issue or PR never matches the label filter.
+    #[inline(always)]
+    fn is_issue_label_matched(&self, issue: Option<u64>) -> bool {
+        self.label_filter.is_empty()
+            || issue.is_some_and(|number| self.label_matched_issues.contains(&number))
+    }
+
+    /// Whether the comment is at least the min length in characters after trimmed, e.g.
+    /// "+1" and "ping" are not long enough for a min length of 5.
+    #[inline(always)]
+    fn is_comment_long_enough(&self, comment: &str) -> bool {
+        self.min_comment_length == 0 || comment.trim().chars().count() >= self.min_comment_length
     }
 
+    /// Whether the comment contains any LGTM comment case-insensitively, or it's matched
+    /// by any LGTM pattern. See `LgtmComment` for how a LGTM comment is matched.
     #[inline(always)]
     fn is_comment_lgtm(&self, comment: &str) -> bool {
-        self.lgtm_comments.iter().any(|lgtm| comment.contains(lgtm))
+        let words = words(comment);
+        let lowercase = comment.to_lowercase();
+        self.lgtm_comments
+            .iter()
+            .any(|lgtm| lgtm.matches(&words, &lowercase))
+            || self
+                .lgtm_patterns
+                .iter()
+                .any(|pattern| pattern.is_match(comment))
+    }
+
+    /// Record the review or comment of the user on the PR as a response, if the PR is
+    /// created within the time range by another user, keeping the first one.
+    #[inline(always)]
+    fn record_response(
+        &mut self,
+        pull_request: Option<u64>,
+        user: &str,
+        responded_at: DateTime<Utc>,
+    ) {
+        let Some(number) = pull_request else {
+            return;
+        };
+        let pull_request = (self.repo.clone(), number);
+        match self.opened_prs.get(&pull_request) {
+            Some((created_at, author))
+                if *author != self.canonical_user(user) && responded_at >= *created_at =>
+            {
+                let first = self
+                    .first_responses
+                    .entry(pull_request)
+                    .or_insert(responded_at);
+                *first = (*first).min(responded_at);
+            }
+            _ => {}
+        }
+    }
+
+    /// Record the PR as reviewed if it's created within the time range by another user.
+    #[inline(always)]
+    fn record_reviewed_pr(&mut self, pull_request: Option<u64>, user: &str) {
+        let Some(number) = pull_request else {
+            return;
+        };
+        let pull_request = (self.repo.clone(), number);
+        if matches!(
+            self.opened_prs.get(&pull_request),
+            Some((_, author)) if *author != self.canonical_user(user)
+        ) {
+            self.reviewed_prs.insert(pull_request);
+        }
+    }
+
+    /// Record the LGTM of the user on the PR, returns whether it's the first one of the
+    /// pair and should be counted. The LGTMs on an unknown PR are always counted.
+    #[inline(always)]
+    fn record_lgtm_pr(&mut self, pull_request: Option<u64>, user: &str) -> bool {
+        match pull_request {
+            Some(number) => self.lgtm_prs.insert((number, self.canonical_user(user))),
+            None => true,
+        }
     }
 
     #[inline(always)]
     fn add_issue(&mut self, user: &str) {
-        let count = self.issues.entry(user.to_string()).or_insert(0);
-        *count += 1;
+        self.user_metrics_mut(user).issues += 1;
+        self.touch_repo(user);
+    }
+
+    fn emit_raw_event(
+        &self,
+        kind: &'static str,
+        user: &str,
+        timestamp: DateTime<Utc>,
+        pr_number: Option<u64>,
+    ) {
+        if let Some(raw_events) = &self.raw_events {
+            // The sink is best-effort, ignore it if nobody is receiving.
+            let _ = raw_events.send(RawEvent {
+                kind,
+                repo: self.repo.clone(),
+                user: self.canonical_user(user),
+                timestamp,
+                pr_number,
+            });
+        }
     }
 
     #[inline(always)]
     fn add_pr(&mut self, user: &str) {
-        let count = self.prs.entry(user.to_string()).or_insert(0);
-        *count += 1;
+        self.user_metrics_mut(user).prs += 1;
+        self.touch_repo(user);
     }
 
     #[inline(always)]
     fn add_issue_comment(&mut self, user: &str) {
-        let count = self.issue_comments.entry(user.to_string()).or_insert(0);
-        *count += 1;
+        self.user_metrics_mut(user).issue_comments += 1;
+        self.touch_repo(user);
     }
 
     #[inline(always)]
     fn add_pr_review(&mut self, user: &str) {
-        let count = self.pr_reviews.entry(user.to_string()).or_insert(0);
-        *count += 1;
+        self.user_metrics_mut(user).pr_reviews += 1;
+        self.touch_repo(user);
     }
 
     #[inline(always)]
     fn add_lgtm(&mut self, user: &str) {
-        let count = self.lgtms.entry(user.to_string()).or_insert(0);
-        *count += 1;
+        self.user_metrics_mut(user).lgtms += 1;
+        self.touch_repo(user);
     }
 
     #[inline(always)]
     fn add_label(&mut self, user: &str) {
-        let count = self.labels.entry(user.to_string()).or_insert(0);
-        *count += 1;
+        self.user_metrics_mut(user).labels += 1;
+        self.touch_repo(user);
+    }
+
+    #[inline(always)]
+    fn add_suggestion_made(&mut self, user: &str) {
+        self.user_metrics_mut(user).suggestions_made += 1;
+        self.touch_repo(user);
+    }
+
+    #[inline(always)]
+    fn add_suggestion_accepted(&mut self, user: &str) {
+        self.user_metrics_mut(user).suggestions_accepted += 1;
+        self.touch_repo(user);
+    }
+
+    #[inline(always)]
+    fn add_changes_requested(&mut self, user: &str) {
+        self.user_metrics_mut(user).changes_requested += 1;
+        self.touch_repo(user);
+    }
+
+    #[inline(always)]
+    fn add_commented_review(&mut self, user: &str) {
+        self.user_metrics_mut(user).commented_reviews += 1;
+        self.touch_repo(user);
+    }
+
+    #[inline(always)]
+    fn add_reaction_given(&mut self, user: &str) {
+        self.user_metrics_mut(user).reactions_given += 1;
+        self.touch_repo(user);
+    }
+
+    #[inline(always)]
+    fn add_pr_merged(&mut self, user: &str) {
+        self.user_metrics_mut(user).prs_merged += 1;
+        self.touch_repo(user);
+    }
+
+    #[inline(always)]
+    fn add_review_comment(&mut self, user: &str) {
+        self.user_metrics_mut(user).review_comments += 1;
+        self.touch_repo(user);
+    }
+
+    #[inline(always)]
+    fn add_issue_closed(&mut self, user: &str) {
+        self.user_metrics_mut(user).issues_closed += 1;
+        self.touch_repo(user);
     }
 
     #[inline(always)]
-    fn merge_map(base: &mut HashMap<String, u64>, added: &HashMap<String, u64>) {
-        for (user, delta) in added {
-            let count = base.entry(user.to_string()).or_insert(0);
-            *count += *delta;
+    fn add_pr_closed(&mut self, user: &str) {
+        self.user_metrics_mut(user).prs_closed += 1;
+        self.touch_repo(user);
+    }
+
+    #[inline(always)]
+    fn touch_repo(&mut self, user: &str) {
+        let repo = self.repo.clone();
+        self.user_metrics_mut(user).repos_touched.insert(repo);
+    }
+
+    #[inline(always)]
+    fn add_reopen(&mut self, user: &str) {
+        self.user_metrics_mut(user).reopens += 1;
+        self.touch_repo(user);
+    }
+
+    /// Record an activity of the user at the time, for the first and last seen times and
+    /// the decayed score.
+    #[inline(always)]
+    fn add_activity(&mut self, user: &str, date_time: DateTime<Utc>) {
+        let decayed_score = self.decay_half_life.map(|half_life| {
+            let age = (self.end_time - date_time).num_seconds().max(0) as f64;
+            let half_life = half_life.num_seconds().max(1) as f64;
+            0.5f64.powf(age / half_life)
+        });
+        let metrics = self.user_metrics_mut(user);
+        merge_option(&mut metrics.first_seen, Some(date_time), Ord::min);
+        merge_option(&mut metrics.last_seen, Some(date_time), Ord::max);
+        merge_option(&mut metrics.decayed_score, decayed_score, |a, b| a + b);
+    }
+
+    #[inline(always)]
+    fn add_comment_length(&mut self, user: &str, body: &str) {
+        let comment_length = &mut self.user_metrics_mut(user).comment_length;
+        comment_length.0 += body.trim().chars().count() as u64;
+        comment_length.1 += 1;
+    }
+
+    /// Get the metrics of the canonical user, which are created on the first access.
+    #[inline(always)]
+    fn user_metrics_mut(&mut self, user: &str) -> &mut UserMetrics {
+        let user = self.canonical_user(user);
+        self.user_metrics.entry(user).or_default()
+    }
+}
+
+/// The login GitHub shows in place of the deleted users.
+const GHOST_LOGIN: &str = "ghost";
+
+/// Whether the login is of a deleted user, i.e. it's missing or the "ghost" user.
+fn is_ghost(user: &str) -> bool {
+    user.is_empty() || user == GHOST_LOGIN
+}
+
+/// The PR size buckets, split by the configured lines changed thresholds.
+const PR_SIZE_BUCKETS: [&str; 5] = ["XS", "S", "M", "L", "XL"];
+
+/// The opening of a code suggestion block in a review comment.
+const SUGGESTION_BLOCK: &str = "```suggestion";
+
+/// Whether the co-author like "Name <12345+login@users.noreply.github.com>" is the user,
+/// by either the name or the login in the GitHub noreply email.
+fn is_co_author(co_author: &str, user: &str) -> bool {
+    let co_author = co_author.trim().to_lowercase();
+    let user = user.to_lowercase();
+    co_author.starts_with(&format!("{} <", user))
+        || co_author.contains(&format!("+{}@users.noreply.github.com", user))
+        || co_author.contains(&format!("<{}@users.noreply.github.com", user))
+}
+
+/// Get the weight of a LGTM on a PR with the lines changed, see
+/// `Stats::traverse_lgtm_pull_request_sizes`.
+fn lgtm_weight(lines_changed: u64) -> f64 {
+    (lines_changed as f64).ln_1p().max(1.0)
+}
+
+/// Get the login of the co-author like "Name <12345+login@users.noreply.github.com>"
+/// from the GitHub noreply email, or from the name if it's a single word.
+fn co_author_login(co_author: &str) -> Option<String> {
+    let co_author = co_author.trim();
+    let (name, email) = match co_author.split_once('<') {
+        Some((name, email)) => (name.trim(), email.trim_end_matches('>').trim()),
+        None => (co_author, ""),
+    };
+    let login = email
+        .strip_suffix("@users.noreply.github.com")
+        .map(|local| local.rsplit('+').next().unwrap_or(local))
+        .or_else(|| (!name.is_empty() && !name.contains(char::is_whitespace)).then_some(name))?;
+    Some(login.to_string())
+}
+
+/// A LGTM comment of the allow list to match the comments against.
+#[derive(Debug, Clone, PartialEq)]
+enum LgtmComment {
+    /// The lowercase words to match on word boundaries, e.g. "/lgtm" and "LGTM!" match
+    /// "lgtm" while "nolgtm" does not.
+    Words(Vec<String>),
+    /// The lowercase text with any symbol, e.g. ":+1:" or "👍", to match as a substring,
+    /// since the symbols would be lost by splitting it into words.
+    Text(String),
+}
+
+impl LgtmComment {
+    /// Get the LGTM comment to match, `None` if it's blank.
+    fn new(lgtm: &str) -> Option<Self> {
+        let lgtm = lgtm.trim();
+        if lgtm.is_empty() {
+            return None;
+        }
+        if lgtm
+            .chars()
+            .all(|c| c.is_alphanumeric() || c.is_whitespace())
+        {
+            Some(LgtmComment::Words(words(lgtm)))
+        } else {
+            Some(LgtmComment::Text(lgtm.to_lowercase()))
         }
     }
+
+    /// Whether the comment split into the lowercase words, or the lowercase comment as a
+    /// whole, contains the LGTM comment.
+    fn matches(&self, words: &[String], lowercase: &str) -> bool {
+        match self {
+            LgtmComment::Words(lgtm) => words
+                .windows(lgtm.len())
+                .any(|window| window == lgtm.as_slice()),
+            LgtmComment::Text(lgtm) => lowercase.contains(lgtm.as_str()),
+        }
+    }
+}
+
+/// Split the text into the lowercase words, i.e. the alphanumeric runs.
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/// Extract the PR number from its HTML URL like "https://github.com/owner/repo/pull/1#discussion_r2".
+#[inline(always)]
+fn pull_request_number(html_url: &str) -> Option<u64> {
+    html_url
+        .split_once("/pull/")
+        .and_then(|(_, rest)| rest.split(|c: char| !c.is_ascii_digit()).next())
+        .and_then(|number| number.parse().ok())
+}
+
+/// Get the number of the issue or PR from the URL of it or its comment, e.g.
+/// "https://github.com/tikv/tikv/issues/42#issuecomment-1".
+fn issue_number(html_url: &str) -> Option<u64> {
+    html_url
+        .split_once("/issues/")
+        .map(|(_, rest)| rest)
+        .or_else(|| html_url.split_once("/pull/").map(|(_, rest)| rest))
+        .and_then(|rest| rest.split(|c: char| !c.is_ascii_digit()).next())
+        .and_then(|number| number.parse().ok())
 }
 
 #[inline(always)]
@@ -289,3 +2207,1047 @@ fn pull_review_into_string(review: &models::pulls::Review) -> String {
         review.user.as_ref().map_or("", |auth| &auth.login)
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::config::ConfigFormat;
+
+    const REPO: &str = "tikv/tikv";
+
+    fn config(review: &str) -> Config {
+        let config = format!(
+            "[review]\nusers = [\"alice\", \"bob\"]\nlgtm_comments = [\"lgtm\"]\n{}",
+            review
+        );
+        Config::from_reader(config.as_bytes(), ConfigFormat::Toml).unwrap()
+    }
+
+    fn time(time: &str) -> DateTime<Utc> {
+        time.parse().unwrap()
+    }
+
+    fn stats(config: &Config) -> Stats {
+        Stats::new(
+            config,
+            REPO,
+            time("2024-01-01T00:00:00Z"),
+            time("2024-01-08T00:00:00Z"),
+        )
+    }
+
+    fn author(login: &str) -> Value {
+        let url = format!("https://api.github.com/users/{}", login);
+        json!({
+            "login": login,
+            "id": 1,
+            "node_id": "",
+            "avatar_url": url,
+            "gravatar_id": "",
+            "url": url,
+            "html_url": url,
+            "followers_url": url,
+            "following_url": url,
+            "gists_url": url,
+            "starred_url": url,
+            "subscriptions_url": url,
+            "organizations_url": url,
+            "repos_url": url,
+            "events_url": url,
+            "received_events_url": url,
+            "type": "User",
+            "site_admin": false,
+        })
+    }
+
+    fn issue(
+        number: u64,
+        login: &str,
+        created_at: &str,
+        pull_request: bool,
+    ) -> models::issues::Issue {
+        let url = format!("https://api.github.com/repos/{}/issues/{}", REPO, number);
+        let mut issue = json!({
+            "id": number,
+            "node_id": "",
+            "url": url,
+            "repository_url": url,
+            "labels_url": url,
+            "comments_url": url,
+            "events_url": url,
+            "html_url": format!("https://github.com/{}/issues/{}", REPO, number),
+            "number": number,
+            "state": "open",
+            "title": "",
+            "user": author(login),
+            "labels": [],
+            "assignees": [],
+            "author_association": "MEMBER",
+            "locked": false,
+            "comments": 0,
+            "created_at": created_at,
+            "updated_at": created_at,
+        });
+        if pull_request {
+            issue["pull_request"] = json!({
+                "url": url,
+                "html_url": url,
+                "diff_url": url,
+                "patch_url": url,
+            });
+        }
+        serde_json::from_value(issue).unwrap()
+    }
+
+    /// Label the issue with the labels by their names.
+    fn labeled(issue: models::issues::Issue, labels: &[&str]) -> models::issues::Issue {
+        let mut issue = serde_json::to_value(issue).unwrap();
+        issue["labels"] = labels
+            .iter()
+            .enumerate()
+            .map(|(id, name)| {
+                json!({
+                    "id": id,
+                    "node_id": "",
+                    "url": format!("https://api.github.com/repos/{}/labels/{}", REPO, name),
+                    "name": name,
+                    "color": "",
+                    "default": false,
+                })
+            })
+            .collect();
+        serde_json::from_value(issue).unwrap()
+    }
+
+    fn review(number: u64, login: &str, state: &str, submitted_at: &str) -> models::pulls::Review {
+        serde_json::from_value(json!({
+            "id": 1,
+            "node_id": "",
+            "html_url": format!("https://github.com/{}/pull/{}#pullrequestreview-1", REPO, number),
+            "user": author(login),
+            "body": "",
+            "state": state,
+            "submitted_at": submitted_at,
+        }))
+        .unwrap()
+    }
+
+    fn issue_comment(
+        id: u64,
+        number: u64,
+        login: &str,
+        body: &str,
+        created_at: &str,
+    ) -> models::issues::Comment {
+        let url = format!(
+            "https://github.com/{}/issues/{}#issuecomment-{}",
+            REPO, number, id
+        );
+        serde_json::from_value(json!({
+            "id": id,
+            "node_id": "",
+            "url": url,
+            "html_url": url,
+            "body": body,
+            "user": author(login),
+            "created_at": created_at,
+            "updated_at": created_at,
+        }))
+        .unwrap()
+    }
+
+    fn pull_request_comment(
+        id: u64,
+        number: u64,
+        login: &str,
+        body: &str,
+        created_at: &str,
+        in_reply_to: Option<u64>,
+    ) -> models::pulls::Comment {
+        let url = format!(
+            "https://api.github.com/repos/{}/pulls/comments/{}",
+            REPO, id
+        );
+        serde_json::from_value(json!({
+            "url": url,
+            "id": id,
+            "node_id": "",
+            "diff_hunk": "",
+            "path": "src/lib.rs",
+            "commit_id": "",
+            "original_commit_id": "",
+            "in_reply_to_id": in_reply_to,
+            "user": author(login),
+            "body": body,
+            "created_at": created_at,
+            "updated_at": created_at,
+            "html_url": format!("https://github.com/{}/pull/{}#discussion_r{}", REPO, number, id),
+            "author_association": "MEMBER",
+            "_links": {},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_time_to_first_response() {
+        let mut stats = stats(&config(""));
+        stats.traverse_issues(vec![issue(1, "alice", "2024-01-02T00:00:00Z", true)]);
+        stats.traverse_pull_request_reviews(vec![
+            // The author's own review is not a response.
+            review(1, "alice", "COMMENTED", "2024-01-02T01:00:00Z"),
+            review(1, "bob", "COMMENTED", "2024-01-02T03:00:00Z"),
+            review(1, "bob", "APPROVED", "2024-01-02T05:00:00Z"),
+        ]);
+        let response_time = stats.time_to_first_response().unwrap();
+        assert_eq!(response_time.responded_prs, 1);
+        assert_eq!(response_time.min, Duration::hours(3));
+        assert_eq!(response_time.median, Duration::hours(3));
+        assert_eq!(response_time.max, Duration::hours(3));
+    }
+
+    /// Get the metrics with each count of the metrics distinct, i.e. the index plus the
+    /// offset, so a count merged into a wrong field is caught.
+    fn user_metrics(offset: u64, seen: &str, repo: &str) -> UserMetrics {
+        let mut counts = (offset..).step_by(2);
+        let mut count = || counts.next().unwrap();
+        UserMetrics {
+            issues: count(),
+            prs: count(),
+            issue_comments: count(),
+            pr_reviews: count(),
+            lgtms: count(),
+            labels: count(),
+            reopens: count(),
+            suggestions_made: count(),
+            suggestions_accepted: count(),
+            changes_requested: count(),
+            commented_reviews: count(),
+            reactions_given: count(),
+            prs_merged: count(),
+            review_comments: count(),
+            issues_closed: count(),
+            prs_closed: count(),
+            pr_sizes: [count(), count(), count(), count(), count()],
+            comment_length: (count(), count()),
+            decayed_score: Some(count() as f64),
+            weighted_lgtms: None,
+            first_seen: Some(time(seen)),
+            last_seen: Some(time(seen)),
+            repos_touched: HashSet::from([repo.to_string()]),
+        }
+    }
+
+    #[test]
+    fn test_user_metrics_merge() {
+        let base = user_metrics(1, "2024-01-03T00:00:00Z", "tikv/tikv");
+        let mut other = user_metrics(2, "2024-01-02T00:00:00Z", "tikv/pd");
+        other.weighted_lgtms = Some(1.5);
+        let mut merged = base.clone();
+        merged.merge(other.clone());
+
+        for metric in Metric::ALL {
+            assert_eq!(
+                merged.metric(metric),
+                base.metric(metric) + other.metric(metric),
+                "{} is not summed",
+                metric
+            );
+        }
+        for bucket in 0..PR_SIZE_BUCKETS.len() {
+            assert_eq!(
+                merged.pr_sizes[bucket],
+                base.pr_sizes[bucket] + other.pr_sizes[bucket]
+            );
+        }
+        assert_eq!(
+            merged.comment_length,
+            (
+                base.comment_length.0 + other.comment_length.0,
+                base.comment_length.1 + other.comment_length.1
+            )
+        );
+        assert_eq!(
+            merged.decayed_score,
+            Some(base.decayed_score.unwrap() + other.decayed_score.unwrap())
+        );
+        // The present one is taken if only one of them is present.
+        assert_eq!(merged.weighted_lgtms, Some(1.5));
+        assert_eq!(merged.first_seen, Some(time("2024-01-02T00:00:00Z")));
+        assert_eq!(merged.last_seen, Some(time("2024-01-03T00:00:00Z")));
+        assert_eq!(
+            merged.repos_touched,
+            HashSet::from(["tikv/tikv".to_string(), "tikv/pd".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_reduce() {
+        let config = config("");
+        let mut first = stats(&config);
+        first.traverse_issues(vec![
+            issue(1, "alice", "2024-01-02T00:00:00Z", false),
+            issue(2, "alice", "2024-01-03T00:00:00Z", true),
+        ]);
+        let mut second = stats(&config);
+        second.traverse_issues(vec![
+            issue(3, "alice", "2024-01-04T00:00:00Z", false),
+            issue(4, "bob", "2024-01-05T00:00:00Z", true),
+        ]);
+        let third = stats(&config);
+
+        let merged = Stats::reduce([first.clone(), second.clone(), third]).unwrap();
+        for user in ["alice", "bob"] {
+            let mut expected = UserMetrics::default();
+            for stats in [&first, &second] {
+                if let Some(metrics) = stats.user_metrics().get(user) {
+                    expected.merge(metrics.clone());
+                }
+            }
+            assert_eq!(merged.user_metrics().get(user), Some(&expected));
+        }
+        let alice = &merged.user_metrics()["alice"];
+        assert_eq!(alice.issues, 2);
+        assert_eq!(alice.prs, 1);
+        assert_eq!(alice.first_seen, Some(time("2024-01-02T00:00:00Z")));
+        assert_eq!(alice.last_seen, Some(time("2024-01-04T00:00:00Z")));
+        assert_eq!(merged.user_metrics()["bob"].prs, 1);
+        assert!(Stats::reduce([]).is_none());
+    }
+
+    fn pull_request(number: u64, lines_changed: u64) -> models::pulls::PullRequest {
+        let branch = json!({ "ref": "main", "sha": "" });
+        serde_json::from_value(json!({
+            "url": format!("https://api.github.com/repos/{}/pulls/{}", REPO, number),
+            "id": number,
+            "number": number,
+            "locked": false,
+            "maintainer_can_modify": false,
+            "head": branch,
+            "base": branch,
+            "additions": lines_changed,
+            "deletions": 0,
+        }))
+        .unwrap()
+    }
+
+    fn timeline_event(
+        event: &str,
+        login: &str,
+        created_at: &str,
+        label: Option<&str>,
+    ) -> models::timelines::TimelineEvent {
+        let mut event = json!({
+            "event": event,
+            "actor": author(login),
+            "created_at": created_at,
+        });
+        if let Some(label) = label {
+            event["label"] = json!({ "name": label, "color": "" });
+        }
+        serde_json::from_value(event).unwrap()
+    }
+
+    #[test]
+    fn test_count_labels() {
+        let events = || {
+            vec![
+                (
+                    1,
+                    timeline_event("labeled", "alice", "2024-01-02T00:00:00Z", Some("bug")),
+                ),
+                (
+                    2,
+                    timeline_event("labeled", "alice", "2024-01-03T00:00:00Z", Some("lgtm")),
+                ),
+                (
+                    2,
+                    timeline_event("labeled", "bob", "2024-01-09T00:00:00Z", Some("bug")),
+                ),
+                (
+                    1,
+                    timeline_event("unlabeled", "bob", "2024-01-03T00:00:00Z", Some("bug")),
+                ),
+            ]
+        };
+        let issues = || {
+            vec![
+                issue(1, "bob", "2024-01-02T00:00:00Z", false),
+                issue(2, "bob", "2024-01-02T00:00:00Z", true),
+            ]
+        };
+
+        let mut counted = stats(&config("count_labels = true\napproval_labels = [\"lgtm\"]"));
+        assert!(counted.needs_timeline_events());
+        counted.traverse_issues(issues());
+        counted.traverse_timeline_events(events());
+        let counts = counted.counts();
+        // Both labels within the time range count, including the approval label, while
+        // the removed labels and the ones after the time range do not.
+        assert_eq!(counts.labels, HashMap::from([("alice".to_string(), 2)]));
+        assert_eq!(counts.lgtms.get("alice"), Some(&1));
+
+        let mut uncounted = stats(&config(""));
+        assert!(!uncounted.needs_timeline_events());
+        uncounted.traverse_issues(issues());
+        uncounted.traverse_timeline_events(events());
+        assert!(uncounted.counts().labels.is_empty());
+    }
+
+    #[test]
+    fn test_review_states() {
+        let mut stats = stats(&config(""));
+        stats.traverse_pull_request_reviews(vec![
+            review(1, "alice", "CHANGES_REQUESTED", "2024-01-02T00:00:00Z"),
+            review(1, "alice", "COMMENTED", "2024-01-03T00:00:00Z"),
+            review(2, "alice", "APPROVED", "2024-01-03T00:00:00Z"),
+            review(1, "bob", "COMMENTED", "2024-01-02T00:00:00Z"),
+            review(2, "bob", "COMMENTED", "2024-01-04T00:00:00Z"),
+            // Out of the time range.
+            review(3, "bob", "CHANGES_REQUESTED", "2024-01-09T00:00:00Z"),
+        ]);
+        let counts = stats.counts();
+        // Each review counts as a review as well, whatever its state is.
+        assert_eq!(
+            counts.pr_reviews,
+            HashMap::from([("alice".to_string(), 3), ("bob".to_string(), 2)])
+        );
+        assert_eq!(
+            counts.changes_requested,
+            HashMap::from([("alice".to_string(), 1)])
+        );
+        assert_eq!(
+            counts.commented_reviews,
+            HashMap::from([("alice".to_string(), 1), ("bob".to_string(), 2)])
+        );
+        assert_eq!(counts.lgtms, HashMap::from([("alice".to_string(), 1)]));
+    }
+
+    #[test]
+    fn test_exclude_users() {
+        let excluding = Config::from_reader(
+            "[review]\nusers = [\"alice\", \"bob\", \"dependabot[bot]\"]\n\
+             lgtm_comments = [\"lgtm\"]\nexclude_bots = true\nexclude_users = [\"bob\"]\n"
+                .as_bytes(),
+            ConfigFormat::Toml,
+        )
+        .unwrap();
+        let user_exclusion = excluding.review_user_exclusion();
+        assert!(!user_exclusion.excludes("alice"));
+        assert!(user_exclusion.excludes("bob"));
+        assert!(user_exclusion.excludes("dependabot[bot]"));
+
+        // The excluded users are not counted even if they are in the allowed users.
+        let mut stats = stats(&excluding);
+        stats.traverse_issues(vec![
+            issue(1, "alice", "2024-01-02T00:00:00Z", false),
+            issue(2, "bob", "2024-01-02T00:00:00Z", false),
+            issue(3, "dependabot[bot]", "2024-01-02T00:00:00Z", true),
+        ]);
+        let counts = stats.counts();
+        assert_eq!(counts.issues, HashMap::from([("alice".to_string(), 1)]));
+        assert!(counts.prs.is_empty());
+
+        // The bots are counted unless they are excluded.
+        assert!(!config("")
+            .review_user_exclusion()
+            .excludes("dependabot[bot]"));
+    }
+
+    #[test]
+    fn test_dismissed_approvals() {
+        let mut stats = stats(&config(""));
+        stats.traverse_pull_request_reviews(vec![
+            // Listed out of order, the dismissal still follows the approval.
+            review(1, "alice", "DISMISSED", "2024-01-03T00:00:00Z"),
+            review(1, "alice", "APPROVED", "2024-01-02T00:00:00Z"),
+            // Approved again after the dismissal, which stands.
+            review(2, "alice", "APPROVED", "2024-01-02T00:00:00Z"),
+            review(2, "alice", "DISMISSED", "2024-01-03T00:00:00Z"),
+            review(2, "alice", "APPROVED", "2024-01-04T00:00:00Z"),
+            // The dismissal of another user or on another PR doesn't matter.
+            review(1, "bob", "APPROVED", "2024-01-02T00:00:00Z"),
+            review(2, "bob", "DISMISSED", "2024-01-03T00:00:00Z"),
+        ]);
+        let counts = stats.counts();
+        assert_eq!(
+            counts.lgtms,
+            HashMap::from([("alice".to_string(), 1), ("bob".to_string(), 1)])
+        );
+        let mut lgtm_pull_requests = stats.lgtm_pull_requests();
+        lgtm_pull_requests.sort();
+        assert_eq!(lgtm_pull_requests, [1, 2]);
+    }
+
+    #[test]
+    fn test_lgtm_once_per_pull_request() {
+        let mut stats = stats(&config(""));
+        stats.traverse_pull_request_comments(vec![
+            pull_request_comment(1, 1, "alice", "lgtm", "2024-01-02T00:00:00Z", None),
+            pull_request_comment(2, 1, "alice", "LGTM!", "2024-01-03T00:00:00Z", None),
+            pull_request_comment(3, 2, "alice", "lgtm", "2024-01-03T00:00:00Z", None),
+        ]);
+        stats.traverse_pull_request_reviews(vec![
+            // Already LGTMed by the comments.
+            review(1, "alice", "APPROVED", "2024-01-04T00:00:00Z"),
+            review(3, "bob", "APPROVED", "2024-01-02T00:00:00Z"),
+            review(3, "bob", "APPROVED", "2024-01-05T00:00:00Z"),
+        ]);
+        assert_eq!(
+            stats.counts().lgtms,
+            HashMap::from([("alice".to_string(), 2), ("bob".to_string(), 1)])
+        );
+    }
+
+    #[test]
+    fn test_traverse_reactions() {
+        let reaction = |id: u64, login: &str, content: &str, created_at: &str| {
+            serde_json::from_value::<models::reactions::Reaction>(json!({
+                "id": id,
+                "node_id": "",
+                "user": author(login),
+                "content": content,
+                "created_at": created_at,
+            }))
+            .unwrap()
+        };
+        let mut stats = stats(&config(""));
+        stats.traverse_reactions(vec![
+            reaction(1, "alice", "+1", "2024-01-02T00:00:00Z"),
+            reaction(2, "alice", "+1", "2024-01-03T00:00:00Z"),
+            // Only the thumbs-up reactions count.
+            reaction(3, "bob", "heart", "2024-01-02T00:00:00Z"),
+            reaction(4, "bob", "-1", "2024-01-02T00:00:00Z"),
+            // Out of the time range or by a user not allowed.
+            reaction(5, "bob", "+1", "2024-01-09T00:00:00Z"),
+            reaction(6, "carol", "+1", "2024-01-02T00:00:00Z"),
+        ]);
+        assert_eq!(
+            stats.counts().reactions_given,
+            HashMap::from([("alice".to_string(), 2)])
+        );
+    }
+
+    #[test]
+    fn test_exclude_draft_pull_requests() {
+        let mut stats = stats(&config(""));
+        stats.set_draft_pull_requests(HashSet::from([2]));
+        stats.traverse_issues(vec![
+            issue(1, "alice", "2024-01-02T00:00:00Z", true),
+            issue(2, "alice", "2024-01-02T00:00:00Z", true),
+            issue(3, "bob", "2024-01-02T00:00:00Z", false),
+        ]);
+        let counts = stats.counts();
+        assert_eq!(counts.prs, HashMap::from([("alice".to_string(), 1)]));
+        assert_eq!(counts.issues, HashMap::from([("bob".to_string(), 1)]));
+    }
+
+    #[test]
+    fn test_review_comments() {
+        let mut stats = stats(&config(""));
+        stats.traverse_pull_request_comments(vec![
+            pull_request_comment(
+                1,
+                1,
+                "alice",
+                "nit: rename it",
+                "2024-01-02T00:00:00Z",
+                None,
+            ),
+            pull_request_comment(2, 1, "alice", "and this one", "2024-01-02T00:00:00Z", None),
+            // A LGTM comment is a LGTM rather than a review comment.
+            pull_request_comment(3, 1, "bob", "lgtm", "2024-01-03T00:00:00Z", None),
+        ]);
+        stats.traverse_pull_request_reviews(vec![review(
+            1,
+            "alice",
+            "COMMENTED",
+            "2024-01-02T00:00:00Z",
+        )]);
+        let counts = stats.counts();
+        assert_eq!(
+            counts.review_comments,
+            HashMap::from([("alice".to_string(), 2)])
+        );
+        // The inline comments don't count as the reviews they are submitted with.
+        assert_eq!(counts.pr_reviews, HashMap::from([("alice".to_string(), 1)]));
+        assert_eq!(counts.lgtms, HashMap::from([("bob".to_string(), 1)]));
+    }
+
+    #[test]
+    fn test_co_author_login() {
+        assert_eq!(
+            co_author_login("Alice <12345+alice@users.noreply.github.com>"),
+            Some("alice".to_string())
+        );
+        // The legacy noreply emails have no ID.
+        assert_eq!(
+            co_author_login(" Alice Liddell <alice@users.noreply.github.com> "),
+            Some("alice".to_string())
+        );
+        // Only a single-word name is taken as the login.
+        assert_eq!(
+            co_author_login("alice <alice@example.com>"),
+            Some("alice".to_string())
+        );
+        assert_eq!(co_author_login("Alice Liddell <alice@example.com>"), None);
+        assert_eq!(co_author_login(""), None);
+    }
+
+    #[test]
+    fn test_is_co_author() {
+        for co_author in [
+            "alice <alice@example.com>",
+            "Alice <alice@example.com>",
+            "Alice Liddell <12345+alice@users.noreply.github.com>",
+            "Alice Liddell <ALICE@users.noreply.github.com>",
+        ] {
+            assert!(is_co_author(co_author, "alice"), "{}", co_author);
+        }
+        for co_author in [
+            "Alice Liddell <alice@example.com>",
+            "malice <malice@example.com>",
+            "Mallory <12345+malice@users.noreply.github.com>",
+        ] {
+            assert!(!is_co_author(co_author, "alice"), "{}", co_author);
+        }
+    }
+
+    #[test]
+    fn test_credit_coauthors() {
+        let mut pull_request = issue(1, "alice", "2024-01-02T00:00:00Z", true);
+        pull_request.body = Some(
+            "Fix it.\n\nCo-authored-by: Bob <2+bob@users.noreply.github.com>\n\
+             Co-authored-by: alice <alice@example.com>\n\
+             Co-authored-by: carol <carol@example.com>"
+                .to_string(),
+        );
+        let mut credited = stats(&config("credit_coauthors = true"));
+        credited.traverse_issues(vec![pull_request.clone()]);
+        // The author is not credited twice, and the users not allowed are not credited.
+        assert_eq!(
+            credited.counts().prs,
+            HashMap::from([("alice".to_string(), 1), ("bob".to_string(), 1)])
+        );
+
+        let mut uncredited = stats(&config(""));
+        uncredited.traverse_issues(vec![pull_request]);
+        assert_eq!(
+            uncredited.counts().prs,
+            HashMap::from([("alice".to_string(), 1)])
+        );
+    }
+
+    #[test]
+    fn test_count_closes() {
+        let issues = || {
+            vec![
+                issue(1, "bob", "2024-01-02T00:00:00Z", false),
+                issue(2, "bob", "2024-01-02T00:00:00Z", true),
+            ]
+        };
+        let events = || {
+            vec![
+                (
+                    1,
+                    timeline_event("closed", "alice", "2024-01-03T00:00:00Z", None),
+                ),
+                (
+                    2,
+                    timeline_event("closed", "alice", "2024-01-03T00:00:00Z", None),
+                ),
+                (
+                    2,
+                    timeline_event("reopened", "bob", "2024-01-04T00:00:00Z", None),
+                ),
+                (
+                    2,
+                    timeline_event("closed", "bob", "2024-01-05T00:00:00Z", None),
+                ),
+                // Out of the time range.
+                (
+                    1,
+                    timeline_event("closed", "bob", "2024-01-09T00:00:00Z", None),
+                ),
+            ]
+        };
+
+        let mut counted = stats(&config("count_closes = true"));
+        assert!(counted.needs_timeline_events());
+        counted.traverse_issues(issues());
+        counted.traverse_timeline_events(events());
+        let counts = counted.counts();
+        assert_eq!(
+            counts.issues_closed,
+            HashMap::from([("alice".to_string(), 1)])
+        );
+        // Each close of a reopened PR counts.
+        assert_eq!(
+            counts.prs_closed,
+            HashMap::from([("alice".to_string(), 1), ("bob".to_string(), 1)])
+        );
+
+        let mut uncounted = stats(&config(""));
+        uncounted.traverse_issues(issues());
+        uncounted.traverse_timeline_events(events());
+        let counts = uncounted.counts();
+        assert!(counts.issues_closed.is_empty() && counts.prs_closed.is_empty());
+    }
+
+    #[test]
+    fn test_collapse_review_threads() {
+        let comments = || {
+            vec![
+                pull_request_comment(1, 1, "alice", "why?", "2024-01-02T00:00:00Z", None),
+                pull_request_comment(2, 1, "bob", "because", "2024-01-02T01:00:00Z", Some(1)),
+                pull_request_comment(3, 1, "alice", "I see", "2024-01-02T02:00:00Z", Some(1)),
+                pull_request_comment(4, 1, "alice", "but...", "2024-01-02T03:00:00Z", Some(1)),
+                pull_request_comment(5, 1, "alice", "and here", "2024-01-03T00:00:00Z", None),
+            ]
+        };
+
+        // Each user counts once per thread.
+        let mut collapsed = stats(&config("collapse_review_threads = true"));
+        collapsed.traverse_pull_request_comments(comments());
+        assert_eq!(
+            collapsed.counts().review_comments,
+            HashMap::from([("alice".to_string(), 2), ("bob".to_string(), 1)])
+        );
+
+        let mut uncollapsed = stats(&config(""));
+        uncollapsed.traverse_pull_request_comments(comments());
+        assert_eq!(
+            uncollapsed.counts().review_comments,
+            HashMap::from([("alice".to_string(), 4), ("bob".to_string(), 1)])
+        );
+    }
+
+    #[test]
+    fn test_user_aliases() {
+        let mut stats = stats(&config(
+            "user_aliases = { \"alice-old\" = \"alice\", \"carol\" = \"bob\" }",
+        ));
+        stats.traverse_issues(vec![
+            issue(1, "alice-old", "2024-01-02T00:00:00Z", false),
+            issue(2, "alice", "2024-01-03T00:00:00Z", false),
+            issue(3, "carol", "2024-01-03T00:00:00Z", true),
+        ]);
+        stats.traverse_pull_request_comments(vec![pull_request_comment(
+            1,
+            3,
+            "alice-old",
+            "lgtm",
+            "2024-01-03T00:00:00Z",
+            None,
+        )]);
+        // Already LGTMed under the old login.
+        stats.traverse_pull_request_reviews(vec![review(
+            3,
+            "alice",
+            "APPROVED",
+            "2024-01-04T00:00:00Z",
+        )]);
+        let counts = stats.counts();
+        // The activities under the aliases are counted as the canonical logins, even if
+        // the aliases are not in the allowed users.
+        assert_eq!(counts.issues, HashMap::from([("alice".to_string(), 2)]));
+        assert_eq!(counts.prs, HashMap::from([("bob".to_string(), 1)]));
+        assert_eq!(counts.lgtms, HashMap::from([("alice".to_string(), 1)]));
+    }
+
+    #[test]
+    fn test_skip_duplicated_issues() {
+        let mut stats = stats(&config(""));
+        stats.traverse_issues(vec![
+            issue(1, "alice", "2024-01-02T00:00:00Z", false),
+            issue(2, "bob", "2024-01-03T00:00:00Z", true),
+            issue(1, "alice", "2024-01-02T00:00:00Z", false),
+        ]);
+        // Listed again by another page, e.g. a retried one.
+        stats.traverse_issues(vec![issue(2, "bob", "2024-01-03T00:00:00Z", true)]);
+        let counts = stats.counts();
+        assert_eq!(counts.issues, HashMap::from([("alice".to_string(), 1)]));
+        assert_eq!(counts.prs, HashMap::from([("bob".to_string(), 1)]));
+    }
+
+    #[test]
+    fn test_review_coverage() {
+        let mut stats = stats(&config(""));
+        stats.traverse_issues(vec![
+            issue(1, "bob", "2024-01-02T00:00:00Z", true),
+            issue(2, "bob", "2024-01-02T00:00:00Z", true),
+            issue(3, "bob", "2024-01-03T00:00:00Z", true),
+            issue(4, "bob", "2024-01-03T00:00:00Z", true),
+            // Created before the time range.
+            issue(5, "bob", "2023-12-31T00:00:00Z", true),
+        ]);
+        stats.traverse_pull_request_reviews(vec![
+            review(1, "alice", "APPROVED", "2024-01-04T00:00:00Z"),
+            review(2, "alice", "COMMENTED", "2024-01-04T00:00:00Z"),
+            review(3, "alice", "CHANGES_REQUESTED", "2024-01-04T00:00:00Z"),
+            // Neither the author nor the users not allowed cover a PR.
+            review(4, "bob", "COMMENTED", "2024-01-04T00:00:00Z"),
+            review(4, "carol", "APPROVED", "2024-01-04T00:00:00Z"),
+            review(5, "alice", "APPROVED", "2024-01-04T00:00:00Z"),
+        ]);
+        let coverage = stats.review_coverage();
+        assert_eq!(
+            coverage,
+            BTreeMap::from([(
+                REPO.to_string(),
+                ReviewCoverage {
+                    reviewed_prs: 3,
+                    total_prs: 4,
+                }
+            )])
+        );
+        assert_eq!(coverage[REPO].ratio(), 0.75);
+        assert_eq!(ReviewCoverage::default().ratio(), 0.0);
+    }
+
+    #[test]
+    fn test_label_filter() {
+        let mut stats = stats(&config(
+            "include_labels = [\"needs-review\"]\nexclude_labels = [\"wontfix\"]",
+        ));
+        stats.traverse_issues(vec![
+            labeled(
+                issue(1, "alice", "2024-01-02T00:00:00Z", false),
+                &["needs-review", "bug"],
+            ),
+            issue(2, "alice", "2024-01-02T00:00:00Z", false),
+            labeled(
+                issue(3, "alice", "2024-01-02T00:00:00Z", false),
+                &["needs-review", "wontfix"],
+            ),
+            labeled(
+                issue(4, "bob", "2024-01-03T00:00:00Z", true),
+                &["needs-review"],
+            ),
+            labeled(issue(5, "bob", "2024-01-03T00:00:00Z", true), &["bug"]),
+        ]);
+        // The comments and reviews follow the labels of their issues and PRs.
+        stats.traverse_issue_comments(vec![
+            issue_comment(1, 1, "bob", "looks good", "2024-01-04T00:00:00Z"),
+            issue_comment(2, 2, "bob", "looks good", "2024-01-04T00:00:00Z"),
+            issue_comment(3, 3, "bob", "looks good", "2024-01-04T00:00:00Z"),
+        ]);
+        stats.traverse_pull_request_reviews(vec![
+            review(4, "alice", "APPROVED", "2024-01-04T00:00:00Z"),
+            review(5, "alice", "APPROVED", "2024-01-04T00:00:00Z"),
+        ]);
+        let counts = stats.counts();
+        assert_eq!(counts.issues, HashMap::from([("alice".to_string(), 1)]));
+        assert_eq!(counts.prs, HashMap::from([("bob".to_string(), 1)]));
+        assert_eq!(
+            counts.issue_comments,
+            HashMap::from([("bob".to_string(), 1)])
+        );
+        assert_eq!(counts.pr_reviews, HashMap::from([("alice".to_string(), 1)]));
+    }
+
+    #[test]
+    fn test_lgtm_weight() {
+        // A LGTM weighs at least 1 however small the PR is.
+        assert_eq!(lgtm_weight(0), 1.0);
+        assert_eq!(lgtm_weight(1), 1.0);
+        assert_eq!(lgtm_weight(2000), 2001f64.ln());
+        assert!(lgtm_weight(100) < lgtm_weight(1000));
+    }
+
+    #[test]
+    fn test_traverse_lgtm_pull_request_sizes() {
+        let mut stats = stats(&config(""));
+        stats.traverse_pull_request_reviews(vec![
+            review(1, "alice", "APPROVED", "2024-01-02T00:00:00Z"),
+            review(2, "alice", "APPROVED", "2024-01-02T00:00:00Z"),
+            // The details of the PR are missing, which weighs 1.
+            review(3, "bob", "APPROVED", "2024-01-02T00:00:00Z"),
+        ]);
+        let mut lgtm_pull_requests = stats.lgtm_pull_requests();
+        lgtm_pull_requests.sort();
+        assert_eq!(lgtm_pull_requests, vec![1, 2, 3]);
+        assert!(stats.weighted_lgtms().is_empty());
+
+        stats.traverse_lgtm_pull_request_sizes(vec![pull_request(1, 2000), pull_request(2, 1)]);
+        let weighted_lgtms = stats.weighted_lgtms();
+        assert_eq!(
+            weighted_lgtms,
+            HashMap::from([
+                ("alice".to_string(), lgtm_weight(2000) + 1.0),
+                ("bob".to_string(), 1.0)
+            ])
+        );
+        // The LGTMs themselves are not changed.
+        assert_eq!(stats.counts().lgtms.get("alice"), Some(&2));
+        // The weighted LGTMs are rolled up by the teams.
+        let counts = stats.counts();
+        assert_eq!(counts.weighted_lgtms, weighted_lgtms);
+        let user_teams = HashMap::from([
+            ("alice".to_string(), "storage".to_string()),
+            ("bob".to_string(), "storage".to_string()),
+        ]);
+        assert_eq!(
+            counts.group_by_team(&user_teams).weighted_lgtms,
+            HashMap::from([("storage".to_string(), lgtm_weight(2000) + 2.0)])
+        );
+    }
+
+    #[test]
+    fn test_repos_touched() {
+        let config = config("");
+        let mut tikv = stats(&config);
+        tikv.traverse_issues(vec![
+            issue(1, "alice", "2024-01-02T00:00:00Z", false),
+            issue(2, "alice", "2024-01-03T00:00:00Z", true),
+            issue(3, "bob", "2024-01-03T00:00:00Z", false),
+        ]);
+        let mut pd = Stats::new(
+            &config,
+            "tikv/pd",
+            time("2024-01-01T00:00:00Z"),
+            time("2024-01-08T00:00:00Z"),
+        );
+        pd.traverse_issues(vec![issue(1, "alice", "2024-01-04T00:00:00Z", false)]);
+
+        // The repos are counted distinctly across the reduced stats.
+        let merged = Stats::reduce([tikv, pd]).unwrap();
+        let counts = merged.counts();
+        assert_eq!(
+            counts.repos_touched,
+            HashMap::from([("alice".to_string(), 2), ("bob".to_string(), 1)])
+        );
+        assert_eq!(merged.active_repos(), 2);
+        // The repos of the members may overlap, so the teams have none.
+        assert!(counts
+            .group_by_team(&HashMap::new())
+            .repos_touched
+            .is_empty());
+    }
+
+    #[test]
+    fn test_avg_comment_length() {
+        let mut stats = stats(&config(""));
+        stats.traverse_issues(vec![issue(1, "bob", "2024-01-02T00:00:00Z", false)]);
+        stats.traverse_issue_comments(vec![
+            // The surrounding whitespaces are not counted.
+            issue_comment(1, 1, "alice", " hello ", "2024-01-02T01:00:00Z"),
+            issue_comment(2, 1, "alice", "hello world", "2024-01-02T02:00:00Z"),
+        ]);
+        let avg_comment_length = stats.avg_comment_length();
+        assert_eq!(avg_comment_length.get("alice"), Some(&8.0));
+        // Bob is active without any comment, which has no average rather than NaN.
+        assert_eq!(avg_comment_length.get("bob"), None);
+
+        let counts = stats.counts();
+        assert_eq!(counts.avg_comment_length, avg_comment_length);
+        let display_names = HashMap::from([("alice".to_string(), "Alice".to_string())]);
+        assert_eq!(
+            counts.with_display_names(&display_names).avg_comment_length,
+            HashMap::from([("Alice".to_string(), 8.0)])
+        );
+        assert!(counts
+            .group_by_team(&HashMap::new())
+            .avg_comment_length
+            .is_empty());
+    }
+
+    #[test]
+    fn test_track_all_users() {
+        let issues = || {
+            vec![
+                issue(1, "alice", "2024-01-02T00:00:00Z", false),
+                issue(2, "carol", "2024-01-02T00:00:00Z", false),
+            ]
+        };
+        let issue_count = |stats: &Stats, user: &str| {
+            stats
+                .user_metrics()
+                .get(user)
+                .map_or(0, |metrics| metrics.metric(Metric::Issues))
+        };
+
+        let mut listed = stats(&config(""));
+        listed.traverse_issues(issues());
+        assert_eq!(issue_count(&listed, "alice"), 1);
+        assert_eq!(issue_count(&listed, "carol"), 0);
+
+        let mut all = stats(&config("track_all_users = true"));
+        all.traverse_issues(issues());
+        assert_eq!(issue_count(&all, "alice"), 1);
+        assert_eq!(issue_count(&all, "carol"), 1);
+
+        // The users list can be omitted when tracking all users.
+        let config = Config::from_reader(
+            "[review]\ntrack_all_users = true\n".as_bytes(),
+            ConfigFormat::Toml,
+        )
+        .unwrap();
+        let mut all = stats(&config);
+        all.traverse_issues(issues());
+        assert_eq!(issue_count(&all, "carol"), 1);
+    }
+
+    #[test]
+    fn test_time_to_first_response_in_business_hours() {
+        let mut stats = stats(&config(
+            "business_hours = [9, 18]\nholidays = [\"2024-01-03\"]",
+        ));
+        stats.traverse_issues(vec![
+            // Created on Tuesday evening and responded to on Thursday morning after the
+            // holiday on Wednesday.
+            issue(1, "alice", "2024-01-02T17:00:00Z", true),
+            // Created and responded to within the same business day.
+            issue(2, "alice", "2024-01-05T10:00:00Z", true),
+        ]);
+        stats.traverse_pull_request_reviews(vec![
+            review(1, "bob", "APPROVED", "2024-01-04T10:30:00Z"),
+            review(2, "bob", "COMMENTED", "2024-01-05T12:00:00Z"),
+        ]);
+        let response_time = stats.time_to_first_response().unwrap();
+        assert_eq!(response_time.responded_prs, 2);
+        assert_eq!(response_time.min, Duration::hours(2));
+        assert_eq!(response_time.max, Duration::minutes(150));
+    }
+
+    fn is_lgtm(lgtm: &str, comment: &str) -> bool {
+        LgtmComment::new(lgtm)
+            .unwrap()
+            .matches(&words(comment), &comment.to_lowercase())
+    }
+
+    #[test]
+    fn test_lgtm_comment_words() {
+        assert_eq!(
+            LgtmComment::new(" Looks good to me "),
+            Some(LgtmComment::Words(vec![
+                "looks".to_string(),
+                "good".to_string(),
+                "to".to_string(),
+                "me".to_string()
+            ]))
+        );
+        assert!(is_lgtm("lgtm", "LGTM!"));
+        assert!(is_lgtm("lgtm", "/lgtm"));
+        assert!(is_lgtm("lgtm", "Thanks, lgtm."));
+        assert!(!is_lgtm("lgtm", "nolgtm"));
+        assert!(!is_lgtm("lgtm", "lgtms"));
+        assert!(is_lgtm("looks good to me", "Looks  good to me!"));
+        assert!(!is_lgtm("looks good to me", "looks good to them"));
+    }
+
+    #[test]
+    fn test_lgtm_comment_symbols() {
+        assert_eq!(
+            LgtmComment::new(":+1:"),
+            Some(LgtmComment::Text(":+1:".to_string()))
+        );
+        assert!(is_lgtm(":+1:", "Nice :+1:"));
+        assert!(!is_lgtm(":+1:", "Fix 1 more bug"));
+        assert!(is_lgtm("👍", "👍"));
+        assert!(is_lgtm("👍", "LGTM 👍👍"));
+        assert!(!is_lgtm("👍", "👎"));
+        assert_eq!(LgtmComment::new("  "), None);
+    }
+}
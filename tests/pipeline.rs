@@ -0,0 +1,331 @@
+use std::{collections::HashMap, fs, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use gh_overseer::config::{Config, ConfigFormat};
+use gh_overseer::{RunOptions, Stats};
+
+const REPO: &str = "tikv/tikv";
+
+fn time(time: &str) -> DateTime<Utc> {
+    time.parse().unwrap()
+}
+
+fn config(access: &str) -> Config {
+    let config = format!(
+        "[access]\n{}\n[review]\nusers = [\"alice\", \"bob\"]\nrepos = [\"{}\"]\nlgtm_comments = [\"lgtm\"]\n",
+        access, REPO
+    );
+    Config::from_reader(config.as_bytes(), ConfigFormat::Toml).unwrap()
+}
+
+fn author(login: &str) -> Value {
+    let url = format!("https://api.github.com/users/{}", login);
+    json!({
+        "login": login,
+        "id": 1,
+        "node_id": "",
+        "avatar_url": url,
+        "gravatar_id": "",
+        "url": url,
+        "html_url": url,
+        "followers_url": url,
+        "following_url": url,
+        "gists_url": url,
+        "starred_url": url,
+        "subscriptions_url": url,
+        "organizations_url": url,
+        "repos_url": url,
+        "events_url": url,
+        "received_events_url": url,
+        "type": "User",
+        "site_admin": false,
+    })
+}
+
+fn issue(number: u64, login: &str, created_at: &str, closed_at: Option<&str>, pr: bool) -> Value {
+    let url = format!("https://api.github.com/repos/{}/issues/{}", REPO, number);
+    let mut issue = json!({
+        "id": number,
+        "node_id": "",
+        "url": url,
+        "repository_url": url,
+        "labels_url": url,
+        "comments_url": url,
+        "events_url": url,
+        "html_url": format!("https://github.com/{}/issues/{}", REPO, number),
+        "number": number,
+        "state": if closed_at.is_some() { "closed" } else { "open" },
+        "title": "",
+        "user": author(login),
+        "labels": [],
+        "assignees": [],
+        "author_association": "MEMBER",
+        "locked": false,
+        "comments": 0,
+        "created_at": created_at,
+        "updated_at": closed_at.unwrap_or(created_at),
+        "closed_at": closed_at,
+    });
+    if pr {
+        issue["pull_request"] = json!({
+            "url": url,
+            "html_url": url,
+            "diff_url": url,
+            "patch_url": url,
+        });
+    }
+    issue
+}
+
+fn pull_request(number: u64, login: &str, lines_changed: u64, merged_at: &str) -> Value {
+    let branch = json!({ "ref": "main", "sha": "" });
+    json!({
+        "url": format!("https://api.github.com/repos/{}/pulls/{}", REPO, number),
+        "id": number,
+        "number": number,
+        "locked": false,
+        "maintainer_can_modify": false,
+        "head": branch,
+        "base": branch,
+        "user": author(login),
+        "additions": lines_changed,
+        "deletions": 0,
+        "merged_at": merged_at,
+    })
+}
+
+fn issue_comment(id: u64, login: &str, body: &str, created_at: &str) -> Value {
+    let url = format!(
+        "https://api.github.com/repos/{}/issues/comments/{}",
+        REPO, id
+    );
+    json!({
+        "id": id,
+        "node_id": "",
+        "url": url,
+        "html_url": url,
+        "body": body,
+        "user": author(login),
+        "created_at": created_at,
+        "updated_at": created_at,
+    })
+}
+
+fn review(id: u64, number: u64, login: &str, state: &str, submitted_at: &str) -> Value {
+    json!({
+        "id": id,
+        "node_id": "",
+        "html_url": format!("https://github.com/{}/pull/{}#pullrequestreview-{}", REPO, number, id),
+        "user": author(login),
+        "body": "",
+        "state": state,
+        "submitted_at": submitted_at,
+    })
+}
+
+/// The listings of the repo keyed by their REST paths: an issue of alice commented by
+/// bob, and a PR of bob approved by alice and merged within the time range.
+fn listings() -> HashMap<String, Value> {
+    let repo = format!("/repos/{}", REPO);
+    HashMap::from([
+        (
+            format!("{}/issues", repo),
+            json!([
+                issue(1, "alice", "2024-01-02T00:00:00Z", None, false),
+                issue(
+                    2,
+                    "bob",
+                    "2024-01-03T00:00:00Z",
+                    Some("2024-01-05T00:00:00Z"),
+                    true
+                ),
+            ]),
+        ),
+        (
+            format!("{}/issues/1/comments", repo),
+            json!([issue_comment(
+                1,
+                "bob",
+                "looks good",
+                "2024-01-02T01:00:00Z"
+            )]),
+        ),
+        (format!("{}/pulls/comments", repo), json!([])),
+        (
+            format!("{}/pulls/2/reviews", repo),
+            json!([review(1, 2, "alice", "APPROVED", "2024-01-04T12:00:00Z")]),
+        ),
+        (
+            format!("{}/pulls/2", repo),
+            pull_request(2, "bob", 42, "2024-01-05T00:00:00Z"),
+        ),
+    ])
+}
+
+/// Serve the JSON bodies by the paths of the requests like GitHub does, regardless of the
+/// queries, and 404 for the others. Returns the base URL of the server.
+async fn serve(routes: HashMap<String, Value>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let routes = Arc::new(routes);
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let routes = routes.clone();
+            tokio::spawn(async move {
+                // Only the GET requests are sent, which end with the headers.
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let target = request.split_whitespace().nth(1).unwrap_or("/");
+                let path = target.split('?').next().unwrap_or(target);
+                let (status, body) = match routes.get(path) {
+                    Some(body) => ("200 OK", body.to_string()),
+                    None => (
+                        "404 Not Found",
+                        json!({ "message": "Not Found" }).to_string(),
+                    ),
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+            });
+        }
+    });
+    format!("http://{}", addr)
+}
+
+fn assert_counted(stats: &Stats) {
+    assert!(stats.failed_endpoints().is_empty());
+    let counts = stats.counts();
+    assert_eq!(counts.issues.get("alice"), Some(&1));
+    assert_eq!(counts.prs.get("bob"), Some(&1));
+    assert_eq!(counts.issue_comments.get("bob"), Some(&1));
+    assert_eq!(counts.pr_reviews.get("alice"), Some(&1));
+    assert_eq!(counts.prs_merged.get("bob"), Some(&1));
+    // 42 lines changed is M by the default thresholds.
+    assert_eq!(counts.pr_sizes.get("bob"), Some(&[0, 0, 1, 0, 0]));
+}
+
+#[tokio::test]
+async fn test_run_against_mock_github() {
+    let base_url = serve(listings()).await;
+    let config = config(&format!(
+        "github_personal_token = \"token\"\ngithub_base_url = \"{}\"",
+        base_url
+    ));
+    let options = RunOptions {
+        pr_sizes: true,
+        prs_merged: true,
+        ..RunOptions::default()
+    };
+    let (start_time, end_time) = (time("2024-01-01T00:00:00Z"), time("2024-01-08T00:00:00Z"));
+    let stats = gh_overseer::run_with(&config, start_time, end_time, &options)
+        .await
+        .unwrap();
+    assert_counted(&stats);
+    assert_eq!(stats.time_range(), (start_time, end_time));
+
+    // The stats of each bucket are counted by the same pipeline.
+    let octocrab = gh_overseer::build_client(&config).unwrap();
+    let options = RunOptions {
+        buckets: vec![
+            (start_time, time("2024-01-04T00:00:00Z")),
+            (time("2024-01-04T00:00:00Z"), end_time),
+        ],
+        ..options
+    };
+    let (stats, bucket_stats) = gh_overseer::repo_stats(
+        octocrab,
+        &config,
+        REPO.to_string(),
+        start_time,
+        end_time,
+        &options,
+    )
+    .await
+    .unwrap();
+    assert_counted(&stats);
+    assert_eq!(bucket_stats.len(), 2);
+    assert_eq!(bucket_stats[0].counts().issues.get("alice"), Some(&1));
+    assert_eq!(bucket_stats[0].counts().pr_reviews.get("alice"), None);
+    assert_eq!(bucket_stats[1].counts().pr_reviews.get("alice"), Some(&1));
+}
+
+#[tokio::test]
+async fn test_run_with_fixtures() {
+    let fixtures_dir =
+        std::env::temp_dir().join(format!("gh-overseer-test-{}-fixtures", std::process::id()));
+    let repo_dir = fixtures_dir.join(REPO);
+    fs::create_dir_all(&repo_dir).unwrap();
+    let mut listings = listings();
+    let repo = format!("/repos/{}", REPO);
+    let mut issues = listings.remove(&format!("{}/issues", repo)).unwrap();
+    // A PR of alice targeting another base branch, which is filtered out.
+    issues
+        .as_array_mut()
+        .unwrap()
+        .push(issue(3, "alice", "2024-01-03T00:00:00Z", None, true));
+    let pull_request = listings.remove(&format!("{}/pulls/2", repo)).unwrap();
+    // The PRs of the base branch and the details of the PRs are read from their own
+    // fixtures, even though both are listed from the pulls API.
+    for (listing, items) in [
+        ("issues", issues),
+        (
+            "issue_comments",
+            listings
+                .remove(&format!("{}/issues/1/comments", repo))
+                .unwrap(),
+        ),
+        (
+            "pull_request_reviews",
+            listings
+                .remove(&format!("{}/pulls/2/reviews", repo))
+                .unwrap(),
+        ),
+        ("base_branch_pull_requests", json!([pull_request])),
+        ("pull_request_details", json!([pull_request])),
+    ] {
+        fs::write(
+            repo_dir.join(format!("{}.json", listing)),
+            items.to_string(),
+        )
+        .unwrap();
+    }
+
+    let options = RunOptions {
+        base_branch: Some("main".to_string()),
+        pr_sizes: true,
+        prs_merged: true,
+        fixtures_dir: Some(fixtures_dir.clone()),
+        ..RunOptions::default()
+    };
+    let stats = gh_overseer::run_with(
+        &config(""),
+        time("2024-01-01T00:00:00Z"),
+        time("2024-01-08T00:00:00Z"),
+        &options,
+    )
+    .await
+    .unwrap();
+    assert_counted(&stats);
+    assert_eq!(stats.counts().prs.get("alice"), None);
+    fs::remove_dir_all(&fixtures_dir).unwrap();
+}